@@ -6,7 +6,7 @@ use std::{
 use criterion::{criterion_group, criterion_main, Criterion, Throughput};
 use rand::SeedableRng;
 use rand_chacha::ChaChaRng;
-use veil::{Digest, SecretKey};
+use veil::{DecryptLimits, Digest, DigestTree, EncryptOptions, Kdf, SecretKey};
 
 const LENS: &[(u64, &str)] = &[(0, "0B"), (1024 * 1024, "1MiB"), (10 * 1024 * 1024, "10MiB")];
 
@@ -24,7 +24,55 @@ fn encrypt(c: &mut Criterion) {
                     io::repeat(0).take(len),
                     io::sink(),
                     &[pk_b.public_key()],
-                    None,
+                    EncryptOptions::default(),
+                )
+                .unwrap()
+            });
+        });
+    }
+    g.finish();
+}
+
+fn encrypt_slice(c: &mut Criterion) {
+    let mut g = c.benchmark_group("encrypt-slice");
+    for &(len, id) in LENS {
+        g.throughput(Throughput::Bytes(len));
+        g.bench_function(id, |b| {
+            let mut rng = ChaChaRng::seed_from_u64(0xDEADBEEF);
+            let pk_a = SecretKey::random(&mut rng);
+            let pk_b = SecretKey::random(&mut rng);
+            let plaintext = vec![0u8; len as usize];
+            b.iter(|| {
+                pk_a.encrypt_slice(
+                    &mut rng,
+                    &plaintext,
+                    io::sink(),
+                    &[pk_b.public_key()],
+                    EncryptOptions::default(),
+                )
+                .unwrap()
+            });
+        });
+    }
+    g.finish();
+}
+
+fn encrypt_receivers(c: &mut Criterion) {
+    let mut g = c.benchmark_group("encrypt-receivers");
+    for n in [1, 10, 100, 1_000] {
+        g.throughput(Throughput::Elements(n as u64));
+        g.bench_function(n.to_string(), |b| {
+            let mut rng = ChaChaRng::seed_from_u64(0xDEADBEEF);
+            let pk_a = SecretKey::random(&mut rng);
+            let receivers =
+                (0..n).map(|_| SecretKey::random(&mut rng).public_key()).collect::<Vec<_>>();
+            b.iter(|| {
+                pk_a.encrypt(
+                    &mut rng,
+                    io::repeat(0).take(0),
+                    io::sink(),
+                    &receivers,
+                    EncryptOptions::default(),
                 )
                 .unwrap()
             });
@@ -47,7 +95,7 @@ fn decrypt(c: &mut Criterion) {
                 io::repeat(0).take(len),
                 &mut ciphertext,
                 &[pk_b.public_key()],
-                None,
+                EncryptOptions::default(),
             )
             .unwrap();
             let ciphertext = Cursor::new(ciphertext.into_inner());
@@ -57,6 +105,32 @@ fn decrypt(c: &mut Criterion) {
     g.finish();
 }
 
+fn decrypt_slice(c: &mut Criterion) {
+    let mut g = c.benchmark_group("decrypt-slice");
+    for &(len, id) in LENS {
+        g.throughput(Throughput::Bytes(len));
+        g.bench_function(id, |b| {
+            let mut rng = ChaChaRng::seed_from_u64(0xDEADBEEF);
+            let pk_a = SecretKey::random(&mut rng);
+            let pk_b = SecretKey::random(&mut rng);
+            let mut ciphertext = Vec::new();
+            pk_a.encrypt(
+                &mut rng,
+                io::repeat(0).take(len),
+                &mut ciphertext,
+                &[pk_b.public_key()],
+                EncryptOptions::default(),
+            )
+            .unwrap();
+            let limits = DecryptLimits::default();
+            b.iter(|| {
+                pk_b.decrypt_slice(&ciphertext, io::sink(), &pk_a.public_key(), &limits).unwrap()
+            });
+        });
+    }
+    g.finish();
+}
+
 fn sign(c: &mut Criterion) {
     let mut g = c.benchmark_group("sign");
     for &(len, id) in LENS {
@@ -70,6 +144,20 @@ fn sign(c: &mut Criterion) {
     g.finish();
 }
 
+fn sign_slice(c: &mut Criterion) {
+    let mut g = c.benchmark_group("sign-slice");
+    for &(len, id) in LENS {
+        g.throughput(Throughput::Bytes(len));
+        g.bench_function(id, |b| {
+            let mut rng = ChaChaRng::seed_from_u64(0xDEADBEEF);
+            let pk = SecretKey::random(&mut rng);
+            let message = vec![0u8; len as usize];
+            b.iter(|| pk.sign_slice(&mut rng, &message));
+        });
+    }
+    g.finish();
+}
+
 fn verify(c: &mut Criterion) {
     let mut g = c.benchmark_group("verify");
     for &(len, id) in LENS {
@@ -95,6 +183,30 @@ fn digest(c: &mut Criterion) {
     g.finish();
 }
 
+fn digest_slice(c: &mut Criterion) {
+    let mut g = c.benchmark_group("digest-slice");
+    for &(len, id) in LENS {
+        g.throughput(Throughput::Bytes(len));
+        g.bench_function(id, |b| {
+            let message = vec![0u8; len as usize];
+            b.iter(|| Digest::from_slice(&[""; 0], &message));
+        });
+    }
+    g.finish();
+}
+
+fn digest_tree(c: &mut Criterion) {
+    let mut g = c.benchmark_group("digest-tree");
+    for &(len, id) in LENS {
+        g.throughput(Throughput::Bytes(len));
+        g.bench_function(id, |b| {
+            let message = vec![0u8; len as usize];
+            b.iter(|| DigestTree::from_slice(&message).digest(&[""; 0]));
+        });
+    }
+    g.finish();
+}
+
 fn pbenc(c: &mut Criterion) {
     let mut g = c.benchmark_group("pbenc");
     for time in [1, 2, 4, 8] {
@@ -102,12 +214,43 @@ fn pbenc(c: &mut Criterion) {
             g.bench_function(format!("t={time}/m={memory}/p=1"), |b| {
                 let mut rng = ChaChaRng::seed_from_u64(0xDEADBEEF);
                 let pk = SecretKey::random(&mut rng);
-                b.iter(|| pk.store(io::sink(), &mut rng, b"passphrase", time, memory, 1));
+                let kdf = Kdf::Balloon { time_cost: time, memory_cost: memory, parallelism: 1 };
+                b.iter(|| pk.store(io::sink(), &mut rng, b"passphrase", kdf));
+            });
+        }
+    }
+    g.finish();
+}
+
+fn argon2id(c: &mut Criterion) {
+    let mut g = c.benchmark_group("argon2id");
+    for time in [0, 1, 2] {
+        for memory in [4, 8, 12] {
+            g.bench_function(format!("t={time}/m={memory}/p=0"), |b| {
+                let mut rng = ChaChaRng::seed_from_u64(0xDEADBEEF);
+                let pk = SecretKey::random(&mut rng);
+                let kdf = Kdf::Argon2id { time_cost: time, memory_cost: memory, parallelism: 0 };
+                b.iter(|| pk.store(io::sink(), &mut rng, b"passphrase", kdf));
             });
         }
     }
     g.finish();
 }
 
-criterion_group!(benches, encrypt, decrypt, sign, verify, digest, pbenc);
+criterion_group!(
+    benches,
+    encrypt,
+    encrypt_slice,
+    encrypt_receivers,
+    decrypt,
+    decrypt_slice,
+    sign,
+    sign_slice,
+    verify,
+    digest,
+    digest_slice,
+    digest_tree,
+    pbenc,
+    argon2id
+);
 criterion_main!(benches);
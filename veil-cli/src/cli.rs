@@ -1,18 +1,27 @@
 use std::{
     error::Error,
-    fs::File,
+    fs::{self, File},
     io::{self, IsTerminal, Read, Write},
-    path::{Path, PathBuf},
+    iter,
+    path::{Component, Path, PathBuf},
     process,
+    time::{Duration, UNIX_EPOCH},
 };
 
-use clap::{ArgAction, CommandFactory, Parser, Subcommand, ValueHint};
+use clap::{
+    builder::{BoolishValueParser, PossibleValuesParser, TypedValueParser},
+    ArgAction, ArgGroup, Args, CommandFactory, Parser, Subcommand, ValueEnum, ValueHint,
+};
 use clap_complete::{generate_to, Shell};
 use console::Term;
+use memmap2::Mmap;
 use rand::rngs::OsRng;
 use thiserror::Error;
 use veil::{
-    DecryptError, Digest, ParsePublicKeyError, ParseSignatureError, PublicKey, SecretKey, Signature,
+    CombineSharesError, DecryptError, DecryptLimits, DecryptionSecretKey, Digest, DigestTree,
+    EncryptError, EncryptOptions, FileMetadata, Kdf, Mac, ParseMnemonicError, ParsePublicKeyError,
+    ParseShareError, ParseSignatureError, PublicKey, ReceiversBucket, SecretKey, Signature,
+    SigningSecretKey, VerifyingPublicKey, DEFAULT_BLOCK_LEN, MAX_BLOCK_LEN, MIN_BLOCK_LEN,
 };
 
 fn main() {
@@ -22,9 +31,12 @@ fn main() {
         Cmd::PublicKey(cmd) => cmd.run(),
         Cmd::Encrypt(cmd) => cmd.run(),
         Cmd::Decrypt(cmd) => cmd.run(),
+        Cmd::EncryptDir(cmd) => cmd.run(),
+        Cmd::DecryptDir(cmd) => cmd.run(),
         Cmd::Sign(cmd) => cmd.run(),
         Cmd::Verify(cmd) => cmd.run(),
         Cmd::Digest(cmd) => cmd.run(),
+        Cmd::Mac(cmd) => cmd.run(),
         Cmd::Complete(cmd) => cmd.run(),
     } {
         e.print();
@@ -50,19 +62,296 @@ enum Cmd {
     PublicKey(PublicKeyArgs),
     Encrypt(EncryptArgs),
     Decrypt(DecryptArgs),
+    EncryptDir(EncryptDirArgs),
+    DecryptDir(DecryptDirArgs),
     Sign(SignArgs),
     Verify(VerifyArgs),
     Digest(DigestArgs),
+    Mac(MacArgs),
     Complete(CompleteArgs),
 }
 
 /// Generate a new secret key.
 #[derive(Debug, Parser)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct SecretKeyArgs {
+    #[command(subcommand)]
+    cmd: Option<SecretKeyCmd>,
+
+    /// The path to the encrypted secret key file or '-' for stdout.
+    #[arg(short, long, required = true, value_hint = ValueHint::FilePath, value_name = "PATH")]
+    output: Option<PathBuf>,
+
+    /// Generate a signing-only key instead of a full secret key.
+    ///
+    /// Decryption-only keys can only be extracted from a full secret key, whose public key others
+    /// encrypt messages to.
+    #[arg(
+        long,
+        value_name = "PURPOSE",
+        value_parser = PossibleValuesParser::new(["sign"]).map(|_| Purpose::Sign),
+    )]
+    purpose: Option<Purpose>,
+
+    #[command(flatten)]
+    kdf: KdfArgs,
+
+    #[command(flatten)]
+    passphrase_input: PassphraseInput,
+}
+
+impl Runnable for SecretKeyArgs {
+    fn run(self) -> Result<(), CliError> {
+        match self.cmd {
+            Some(SecretKeyCmd::Split(cmd)) => cmd.run(),
+            Some(SecretKeyCmd::Combine(cmd)) => cmd.run(),
+            Some(SecretKeyCmd::ExportMnemonic(cmd)) => cmd.run(),
+            Some(SecretKeyCmd::ImportMnemonic(cmd)) => cmd.run(),
+            Some(SecretKeyCmd::Derive(cmd)) => cmd.run(),
+            Some(SecretKeyCmd::Extract(cmd)) => cmd.run(),
+            None => {
+                let path = self.output.expect("should be required without a subcommand");
+                match self.purpose {
+                    None => store_secret_key(
+                        SecretKey::random(OsRng),
+                        &self.passphrase_input,
+                        &self.kdf,
+                        path,
+                    ),
+                    Some(Purpose::Sign) => {
+                        store_signing_key(&self.passphrase_input, &self.kdf, path)
+                    }
+                    Some(Purpose::Decrypt) => unreachable!("should only parse signing keys"),
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Subcommand)]
+enum SecretKeyCmd {
+    Split(SplitArgs),
+    Combine(CombineArgs),
+    ExportMnemonic(ExportMnemonicArgs),
+    ImportMnemonic(ImportMnemonicArgs),
+    Derive(DeriveArgs),
+    Extract(ExtractArgs),
+}
+
+/// Split a secret key into shares, a threshold of which can reconstruct it.
+#[derive(Debug, Parser)]
+struct SplitArgs {
+    #[command(flatten)]
+    secret_key: SecretKeyInput,
+
+    /// The total number of shares to create.
+    #[arg(long, value_name = "COUNT", value_parser = clap::value_parser!(u8).range(1..))]
+    shares: u8,
+
+    /// The number of shares required to reconstruct the secret key.
+    #[arg(long, value_name = "COUNT", value_parser = clap::value_parser!(u8).range(1..))]
+    threshold: u8,
+
+    /// The path to the shares file or '-' for stdout.
+    #[arg(short, long, value_hint = ValueHint::FilePath, default_value = "-", value_name = "PATH")]
+    output: PathBuf,
+}
+
+impl Runnable for SplitArgs {
+    fn run(self) -> Result<(), CliError> {
+        if self.threshold > self.shares {
+            return Err(CliError::InvalidThreshold);
+        }
+        let secret_key = self.secret_key.decrypt()?;
+        let mut output = open_output(&self.output, false)?;
+        let shares = secret_key
+            .split(OsRng, self.threshold, self.shares)
+            .map_err(|_| CliError::InvalidThreshold)?;
+        for share in shares {
+            writeln!(output, "{share}").map_err(|e| CliError::WriteIo(e, self.output.clone()))?;
+        }
+        Ok(())
+    }
+}
+
+/// Reconstruct a secret key from shares.
+#[derive(Debug, Parser)]
+struct CombineArgs {
+    /// The paths to files containing shares, one per line.
+    #[arg(
+        short = 'i',
+        long = "input",
+        value_name = "PATH",
+        num_args(1..),
+        required = true,
+        action(ArgAction::Append),
+        value_hint = ValueHint::FilePath,
+    )]
+    inputs: Vec<PathBuf>,
+
+    /// The path to the encrypted secret key file or '-' for stdout.
+    #[arg(short, long, value_hint = ValueHint::FilePath, value_name = "PATH")]
+    output: PathBuf,
+
+    #[command(flatten)]
+    kdf: KdfArgs,
+
+    #[command(flatten)]
+    passphrase_input: PassphraseInput,
+}
+
+impl Runnable for CombineArgs {
+    fn run(self) -> Result<(), CliError> {
+        let mut shares = Vec::new();
+        for path in self.inputs {
+            let mut s = String::new();
+            let mut f = File::open(&path).map_err(|e| CliError::ReadIo(e, path.clone()))?;
+            f.read_to_string(&mut s).map_err(|e| CliError::ReadIo(e, path.clone()))?;
+            for line in s.lines().map(str::trim).filter(|l| !l.is_empty()) {
+                shares.push(line.parse().map_err(|e| CliError::InvalidShare(e, path.clone()))?);
+            }
+        }
+        let secret_key = SecretKey::combine(&shares).map_err(CliError::CombineShares)?;
+        store_secret_key(secret_key, &self.passphrase_input, &self.kdf, self.output)
+    }
+}
+
+/// Export a secret key as a mnemonic phrase for paper backups.
+#[derive(Debug, Parser)]
+struct ExportMnemonicArgs {
+    #[command(flatten)]
+    secret_key: SecretKeyInput,
+
+    /// The path to the mnemonic file or '-' for stdout.
+    #[arg(short, long, value_hint = ValueHint::FilePath, default_value = "-", value_name = "PATH")]
+    output: PathBuf,
+}
+
+impl Runnable for ExportMnemonicArgs {
+    fn run(self) -> Result<(), CliError> {
+        let secret_key = self.secret_key.decrypt()?;
+        let mut output = open_output(&self.output, false)?;
+        let mnemonic = secret_key.to_mnemonic();
+        for line in mnemonic.split(' ').collect::<Vec<_>>().chunks(12) {
+            writeln!(output, "{}", line.join(" "))
+                .map_err(|e| CliError::WriteIo(e, self.output.clone()))?;
+        }
+        Ok(())
+    }
+}
+
+/// Import a secret key from a mnemonic phrase.
+#[derive(Debug, Parser)]
+struct ImportMnemonicArgs {
+    /// The path to the mnemonic file or '-' for stdin.
+    #[arg(short, long, value_hint = ValueHint::FilePath, value_name = "PATH")]
+    input: PathBuf,
+
     /// The path to the encrypted secret key file or '-' for stdout.
     #[arg(short, long, value_hint = ValueHint::FilePath, value_name = "PATH")]
     output: PathBuf,
 
+    #[command(flatten)]
+    kdf: KdfArgs,
+
+    #[command(flatten)]
+    passphrase_input: PassphraseInput,
+}
+
+impl Runnable for ImportMnemonicArgs {
+    fn run(self) -> Result<(), CliError> {
+        let mut mnemonic = String::new();
+        open_input(&self.input)?
+            .read_to_string(&mut mnemonic)
+            .map_err(|e| CliError::ReadIo(e, self.input.clone()))?;
+        let secret_key = SecretKey::from_mnemonic(&mnemonic).map_err(CliError::InvalidMnemonic)?;
+        store_secret_key(secret_key, &self.passphrase_input, &self.kdf, self.output)
+    }
+}
+
+/// Derive a labeled child secret key from a secret key.
+///
+/// The child key is encrypted with the same passphrase as the parent key.
+#[derive(Debug, Parser)]
+struct DeriveArgs {
+    #[command(flatten)]
+    secret_key: SecretKeyInput,
+
+    /// The child key's label, with '/'-separated segments (e.g. 'work/2026').
+    #[arg(short, long)]
+    label: String,
+
+    /// The path to the encrypted child secret key file or '-' for stdout.
+    #[arg(short, long, value_hint = ValueHint::FilePath, value_name = "PATH")]
+    output: PathBuf,
+
+    #[command(flatten)]
+    kdf: KdfArgs,
+}
+
+impl Runnable for DeriveArgs {
+    fn run(self) -> Result<(), CliError> {
+        let kdf = self.kdf.kdf()?;
+        let passphrase = self.secret_key.passphrase_input.read_passphrase()?;
+        let child = self.secret_key.load(&passphrase)?.derive_child(&self.label);
+        let output = open_output(&self.output, true)?;
+        child
+            .store(output, OsRng, &passphrase, kdf)
+            .map_err(|e| CliError::WriteIo(e, self.output))?;
+        Ok(())
+    }
+}
+
+/// Extract a signing-only or decryption-only key from a secret key.
+///
+/// The extracted key is encrypted with the same passphrase as the secret key.
+#[derive(Debug, Parser)]
+struct ExtractArgs {
+    #[command(flatten)]
+    secret_key: SecretKeyInput,
+
+    /// The capability of the extracted key.
+    #[arg(long, value_enum)]
+    purpose: Purpose,
+
+    /// The path to the encrypted extracted key file or '-' for stdout.
+    #[arg(short, long, value_hint = ValueHint::FilePath, value_name = "PATH")]
+    output: PathBuf,
+
+    #[command(flatten)]
+    kdf: KdfArgs,
+}
+
+impl Runnable for ExtractArgs {
+    fn run(self) -> Result<(), CliError> {
+        let kdf = self.kdf.kdf()?;
+        let passphrase = self.secret_key.passphrase_input.read_passphrase()?;
+        let secret_key = self.secret_key.load(&passphrase)?;
+        let output = open_output(&self.output, true)?;
+        match self.purpose {
+            Purpose::Sign => secret_key.signing_key().store(output, OsRng, &passphrase, kdf),
+            Purpose::Decrypt => secret_key.decryption_key().store(output, OsRng, &passphrase, kdf),
+        }
+        .map_err(|e| CliError::WriteIo(e, self.output))?;
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Purpose {
+    /// A signing-only key, which can sign messages but not decrypt them.
+    Sign,
+
+    /// A decryption-only key, which can decrypt messages but not sign them.
+    Decrypt,
+}
+
+#[derive(Debug, Args)]
+struct KdfArgs {
+    /// The key derivation function used to encrypt the secret key.
+    #[arg(long, value_enum, default_value = "balloon")]
+    kdf: KdfAlgorithm,
+
     /// The time cost for encryption (in 2^t iterations).
     #[arg(long, default_value = "8")]
     time_cost: u8,
@@ -74,31 +363,58 @@ struct SecretKeyArgs {
     /// The number of parallel tasks to use (in 2^p threads). [default: log2(NUM_CPU)]
     #[arg(long)]
     parallelism: Option<u8>,
-
-    #[command(flatten)]
-    passphrase_input: PassphraseInput,
 }
 
-impl Runnable for SecretKeyArgs {
-    fn run(self) -> Result<(), CliError> {
-        let passphrase = self.passphrase_input.read_passphrase()?;
-        let output = open_output(&self.output, true)?;
-        let secret_key = SecretKey::random(OsRng);
+impl KdfArgs {
+    fn kdf(&self) -> Result<Kdf, CliError> {
         let p = (num_cpus::get().min(255) as f64).log2() as u8;
-        secret_key
-            .store(
-                output,
-                OsRng,
-                &passphrase,
-                self.time_cost,
-                self.memory_cost,
-                self.parallelism.unwrap_or(p),
-            )
-            .map_err(|e| CliError::WriteIo(e, self.output))?;
-        Ok(())
+        let (time_cost, memory_cost, parallelism) =
+            (self.time_cost, self.memory_cost, self.parallelism.unwrap_or(p));
+        let kdf = match self.kdf {
+            KdfAlgorithm::Balloon => Kdf::Balloon { time_cost, memory_cost, parallelism },
+            KdfAlgorithm::Argon2id => Kdf::Argon2id { time_cost, memory_cost, parallelism },
+        };
+        kdf.is_valid().then_some(kdf).ok_or(CliError::InvalidKdf)
     }
 }
 
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum KdfAlgorithm {
+    /// Balloon hashing over Lockstitch.
+    Balloon,
+
+    /// Argon2id (RFC 9106).
+    Argon2id,
+}
+
+fn store_secret_key(
+    secret_key: SecretKey,
+    passphrase_input: &PassphraseInput,
+    kdf: &KdfArgs,
+    path: PathBuf,
+) -> Result<(), CliError> {
+    let kdf = kdf.kdf()?;
+    let passphrase = passphrase_input.read_passphrase()?;
+    let output = open_output(&path, true)?;
+    secret_key.store(output, OsRng, &passphrase, kdf).map_err(|e| CliError::WriteIo(e, path))?;
+    Ok(())
+}
+
+/// Generates a signing-only key and stores it, encrypted with a passphrase.
+fn store_signing_key(
+    passphrase_input: &PassphraseInput,
+    kdf: &KdfArgs,
+    path: PathBuf,
+) -> Result<(), CliError> {
+    let kdf = kdf.kdf()?;
+    let passphrase = passphrase_input.read_passphrase()?;
+    let output = open_output(&path, true)?;
+    SigningSecretKey::random(OsRng)
+        .store(output, OsRng, &passphrase, kdf)
+        .map_err(|e| CliError::WriteIo(e, path))?;
+    Ok(())
+}
+
 /// Derive a public key from a secret key.
 #[derive(Debug, Parser)]
 struct PublicKeyArgs {
@@ -112,9 +428,18 @@ struct PublicKeyArgs {
 
 impl Runnable for PublicKeyArgs {
     fn run(self) -> Result<(), CliError> {
-        let secret_key = self.secret_key.decrypt()?;
+        let passphrase = self.secret_key.passphrase_input.read_passphrase()?;
+        let public_key = match self.secret_key.load(&passphrase) {
+            Ok(secret_key) => secret_key.public_key().to_string(),
+            // Signing-only keys have a verifying key instead of a full public key.
+            Err(CliError::BadPassphrase(DecryptError::InvalidKeyType)) => self
+                .secret_key
+                .load_with(&passphrase, SigningSecretKey::load)?
+                .public_key()
+                .to_string(),
+            Err(e) => return Err(e),
+        };
         let mut output = open_output(&self.output, false)?;
-        let public_key = secret_key.public_key();
         write!(output, "{public_key}").map_err(|e| CliError::WriteIo(e, self.output))
     }
 }
@@ -145,26 +470,134 @@ struct EncryptArgs {
     )]
     receivers: Vec<PathBuf>,
 
-    /// Add fake receivers.
-    #[arg(long, value_name = "COUNT")]
-    fakes: Option<usize>,
+    #[command(flatten)]
+    options: EncryptOptionsArgs,
+
+    /// Read and write on separate threads from encryption.
+    #[arg(long)]
+    pipelined: bool,
+
+    /// Encrypt the input file's name, size, permissions, and modification time with it.
+    #[arg(long)]
+    metadata: bool,
 }
 
 impl Runnable for EncryptArgs {
     fn run(self) -> Result<(), CliError> {
         let secret_key = self.secret_key.decrypt()?;
-        let input = open_input(&self.input)?;
-        let output = open_output(&self.output, true)?;
         let receivers =
             self.receivers.into_iter().map(open_public_key).collect::<Result<Vec<_>, _>>()?;
-        secret_key.encrypt(OsRng, input, output, &receivers, self.fakes).map_err(|e| match e {
-            veil::EncryptError::ReadIo(e) => CliError::ReadIo(e, self.input),
-            veil::EncryptError::WriteIo(e) => CliError::WriteIo(e, self.output),
+        let metadata = self.metadata.then(|| file_metadata(&self.input)).transpose()?;
+        let options = EncryptOptions { metadata, ..self.options.options() };
+        let res = if self.pipelined {
+            let input = open_pipelined_input(&self.input)?;
+            let output = open_pipelined_output(&self.output)?;
+            secret_key.encrypt_pipelined(OsRng, input, output, &receivers, options)
+        } else {
+            let input = open_mapped_input(&self.input)?;
+            let output = open_output(&self.output, true)?;
+            match input {
+                Input::Mapped(input) => {
+                    secret_key.encrypt_slice(OsRng, &input, output, &receivers, options)
+                }
+                Input::Stream(input) => {
+                    secret_key.encrypt(OsRng, input, output, &receivers, options)
+                }
+            }
+        };
+        res.map_err(|e| match e {
+            EncryptError::ReadIo(e) => CliError::ReadIo(e, self.input),
+            EncryptError::WriteIo(e) => CliError::WriteIo(e, self.output),
+            EncryptError::InvalidMetadata => CliError::InvalidMetadata,
+            EncryptError::InvalidBlockLength => CliError::InvalidBlockLength,
         })?;
         Ok(())
     }
 }
 
+/// Returns the metadata of the given input file.
+fn file_metadata(path: &Path) -> Result<FileMetadata, CliError> {
+    if path.as_os_str() == "-" {
+        return Err(CliError::MetadataRequiresFile);
+    }
+
+    let read_err = |e| CliError::ReadIo(e, path.to_path_buf());
+    let name = path.file_name().and_then(|n| n.to_str()).ok_or(CliError::InvalidMetadata)?;
+    let metadata = fs::metadata(path).map_err(read_err)?;
+    let mtime = match metadata.modified().map_err(read_err)?.duration_since(UNIX_EPOCH) {
+        Ok(d) => i64::try_from(d.as_secs()).unwrap_or(i64::MAX),
+        Err(e) => i64::try_from(e.duration().as_secs()).map_or(i64::MIN, |s| -s),
+    };
+
+    #[cfg(unix)]
+    let mode = {
+        use std::os::unix::fs::PermissionsExt;
+        metadata.permissions().mode() & 0o777
+    };
+    #[cfg(not(unix))]
+    let mode = if metadata.permissions().readonly() { 0o444 } else { 0o644 };
+
+    Ok(FileMetadata { name: name.into(), size: metadata.len(), mode, mtime })
+}
+
+#[derive(Debug, Parser)]
+struct EncryptOptionsArgs {
+    /// Add fake receivers.
+    #[arg(long, value_name = "COUNT")]
+    fakes: Option<usize>,
+
+    /// Pad the number of receivers with fakes to the next power of two ('pow2') or to a fixed
+    /// number of slots.
+    #[arg(long, value_name = "pow2|SLOTS", value_parser = parse_receivers_bucket)]
+    receivers_bucket: Option<ReceiversBucket>,
+
+    /// Add yourself as a receiver, allowing you to decrypt the message later.
+    #[arg(long = "self", env = "VEIL_ENCRYPT_TO_SELF", value_parser = BoolishValueParser::new())]
+    include_self: bool,
+
+    /// Don't add yourself as a receiver, overriding VEIL_ENCRYPT_TO_SELF.
+    #[arg(long)]
+    no_self: bool,
+
+    /// The length of each plaintext block, in bytes.
+    #[arg(long, value_name = "BYTES", value_parser = parse_block_len)]
+    block_len: Option<usize>,
+
+    /// Compress the message before encrypting it.
+    #[arg(long)]
+    compress: bool,
+}
+
+impl EncryptOptionsArgs {
+    fn options(&self) -> EncryptOptions {
+        EncryptOptions {
+            fakes: self.fakes.unwrap_or_default(),
+            include_self: self.include_self && !self.no_self,
+            bucket: self.receivers_bucket.unwrap_or_default(),
+            block_len: self.block_len.unwrap_or(DEFAULT_BLOCK_LEN),
+            compress: self.compress,
+            metadata: None,
+        }
+    }
+}
+
+fn parse_block_len(s: &str) -> Result<usize, String> {
+    match s.parse::<usize>() {
+        Ok(n) if (MIN_BLOCK_LEN..=MAX_BLOCK_LEN).contains(&n) => Ok(n),
+        _ => Err(format!("must be between {MIN_BLOCK_LEN} and {MAX_BLOCK_LEN} bytes")),
+    }
+}
+
+fn parse_receivers_bucket(s: &str) -> Result<ReceiversBucket, String> {
+    match s {
+        "pow2" => Ok(ReceiversBucket::PowerOfTwo),
+        _ => match s.parse::<usize>() {
+            Ok(slots) if slots > 0 => Ok(ReceiversBucket::Fixed(slots)),
+            _ => Err("must be 'pow2' or a positive number of slots".into()),
+        },
+    }
+}
+
 /// Decrypt and verify a message.
 #[derive(Debug, Parser)]
 struct DecryptArgs {
@@ -175,40 +608,264 @@ struct DecryptArgs {
     #[arg(short, long, value_hint = ValueHint::FilePath, value_name = "PATH")]
     input: PathBuf,
 
+    /// The path to the output file or '-' for stdout, or the output directory if restoring file
+    /// metadata.
+    #[arg(short, long, value_hint = ValueHint::AnyPath, value_name = "PATH")]
+    output: PathBuf,
+
+    /// The sender's public key.
+    #[arg(short, long, value_hint = ValueHint::FilePath, value_name = "PATH")]
+    sender: PathBuf,
+
+    #[command(flatten)]
+    limits: DecryptLimitsArgs,
+
+    /// Read and write on separate threads from decryption.
+    #[arg(long)]
+    pipelined: bool,
+
+    /// Write the plaintext to the output directory under its original file name, with its
+    /// original permissions and modification time.
+    #[arg(long, conflicts_with = "pipelined")]
+    restore_metadata: bool,
+}
+
+impl Runnable for DecryptArgs {
+    fn run(self) -> Result<(), CliError> {
+        if self.restore_metadata {
+            let input = open_mapped_input(&self.input)?;
+            return self.restore(input);
+        }
+        let secret_key = self.secret_key.decrypt_with(DecryptionSecretKey::load)?;
+        let limits = self.limits.limits();
+        let sender = open_public_key(self.sender)?;
+        let res = if self.pipelined {
+            let input = open_pipelined_input(&self.input)?;
+            let output = open_pipelined_output(&self.output)?;
+            secret_key.decrypt_pipelined(input, output, &sender, &limits)
+        } else {
+            let input = open_mapped_input(&self.input)?;
+            let output = open_output(&self.output, true)?;
+            match input {
+                Input::Mapped(input) => secret_key.decrypt_slice(&input, output, &sender, &limits),
+                Input::Stream(input) => {
+                    secret_key.decrypt_with_limits(input, output, &sender, &limits)
+                }
+            }
+        };
+        res.map_err(|e| decrypt_error(e, self.input))?;
+        Ok(())
+    }
+}
+
+impl DecryptArgs {
+    /// Decrypts the input to a temporary file in the output directory, then restores its original
+    /// name, permissions, and modification time from the message's file metadata.
+    fn restore(self, input: Input) -> Result<(), CliError> {
+        let secret_key = self.secret_key.decrypt_with(DecryptionSecretKey::load)?;
+        let limits = self.limits.limits();
+        let sender = open_public_key(self.sender)?;
+
+        let tmp_path = self.output.join(format!(".veil-{}.tmp", process::id()));
+        let tmp = File::options()
+            .write(true)
+            .create_new(true)
+            .open(&tmp_path)
+            .map_err(|e| CliError::WriteIo(e, tmp_path.clone()))?;
+
+        let res = match input {
+            Input::Mapped(input) => {
+                secret_key.decrypt_with_metadata(&input[..], &tmp, &sender, &limits)
+            }
+            Input::Stream(input) => secret_key.decrypt_with_metadata(input, &tmp, &sender, &limits),
+        };
+        let res = res.map_err(|e| decrypt_error(e, self.input)).and_then(|(_, metadata)| {
+            let metadata = metadata.ok_or(CliError::MissingMetadata)?;
+            restore_file(&tmp, &tmp_path, &self.output, &metadata)
+        });
+
+        // Don't leave partial or unnamed plaintexts behind.
+        if res.is_err() {
+            let _ = fs::remove_file(&tmp_path);
+        }
+        res
+    }
+}
+
+/// Applies the given file metadata to the decrypted temporary file and links it into `dir` under
+/// its original name, refusing to overwrite an existing file.
+fn restore_file(
+    tmp: &File,
+    tmp_path: &Path,
+    dir: &Path,
+    metadata: &FileMetadata,
+) -> Result<(), CliError> {
+    let path = dir.join(&metadata.name);
+    let write_err = |e| CliError::WriteIo(e, path.clone());
+
+    let offset = Duration::from_secs(metadata.mtime.unsigned_abs());
+    let mtime = if metadata.mtime < 0 {
+        UNIX_EPOCH.checked_sub(offset)
+    } else {
+        UNIX_EPOCH.checked_add(offset)
+    };
+    if let Some(mtime) = mtime {
+        tmp.set_modified(mtime).map_err(write_err)?;
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        tmp.set_permissions(fs::Permissions::from_mode(metadata.mode & 0o777))
+            .map_err(write_err)?;
+    }
+
+    fs::hard_link(tmp_path, &path).map_err(write_err)?;
+    fs::remove_file(tmp_path).map_err(write_err)
+}
+
+#[derive(Debug, Parser)]
+struct DecryptLimitsArgs {
+    /// The maximum number of headers to read, including fake receivers.
+    #[arg(long, value_name = "COUNT")]
+    max_headers: Option<u64>,
+
+    /// The maximum length of the encrypted message, in bytes.
+    #[arg(long, value_name = "BYTES")]
+    max_ciphertext_len: Option<u64>,
+
+    /// The maximum length of a single block of the message, in bytes.
+    #[arg(long, value_name = "BYTES")]
+    max_block_len: Option<usize>,
+
+    /// The maximum length of the decrypted (and decompressed) message, in bytes.
+    #[arg(long, value_name = "BYTES")]
+    max_plaintext_len: Option<u64>,
+}
+
+impl DecryptLimitsArgs {
+    const fn limits(&self) -> DecryptLimits {
+        DecryptLimits {
+            max_headers: self.max_headers,
+            max_ciphertext_len: self.max_ciphertext_len,
+            max_block_len: self.max_block_len,
+            max_plaintext_len: self.max_plaintext_len,
+        }
+    }
+}
+
+/// Encrypt a directory tree as a single message.
+#[derive(Debug, Parser)]
+struct EncryptDirArgs {
+    #[command(flatten)]
+    secret_key: SecretKeyInput,
+
+    /// The path to the input directory.
+    #[arg(short, long, value_hint = ValueHint::DirPath, value_name = "PATH")]
+    input: PathBuf,
+
     /// The path to the output file or '-' for stdout.
     #[arg(short, long, value_hint = ValueHint::FilePath, value_name = "PATH")]
     output: PathBuf,
 
+    /// The receivers' public keys.
+    #[arg(
+        short = 'r',
+        long = "receiver",
+        value_name = "PATH",
+        num_args(1..),
+        required = true,
+        action(ArgAction::Append),
+        value_hint = ValueHint::FilePath,
+    )]
+    receivers: Vec<PathBuf>,
+
+    #[command(flatten)]
+    options: EncryptOptionsArgs,
+}
+
+impl Runnable for EncryptDirArgs {
+    fn run(self) -> Result<(), CliError> {
+        let secret_key = self.secret_key.decrypt()?;
+        let output = open_output(&self.output, true)?;
+        let receivers =
+            self.receivers.into_iter().map(open_public_key).collect::<Result<Vec<_>, _>>()?;
+        secret_key
+            .encrypt_dir(OsRng, &self.input, output, &receivers, self.options.options())
+            .map_err(|e| match e {
+                EncryptError::ReadIo(e) => CliError::ReadIo(e, self.input),
+                EncryptError::WriteIo(e) => CliError::WriteIo(e, self.output),
+                EncryptError::InvalidMetadata => CliError::InvalidMetadata,
+                EncryptError::InvalidBlockLength => CliError::InvalidBlockLength,
+            })?;
+        Ok(())
+    }
+}
+
+/// Decrypt and verify a directory tree.
+#[derive(Debug, Parser)]
+struct DecryptDirArgs {
+    #[command(flatten)]
+    secret_key: SecretKeyInput,
+
+    /// The path to the input file or '-' for stdin.
+    #[arg(short, long, value_hint = ValueHint::FilePath, value_name = "PATH")]
+    input: PathBuf,
+
+    /// The path to the output directory, which must be empty or not exist.
+    #[arg(short, long, value_hint = ValueHint::DirPath, value_name = "PATH")]
+    output: PathBuf,
+
     /// The sender's public key.
     #[arg(short, long, value_hint = ValueHint::FilePath, value_name = "PATH")]
     sender: PathBuf,
+
+    #[command(flatten)]
+    limits: DecryptLimitsArgs,
 }
 
-impl Runnable for DecryptArgs {
+impl Runnable for DecryptDirArgs {
     fn run(self) -> Result<(), CliError> {
         let input = open_input(&self.input)?;
-        let output = open_output(&self.output, true)?;
-        let secret_key = self.secret_key.decrypt()?;
+        let secret_key = self.secret_key.decrypt_with(DecryptionSecretKey::load)?;
         let sender = open_public_key(self.sender)?;
-        secret_key.decrypt(input, output, &sender).map_err(|e| match e {
-            DecryptError::InvalidCiphertext => CliError::InvalidCiphertext,
-            DecryptError::ReadIo(e) => CliError::ReadIo(e, self.input),
-            DecryptError::WriteIo(e) => CliError::WriteIo(e, self.input),
-            DecryptError::InvalidBlockType(b) => CliError::InvalidBlockType(b),
-        })?;
+        secret_key.decrypt_dir(input, &self.output, &sender, &self.limits.limits()).map_err(
+            |e| match e {
+                DecryptError::WriteIo(e) => CliError::WriteIo(e, self.output),
+                e => decrypt_error(e, self.input),
+            },
+        )?;
         Ok(())
     }
 }
 
+fn decrypt_error(e: DecryptError, input: PathBuf) -> CliError {
+    match e {
+        DecryptError::InvalidCiphertext => CliError::InvalidCiphertext,
+        DecryptError::LimitExceeded => CliError::LimitExceeded,
+        DecryptError::UnsupportedCompression => CliError::UnsupportedCompression,
+        DecryptError::InvalidArchive => CliError::InvalidArchive,
+        DecryptError::ReadIo(e) => CliError::ReadIo(e, input.clone()),
+        DecryptError::WriteIo(e) => CliError::WriteIo(e, input),
+        DecryptError::InvalidBlockType(b) => CliError::InvalidBlockType(b),
+        e @ DecryptError::InvalidKeyType => CliError::BadPassphrase(e),
+    }
+}
+
 /// Sign a message.
 #[derive(Debug, Parser)]
+#[command(group(ArgGroup::new("message").required(true)))]
 struct SignArgs {
     #[command(flatten)]
     secret_key: SecretKeyInput,
 
     /// The path to the message file or '-' for stdin.
-    #[arg(short, long, value_hint = ValueHint::FilePath, value_name = "PATH")]
-    input: PathBuf,
+    #[arg(short, long, value_hint = ValueHint::FilePath, value_name = "PATH", group("message"))]
+    input: Option<PathBuf>,
+
+    /// Sign a pre-computed digest of the message instead of reading it.
+    #[arg(long, value_name = "DIGEST", group("message"))]
+    digest: Option<Digest>,
 
     /// The path to the signature file or '-' for stdout.
     #[arg(short, long, value_hint = ValueHint::FilePath, default_value = "-", value_name = "PATH")]
@@ -217,10 +874,17 @@ struct SignArgs {
 
 impl Runnable for SignArgs {
     fn run(self) -> Result<(), CliError> {
-        let input = open_input(&self.input)?;
+        let input = self.input.as_deref().map(open_mapped_input).transpose()?;
         let mut output = open_output(&self.output, false)?;
-        let secret_key = self.secret_key.decrypt()?;
-        let sig = secret_key.sign(OsRng, input).map_err(|e| CliError::ReadIo(e, self.input))?;
+        let secret_key = self.secret_key.decrypt_with(SigningSecretKey::load)?;
+        let sig = match input {
+            Some(Input::Mapped(input)) => secret_key.sign_slice(OsRng, &input),
+            Some(Input::Stream(input)) => secret_key
+                .sign(OsRng, input)
+                .map_err(|e| CliError::ReadIo(e, self.input.unwrap_or_default()))?,
+            None => secret_key
+                .sign_digest(OsRng, &self.digest.expect("should be required without an input")),
+        };
         write!(output, "{sig}").map_err(|e| CliError::WriteIo(e, self.output))?;
         Ok(())
     }
@@ -228,8 +892,9 @@ impl Runnable for SignArgs {
 
 /// Verify a signature.
 #[derive(Debug, Parser)]
+#[command(group(ArgGroup::new("message").required(true)))]
 struct VerifyArgs {
-    /// The signer's public key.
+    /// The signer's public key or verifying key.
     #[arg(long, value_hint = ValueHint::FilePath, value_name = "PATH")]
     signer: PathBuf,
 
@@ -238,18 +903,30 @@ struct VerifyArgs {
     signature: PathBuf,
 
     /// The path to the message file or '-' for stdin.
-    #[arg(short, long, value_hint = ValueHint::FilePath, value_name = "PATH")]
-    input: PathBuf,
+    #[arg(short, long, value_hint = ValueHint::FilePath, value_name = "PATH", group("message"))]
+    input: Option<PathBuf>,
+
+    /// Verify a signature of a pre-computed digest, created with `sign --digest`.
+    #[arg(long, value_name = "DIGEST", group("message"))]
+    digest: Option<Digest>,
 }
 
 impl Runnable for VerifyArgs {
     fn run(self) -> Result<(), CliError> {
-        let input = open_input(&self.input)?;
-        let signer = open_public_key(self.signer)?;
+        let input = self.input.as_deref().map(open_mapped_input).transpose()?;
+        let signer = open_verifying_key(self.signer)?;
         let signature = open_signature(self.signature)?;
-        signer.verify(input, &signature).map_err(|e| match e {
+        let res = match input {
+            Some(Input::Mapped(input)) => signer.verify_slice(&input, &signature),
+            Some(Input::Stream(input)) => signer.verify(input, &signature),
+            None => signer.verify_digest(
+                &self.digest.expect("should be required without an input"),
+                &signature,
+            ),
+        };
+        res.map_err(|e| match e {
             veil::VerifyError::InvalidSignature => CliError::BadSignature,
-            veil::VerifyError::ReadIo(e) => CliError::ReadIo(e, self.input),
+            veil::VerifyError::ReadIo(e) => CliError::ReadIo(e, self.input.unwrap_or_default()),
         })?;
         Ok(())
     }
@@ -257,18 +934,39 @@ impl Runnable for VerifyArgs {
 
 /// Calculate a message digest.
 #[derive(Debug, Parser)]
+#[command(group(ArgGroup::new("message").required(true)))]
 struct DigestArgs {
     /// Associated metadata to be included in the digest.
     #[arg(short, long)]
     metadata: Vec<String>,
 
     /// Compare the computed digest to a given digest.
-    #[arg(long, value_name = "DIGEST", group("out"))]
+    #[arg(long, value_name = "DIGEST", group("out"), requires = "input")]
     check: Option<Digest>,
 
+    /// Hash the message as a tree of chunks in parallel, creating a `veil.digest.tree` digest.
+    #[arg(long)]
+    tree: bool,
+
     /// The path to the message file or '-' for stdin.
-    #[arg(short, long, value_hint = ValueHint::FilePath, value_name = "PATH")]
-    input: PathBuf,
+    #[arg(short, long, value_hint = ValueHint::FilePath, value_name = "PATH", group("message"))]
+    input: Option<PathBuf>,
+
+    /// Create a manifest of `digest  path` lines for the given files.
+    #[arg(value_hint = ValueHint::FilePath, value_name = "FILE", group("message"))]
+    files: Vec<PathBuf>,
+
+    /// Check the digest of every file listed in the given manifest.
+    #[arg(long, value_hint = ValueHint::FilePath, value_name = "PATH", group("message"))]
+    check_manifest: Option<PathBuf>,
+
+    /// Verify the manifest's signature by the given public key or verifying key before checking it.
+    #[arg(long, value_hint = ValueHint::FilePath, value_name = "PATH", requires = "check_manifest", requires = "signature")]
+    signer: Option<PathBuf>,
+
+    /// The signature of the manifest, created with `sign`.
+    #[arg(long, value_hint = ValueHint::FilePath, value_name = "PATH", requires = "signer")]
+    signature: Option<PathBuf>,
 
     /// The path to the digest file or '-' for stdout.
     #[arg(short, long, value_hint = ValueHint::FilePath, default_value = "-", value_name = "PATH", group("out"))]
@@ -277,15 +975,200 @@ struct DigestArgs {
 
 impl Runnable for DigestArgs {
     fn run(self) -> Result<(), CliError> {
-        let input = open_input(&self.input)?;
-        let digest =
-            Digest::new(&self.metadata, input).map_err(|e| CliError::ReadIo(e, self.input))?;
+        if let Some(manifest) = &self.check_manifest {
+            return self.check_manifest(manifest);
+        }
+
+        if let Some(input) = &self.input {
+            let digest = self.digest(input)?;
+            if let Some(check) = self.check {
+                if check != digest {
+                    return Err(CliError::DigestMismatch);
+                }
+            } else {
+                write!(open_output(&self.output, false)?, "{digest}").map_err(CliError::TermIo)?;
+            }
+        } else {
+            // Validate the header and every path before writing anything.
+            let header = self.manifest_header()?;
+            let names = self
+                .files
+                .iter()
+                .map(|path| {
+                    path.to_str()
+                        .filter(|name| is_manifest_path(name))
+                        .ok_or_else(|| CliError::InvalidManifestPath(path.clone()))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+
+            let mut output = open_output(&self.output, false)?;
+            for line in header {
+                writeln!(output, "{line}").map_err(CliError::TermIo)?;
+            }
+            for (path, name) in self.files.iter().zip(names) {
+                let digest = self.digest(path)?;
+                writeln!(output, "{digest}  {name}").map_err(CliError::TermIo)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl DigestArgs {
+    /// Calculates the digest of the given file with the given metadata.
+    fn digest(&self, path: &Path) -> Result<Digest, CliError> {
+        let read_err = |e| CliError::ReadIo(e, path.to_path_buf());
+        Ok(match (open_mapped_input(path)?, self.tree) {
+            (Input::Mapped(input), false) => Digest::from_slice(&self.metadata, &input),
+            (Input::Mapped(input), true) => DigestTree::from_slice(&input).digest(&self.metadata),
+            (Input::Stream(input), false) => {
+                Digest::new(&self.metadata, input).map_err(read_err)?
+            }
+            (Input::Stream(input), true) => {
+                DigestTree::new(input).map_err(read_err)?.digest(&self.metadata)
+            }
+        })
+    }
+
+    /// Returns the header lines of a manifest created with these options: the digest construction,
+    /// followed by each metadata value.
+    fn manifest_header(&self) -> Result<Vec<String>, CliError> {
+        let mode = if self.tree { "veil.digest.tree" } else { "veil.digest" };
+        let mut header = vec![format!("# {mode}")];
+        for v in &self.metadata {
+            if v.contains(['\n', '\r']) {
+                return Err(CliError::InvalidManifestMetadata);
+            }
+            header.push(format!("# metadata: {v}"));
+        }
+        Ok(header)
+    }
+
+    /// Checks each entry of a manifest, verifying its signature first if a signer was given.
+    fn check_manifest(&self, path: &Path) -> Result<(), CliError> {
+        let mut manifest = String::new();
+        open_input(path)?
+            .read_to_string(&mut manifest)
+            .map_err(|e| CliError::ReadIo(e, path.to_path_buf()))?;
+
+        // Verify the manifest's signature before trusting any of its entries.
+        if let (Some(signer), Some(signature)) = (&self.signer, &self.signature) {
+            let signer = open_verifying_key(signer.clone())?;
+            let signature = open_signature(signature.clone())?;
+            signer
+                .verify_slice(manifest.as_bytes(), &signature)
+                .map_err(|_| CliError::BadSignature)?;
+        }
+
+        // Ensure the manifest was created with the same mode and metadata.
+        let header = self.manifest_header()?;
+        let mut lines = manifest.lines().enumerate().peekable();
+        let manifest_header = iter::from_fn(|| lines.next_if(|(_, line)| line.starts_with('#')))
+            .map(|(_, line)| line)
+            .collect::<Vec<_>>();
+        if manifest_header != header {
+            return Err(CliError::ManifestModeMismatch);
+        }
+
+        // Parse every entry before checking any of them.
+        let entries = lines
+            .map(|(i, line)| {
+                line.split_once("  ")
+                    .filter(|&(_, name)| is_manifest_path(name))
+                    .and_then(|(digest, name)| Some((digest.parse::<Digest>().ok()?, name)))
+                    .ok_or(CliError::InvalidManifest(i + 1))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if entries.is_empty() {
+            return Err(CliError::EmptyManifest);
+        }
+
+        // Check every entry, reporting unreadable files as failures.
+        let mut output = open_output(&self.output, false)?;
+        let mut failures = 0;
+        for (digest, name) in entries {
+            let ok = self.digest(Path::new(name)).is_ok_and(|d| d == digest);
+            writeln!(output, "{name}: {}", if ok { "OK" } else { "FAILED" })
+                .map_err(CliError::TermIo)?;
+            failures += usize::from(!ok);
+        }
+        if failures > 0 {
+            return Err(CliError::ManifestMismatch(failures));
+        }
+        Ok(())
+    }
+}
+
+/// Returns whether `name` can be listed in a manifest: a non-empty relative path which isn't `-`
+/// (which would be read from stdin) and doesn't contain line breaks or refer to a parent directory.
+fn is_manifest_path(name: &str) -> bool {
+    !name.is_empty()
+        && name != "-"
+        && !name.contains(['\n', '\r'])
+        && Path::new(name)
+            .components()
+            .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+}
+
+/// Calculate a message authentication code with a shared key.
+#[derive(Debug, Parser)]
+#[command(group(ArgGroup::new("shared_key").required(true)))]
+struct MacArgs {
+    /// The path to the shared key file.
+    #[arg(long, value_hint = ValueHint::FilePath, value_name = "PATH", group("shared_key"))]
+    key: Option<PathBuf>,
+
+    /// Read the shared key from the given file descriptor.
+    #[arg(long, value_name = "FD", group("shared_key"))]
+    #[cfg(unix)]
+    key_fd: Option<std::os::unix::prelude::RawFd>,
+
+    /// Associated metadata to be included in the MAC.
+    #[arg(short, long)]
+    metadata: Vec<String>,
+
+    /// Compare the computed MAC to a given MAC.
+    #[arg(long, value_name = "MAC", group("out"))]
+    check: Option<Mac>,
+
+    /// The path to the message file or '-' for stdin.
+    #[arg(short, long, value_hint = ValueHint::FilePath, value_name = "PATH")]
+    input: PathBuf,
+
+    /// The path to the MAC file or '-' for stdout.
+    #[arg(short, long, value_hint = ValueHint::FilePath, default_value = "-", value_name = "PATH", group("out"))]
+    output: PathBuf,
+}
+
+impl MacArgs {
+    fn read_key(&self) -> Result<Vec<u8>, CliError> {
+        #[cfg(unix)]
+        if let Some(fd) = self.key_fd {
+            return read_from_fd(fd);
+        }
+
+        let path = self.key.as_ref().expect("should be required without a file descriptor");
+        fs::read(path).map_err(|e| CliError::ReadIo(e, path.clone()))
+    }
+}
+
+impl Runnable for MacArgs {
+    fn run(self) -> Result<(), CliError> {
+        let key = self.read_key()?;
+        if key.is_empty() {
+            return Err(CliError::EmptyKey);
+        }
+        let mac = match open_mapped_input(&self.input)? {
+            Input::Mapped(input) => Mac::from_slice(&key, &self.metadata, &input),
+            Input::Stream(input) => Mac::new(&key, &self.metadata, input)
+                .map_err(|e| CliError::ReadIo(e, self.input))?,
+        };
         if let Some(check) = self.check {
-            if check != digest {
-                return Err(CliError::DigestMismatch);
+            if check != mac {
+                return Err(CliError::MacMismatch);
             }
         } else {
-            write!(open_output(&self.output, false)?, "{digest}").map_err(CliError::TermIo)?;
+            write!(open_output(&self.output, false)?, "{mac}").map_err(CliError::TermIo)?;
         }
         Ok(())
     }
@@ -325,10 +1208,29 @@ struct SecretKeyInput {
 
 impl SecretKeyInput {
     fn decrypt(&self) -> Result<SecretKey, CliError> {
+        self.decrypt_with(SecretKey::load)
+    }
+
+    fn decrypt_with<T>(
+        &self,
+        load: impl FnOnce(File, &[u8]) -> Result<T, DecryptError>,
+    ) -> Result<T, CliError> {
         let passphrase = self.passphrase_input.read_passphrase()?;
+        self.load_with(&passphrase, load)
+    }
+
+    fn load(&self, passphrase: &[u8]) -> Result<SecretKey, CliError> {
+        self.load_with(passphrase, SecretKey::load)
+    }
+
+    fn load_with<T>(
+        &self,
+        passphrase: &[u8],
+        load: impl FnOnce(File, &[u8]) -> Result<T, DecryptError>,
+    ) -> Result<T, CliError> {
         let ciphertext = File::open(&self.secret_key)
             .map_err(|e| CliError::ReadIo(e, self.secret_key.to_path_buf()))?;
-        SecretKey::load(ciphertext, &passphrase).map_err(CliError::BadPassphrase)
+        load(ciphertext, passphrase).map_err(CliError::BadPassphrase)
     }
 }
 
@@ -344,24 +1246,13 @@ impl PassphraseInput {
     fn read_passphrase(&self) -> Result<Vec<u8>, CliError> {
         if cfg!(unix) {
             if let Some(fd) = self.passphrase_fd {
-                return Self::read_from_fd(fd);
+                return read_from_fd(fd);
             }
         }
 
         self.prompt_for_passphrase()
     }
 
-    #[cfg(unix)]
-    fn read_from_fd(fd: i32) -> Result<Vec<u8>, CliError> {
-        use std::os::unix::prelude::FromRawFd;
-
-        let mut out = Vec::new();
-        unsafe { File::from_raw_fd(fd) }
-            .read_to_end(&mut out)
-            .map_err(|e| CliError::FdIo(e, fd))?;
-        Ok(out)
-    }
-
     fn prompt_for_passphrase(&self) -> Result<Vec<u8>, CliError> {
         let mut term = Term::stderr();
         let _ = term.write(b"Enter passphrase: ").map_err(CliError::TermIo)?;
@@ -373,6 +1264,15 @@ impl PassphraseInput {
     }
 }
 
+#[cfg(unix)]
+fn read_from_fd(fd: i32) -> Result<Vec<u8>, CliError> {
+    use std::os::unix::prelude::FromRawFd;
+
+    let mut out = Vec::new();
+    unsafe { File::from_raw_fd(fd) }.read_to_end(&mut out).map_err(|e| CliError::FdIo(e, fd))?;
+    Ok(out)
+}
+
 fn open_signature(path: PathBuf) -> Result<Signature, CliError> {
     let mut s = String::with_capacity(2048);
     let mut f = File::open(&path).map_err(|e| CliError::ReadIo(e, path.clone()))?;
@@ -387,6 +1287,13 @@ fn open_public_key(path: PathBuf) -> Result<PublicKey, CliError> {
     s.parse().map_err(|e| CliError::InvalidPublicKey(e, path.clone()))
 }
 
+fn open_verifying_key(path: PathBuf) -> Result<VerifyingPublicKey, CliError> {
+    let mut s = String::with_capacity(2048);
+    let mut f = File::open(&path).map_err(|e| CliError::ReadIo(e, path.clone()))?;
+    f.read_to_string(&mut s).map_err(|e| CliError::ReadIo(e, path.clone()))?;
+    s.parse().map_err(|e| CliError::InvalidPublicKey(e, path.clone()))
+}
+
 fn open_input(path: &Path) -> Result<Box<dyn Read>, CliError> {
     if path.as_os_str() == "-" {
         if io::stdin().is_terminal() {
@@ -399,6 +1306,44 @@ fn open_input(path: &Path) -> Result<Box<dyn Read>, CliError> {
     }
 }
 
+/// Opens the given input to be read on another thread. Unlike [`open_input`], stdin isn't locked,
+/// because its lock can't be sent between threads.
+fn open_pipelined_input(path: &Path) -> Result<Box<dyn Read + Send>, CliError> {
+    if path.as_os_str() == "-" {
+        if io::stdin().is_terminal() {
+            return Err(CliError::StdinTty);
+        }
+        Ok(Box::new(io::stdin()))
+    } else {
+        let f = File::open(path).map_err(|e| CliError::ReadIo(e, path.to_path_buf()))?;
+        Ok(Box::new(f))
+    }
+}
+
+/// An input which is either memory-mapped or read as a stream.
+enum Input {
+    Mapped(Mmap),
+    Stream(Box<dyn Read>),
+}
+
+/// Opens the given input, memory-mapping it if it's a non-empty regular file.
+fn open_mapped_input(path: &Path) -> Result<Input, CliError> {
+    if path.as_os_str() == "-" {
+        return Ok(Input::Stream(open_input(path)?));
+    }
+
+    let f = File::open(path).map_err(|e| CliError::ReadIo(e, path.to_path_buf()))?;
+    let metadata = f.metadata().map_err(|e| CliError::ReadIo(e, path.to_path_buf()))?;
+    if metadata.is_file() && metadata.len() > 0 {
+        // SAFETY: The map is read-only. As with any tool which maps its input, this assumes the
+        // file isn't truncated or modified by another process while it's being read.
+        if let Ok(map) = unsafe { Mmap::map(&f) } {
+            return Ok(Input::Mapped(map));
+        }
+    }
+    Ok(Input::Stream(Box::new(f)))
+}
+
 fn open_output(path: &Path, binary: bool) -> Result<Box<dyn Write>, CliError> {
     if path.as_os_str() == "-" {
         if binary && io::stdout().is_terminal() {
@@ -411,6 +1356,20 @@ fn open_output(path: &Path, binary: bool) -> Result<Box<dyn Write>, CliError> {
     }
 }
 
+/// Opens the given binary output to be written on another thread. Unlike [`open_output`], stdout
+/// isn't locked, because its lock can't be sent between threads.
+fn open_pipelined_output(path: &Path) -> Result<Box<dyn Write + Send>, CliError> {
+    if path.as_os_str() == "-" {
+        if io::stdout().is_terminal() {
+            return Err(CliError::StdoutTty);
+        }
+        Ok(Box::new(io::stdout()))
+    } else {
+        let f = File::create(path).map_err(|e| CliError::WriteIo(e, path.to_path_buf()))?;
+        Ok(Box::new(f))
+    }
+}
+
 #[derive(Debug, Error)]
 enum CliError {
     #[error("unable to read from stdin: is a tty")]
@@ -437,9 +1396,36 @@ enum CliError {
     #[error("unable to decrypt secret key")]
     BadPassphrase(#[source] DecryptError),
 
+    #[error("KDF costs are out of range")]
+    InvalidKdf,
+
     #[error("digest mismatch")]
     DigestMismatch,
 
+    #[error("digest mismatch for {0} manifest entries")]
+    ManifestMismatch(usize),
+
+    #[error("invalid manifest entry on line {0}")]
+    InvalidManifest(usize),
+
+    #[error("manifest has no entries")]
+    EmptyManifest,
+
+    #[error("unable to list {0:?} in a manifest")]
+    InvalidManifestPath(PathBuf),
+
+    #[error("unable to record metadata with line breaks in a manifest")]
+    InvalidManifestMetadata,
+
+    #[error("manifest was created with different --tree or --metadata options")]
+    ManifestModeMismatch,
+
+    #[error("MAC mismatch")]
+    MacMismatch,
+
+    #[error("shared key is empty")]
+    EmptyKey,
+
     #[error("invalid signature at {1:?}")]
     InvalidSignature(#[source] ParseSignatureError, PathBuf),
 
@@ -452,8 +1438,41 @@ enum CliError {
     #[error("invalid block type: {0:02x}")]
     InvalidBlockType(u8),
 
+    #[error("decryption limit exceeded")]
+    LimitExceeded,
+
+    #[error("message is compressed but compression is not supported")]
+    UnsupportedCompression,
+
+    #[error("invalid file metadata")]
+    InvalidMetadata,
+
+    #[error("block length must be between {MIN_BLOCK_LEN} and {MAX_BLOCK_LEN} bytes")]
+    InvalidBlockLength,
+
+    #[error("file metadata requires an input file")]
+    MetadataRequiresFile,
+
+    #[error("message has no file metadata")]
+    MissingMetadata,
+
+    #[error("invalid archive")]
+    InvalidArchive,
+
     #[error("invalid public key at {1:?}")]
     InvalidPublicKey(#[source] ParsePublicKeyError, PathBuf),
+
+    #[error("invalid share at {1:?}")]
+    InvalidShare(#[source] ParseShareError, PathBuf),
+
+    #[error("unable to combine shares")]
+    CombineShares(#[source] CombineSharesError),
+
+    #[error("threshold must not be greater than the number of shares")]
+    InvalidThreshold,
+
+    #[error("invalid mnemonic")]
+    InvalidMnemonic(#[source] ParseMnemonicError),
 }
 
 impl CliError {
@@ -65,7 +65,7 @@ fn encrypt_and_decrypt_a_message() -> Result<()> {
     let ciphertext_path = &dir.path().join("message.veil");
     veil_cmd!(
         sh,
-        "encrypt -k {secret_key_path_a:?} -i {message_file:?} -o {ciphertext_path:?} -r {public_key_path_b:?} --fakes=20", 
+        "encrypt -k {secret_key_path_a:?} -i {message_file:?} -o {ciphertext_path:?} -r {public_key_path_b:?} --fakes=20 --receivers-bucket=pow2 --block-len=1024", 
         alice_passphrase
     )
     .run()?;
@@ -74,7 +74,7 @@ fn encrypt_and_decrypt_a_message() -> Result<()> {
     let plaintext_path = &dir.path().join("message.txt");
     veil_cmd!(
         sh,
-        "decrypt -k {secret_key_path_b:?} -i {ciphertext_path:?} -o {plaintext_path:?} -s {public_key_path_a:?}",
+        "decrypt -k {secret_key_path_b:?} -i {ciphertext_path:?} -o {plaintext_path:?} -s {public_key_path_a:?} --pipelined",
         bea_passphrase
     )
     .run()?;
@@ -130,3 +130,801 @@ fn sign_and_verify_message() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn sign_and_verify_digest() -> Result<()> {
+    let sh = Shell::new()?;
+    let dir = sh.create_temp_dir()?;
+
+    // Alice generates a secret key and public key.
+    let alice_passphrase = "excelsior";
+    let secret_key_path = &dir.path().join("secret-key-a");
+    veil_cmd!(
+        sh,
+        "secret-key -o {secret_key_path:?} --time-cost=0 --memory-cost=0",
+        alice_passphrase
+    )
+    .run()?;
+    let public_key_path = &dir.path().join("public-key-a");
+    veil_cmd!(sh, "public-key -k {secret_key_path:?} -o {public_key_path:?}", alice_passphrase)
+        .run()?;
+
+    // A build server calculates the digest of an artifact.
+    let artifact_file = &dir.path().join("artifact");
+    fs::write(artifact_file, "this is a release artifact")?;
+    let digest = cmd!(sh, "{VEIL_PATH} digest -i {artifact_file}").read()?;
+
+    // Alice signs the digest without reading the artifact.
+    let sig_file = &dir.path().join("artifact.sig");
+    veil_cmd!(
+        sh,
+        "sign -k {secret_key_path:?} --digest {digest} -o {sig_file:?}",
+        alice_passphrase
+    )
+    .run()?;
+
+    // Bea verifies the signature of the digest.
+    cmd!(
+        sh,
+        "{VEIL_PATH} verify --signer {public_key_path} --digest {digest} --signature {sig_file}"
+    )
+    .run()?;
+
+    // The signature isn't valid for the artifact itself.
+    let res = cmd!(
+        sh,
+        "{VEIL_PATH} verify --signer {public_key_path} -i {artifact_file} --signature {sig_file}"
+    )
+    .ignore_stderr()
+    .run();
+    assert!(res.is_err(), "should not verify a digest signature as a message signature");
+
+    Ok(())
+}
+
+#[test]
+fn tree_digest() -> Result<()> {
+    let sh = Shell::new()?;
+    let dir = sh.create_temp_dir()?;
+
+    // A build server calculates the tree digest of a disk image.
+    let image_file = &dir.path().join("disk.img");
+    fs::write(image_file, vec![0xAB; 3 * 1024 * 1024 + 12345])?;
+    let digest = cmd!(sh, "{VEIL_PATH} digest --tree -i {image_file} -m disk.img").read()?;
+
+    // The tree digest of the image read from stdin is the same.
+    let bash = format!("cat {image_file:?} | {VEIL_PATH} digest --tree -i - -m disk.img");
+    assert_eq!(digest, cmd!(sh, "bash -c {bash}").read()?);
+
+    // The digest can be checked with --tree.
+    cmd!(sh, "{VEIL_PATH} digest --tree -i {image_file} -m disk.img --check {digest}").run()?;
+
+    // A tree digest isn't a sequential digest.
+    let res = cmd!(sh, "{VEIL_PATH} digest -i {image_file} -m disk.img --check {digest}")
+        .ignore_stderr()
+        .run();
+    assert!(res.is_err(), "should not check a tree digest as a sequential digest");
+
+    Ok(())
+}
+
+#[test]
+fn signed_manifest() -> Result<()> {
+    let sh = Shell::new()?;
+    let dir = sh.create_temp_dir()?;
+    sh.change_dir(dir.path());
+
+    // Alice generates a secret key and public key.
+    let alice_passphrase = "excelsior";
+    let secret_key_path = &dir.path().join("secret-key-a");
+    veil_cmd!(
+        sh,
+        "secret-key -o {secret_key_path:?} --time-cost=0 --memory-cost=0",
+        alice_passphrase
+    )
+    .run()?;
+    let public_key_path = &dir.path().join("public-key-a");
+    veil_cmd!(sh, "public-key -k {secret_key_path:?} -o {public_key_path:?}", alice_passphrase)
+        .run()?;
+
+    // Alice creates a manifest of a release directory and signs it.
+    sh.create_dir("release")?;
+    sh.write_file("release/app", "this is an application")?;
+    sh.write_file("release/app docs.txt", "these are the docs")?;
+    cmd!(sh, "{VEIL_PATH} digest release/app 'release/app docs.txt' -o MANIFEST").run()?;
+    let manifest = sh.read_file("MANIFEST")?;
+    let (header, entries) = manifest.split_once('\n').expect("should have a header");
+    assert_eq!("# veil.digest", header, "should record the mode");
+    assert_eq!(2, entries.lines().count(), "should list both files");
+    assert!(entries.lines().all(|l| l.contains("  release/app")), "should list the paths");
+    veil_cmd!(sh, "sign -k {secret_key_path:?} -i MANIFEST -o MANIFEST.sig", alice_passphrase)
+        .run()?;
+
+    // Bea checks the manifest and its signature in one command.
+    let report = cmd!(
+        sh,
+        "{VEIL_PATH} digest --check-manifest MANIFEST --signer {public_key_path} --signature MANIFEST.sig"
+    )
+    .read()?;
+    assert_eq!("release/app: OK\nrelease/app docs.txt: OK", report);
+
+    // Modified files are reported.
+    sh.write_file("release/app", "this is malware")?;
+    let res = cmd!(sh, "{VEIL_PATH} digest --check-manifest MANIFEST")
+        .ignore_status()
+        .ignore_stderr()
+        .output()?;
+    assert!(!res.status.success(), "should not check a modified file");
+    assert_eq!("release/app: FAILED\nrelease/app docs.txt: OK\n", String::from_utf8(res.stdout)?);
+
+    // Manifests can't be checked with a different mode or metadata.
+    let res = cmd!(sh, "{VEIL_PATH} digest --check-manifest MANIFEST --tree").ignore_stderr().run();
+    assert!(res.is_err(), "should not check a manifest with a different mode");
+    let res =
+        cmd!(sh, "{VEIL_PATH} digest --check-manifest MANIFEST -m release").ignore_stderr().run();
+    assert!(res.is_err(), "should not check a manifest with different metadata");
+
+    // Entries outside the manifest's directory tree are rejected.
+    for path in ["/etc/passwd", "../secret-key-a", "release/../../secret-key-a"] {
+        let digest = cmd!(sh, "{VEIL_PATH} digest -i release/app").read()?;
+        sh.write_file("ESCAPE", format!("# veil.digest\n{digest}  {path}\n"))?;
+        let res = cmd!(sh, "{VEIL_PATH} digest --check-manifest ESCAPE")
+            .ignore_stdout()
+            .ignore_stderr()
+            .run();
+        assert!(res.is_err(), "should not check {path}");
+    }
+
+    // Entries can't be read from stdin, even if its digest matches.
+    let digest = cmd!(sh, "{VEIL_PATH} digest -i -").stdin("").read()?;
+    sh.write_file("STDIN", format!("# veil.digest\n{digest}  -\n"))?;
+    let res = cmd!(sh, "{VEIL_PATH} digest --check-manifest STDIN")
+        .stdin("")
+        .ignore_stdout()
+        .ignore_stderr()
+        .run();
+    assert!(res.is_err(), "should not check stdin");
+
+    // A manifest which has been modified to match doesn't verify.
+    let digest = cmd!(sh, "{VEIL_PATH} digest -i release/app").read()?;
+    let line = entries.lines().next().expect("should have an entry").to_string();
+    sh.write_file("MANIFEST", manifest.replace(&line, &format!("{digest}  release/app")))?;
+    cmd!(sh, "{VEIL_PATH} digest --check-manifest MANIFEST").run()?;
+    let res = cmd!(
+        sh,
+        "{VEIL_PATH} digest --check-manifest MANIFEST --signer {public_key_path} --signature MANIFEST.sig"
+    )
+    .ignore_stderr()
+    .run();
+    assert!(res.is_err(), "should not verify a modified manifest");
+
+    Ok(())
+}
+
+#[test]
+fn create_and_check_mac() -> Result<()> {
+    let sh = Shell::new()?;
+    let dir = sh.create_temp_dir()?;
+
+    // Alice and Bea share a key.
+    let key_path = &dir.path().join("shared-key");
+    fs::write(key_path, "our special secret")?;
+
+    // Alice writes a message and calculates its MAC.
+    let message_file = &dir.path().join("message");
+    fs::write(message_file, "this is an authentic message")?;
+    let mac = cmd!(sh, "{VEIL_PATH} mac --key {key_path} -i {message_file} -m message").read()?;
+
+    // Bea checks the MAC, reading the key from a file descriptor.
+    let bash = format!(
+        "{VEIL_PATH} mac --key-fd=3 -i {message_file:?} -m message --check {mac} 3< <(echo -n 'our special secret')"
+    );
+    cmd!(sh, "bash -c {bash}").run()?;
+
+    // The MAC doesn't match with a different key or different metadata.
+    let wrong_key_path = &dir.path().join("wrong-key");
+    fs::write(wrong_key_path, "not our secret")?;
+    let res = cmd!(
+        sh,
+        "{VEIL_PATH} mac --key {wrong_key_path} -i {message_file} -m message --check {mac}"
+    )
+    .ignore_stderr()
+    .run();
+    assert!(res.is_err(), "should not match with a different key");
+    let res = cmd!(sh, "{VEIL_PATH} mac --key {key_path} -i {message_file} --check {mac}")
+        .ignore_stderr()
+        .run();
+    assert!(res.is_err(), "should not match with different metadata");
+
+    // A MAC is not a digest with the key as metadata.
+    let digest =
+        cmd!(sh, "{VEIL_PATH} digest -i {message_file} -m 'our special secret' -m message")
+            .read()?;
+    assert_ne!(mac, digest);
+
+    Ok(())
+}
+
+#[test]
+fn split_and_combine_secret_key() -> Result<()> {
+    let sh = Shell::new()?;
+    let dir = sh.create_temp_dir()?;
+
+    // Alice picks a passphrase.
+    let alice_passphrase = "excelsior";
+
+    // Alice generates a secret key.
+    let secret_key_path = &dir.path().join("secret-key-a");
+    veil_cmd!(
+        sh,
+        "secret-key -o {secret_key_path:?} --time-cost=0 --memory-cost=0",
+        alice_passphrase
+    )
+    .run()?;
+
+    // Alice generates a public key.
+    let public_key_path = &dir.path().join("public-key-a");
+    veil_cmd!(sh, "public-key -k {secret_key_path:?} -o {public_key_path:?}", alice_passphrase)
+        .run()?;
+
+    // Alice splits her secret key into five shares.
+    let shares_path = &dir.path().join("shares");
+    veil_cmd!(
+        sh,
+        "secret-key split -k {secret_key_path:?} --shares 5 --threshold 3 -o {shares_path:?}",
+        alice_passphrase
+    )
+    .run()?;
+
+    // Alice loses her secret key, but recovers three of the shares.
+    let shares = fs::read_to_string(shares_path)?;
+    let shares = shares.lines().collect::<Vec<_>>();
+    assert_eq!(5, shares.len(), "invalid number of shares");
+    let share_path_a = &dir.path().join("share-a");
+    fs::write(share_path_a, shares[4])?;
+    let share_path_b = &dir.path().join("share-b");
+    fs::write(share_path_b, format!("{}\n{}\n", shares[0], shares[2]))?;
+
+    // Alice combines the shares into a new secret key file.
+    let recovered_path = &dir.path().join("secret-key-recovered");
+    veil_cmd!(
+        sh,
+        "secret-key combine -i {share_path_a:?} -i {share_path_b:?} -o {recovered_path:?} --time-cost=0 --memory-cost=0",
+        alice_passphrase
+    )
+    .run()?;
+
+    // Alice's recovered secret key has the same public key.
+    let recovered_public_key_path = &dir.path().join("public-key-recovered");
+    veil_cmd!(
+        sh,
+        "public-key -k {recovered_path:?} -o {recovered_public_key_path:?}",
+        alice_passphrase
+    )
+    .run()?;
+    assert_eq!(
+        fs::read_to_string(public_key_path)?,
+        fs::read_to_string(recovered_public_key_path)?,
+        "invalid recovered public key"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn export_and_import_mnemonic() -> Result<()> {
+    let sh = Shell::new()?;
+    let dir = sh.create_temp_dir()?;
+
+    // Alice picks a passphrase.
+    let alice_passphrase = "excelsior";
+
+    // Alice generates a secret key.
+    let secret_key_path = &dir.path().join("secret-key-a");
+    veil_cmd!(
+        sh,
+        "secret-key -o {secret_key_path:?} --time-cost=0 --memory-cost=0",
+        alice_passphrase
+    )
+    .run()?;
+
+    // Alice generates a public key.
+    let public_key_path = &dir.path().join("public-key-a");
+    veil_cmd!(sh, "public-key -k {secret_key_path:?} -o {public_key_path:?}", alice_passphrase)
+        .run()?;
+
+    // Alice exports her secret key as a mnemonic.
+    let mnemonic_path = &dir.path().join("mnemonic");
+    veil_cmd!(
+        sh,
+        "secret-key export-mnemonic -k {secret_key_path:?} -o {mnemonic_path:?}",
+        alice_passphrase
+    )
+    .run()?;
+
+    // Alice imports her secret key from the mnemonic on another machine.
+    let imported_path = &dir.path().join("secret-key-imported");
+    veil_cmd!(
+        sh,
+        "secret-key import-mnemonic -i {mnemonic_path:?} -o {imported_path:?} --time-cost=0 --memory-cost=0",
+        alice_passphrase
+    )
+    .run()?;
+
+    // Alice's imported secret key has the same public key.
+    let imported_public_key_path = &dir.path().join("public-key-imported");
+    veil_cmd!(
+        sh,
+        "public-key -k {imported_path:?} -o {imported_public_key_path:?}",
+        alice_passphrase
+    )
+    .run()?;
+    assert_eq!(
+        fs::read_to_string(public_key_path)?,
+        fs::read_to_string(imported_public_key_path)?,
+        "invalid imported public key"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn derive_child_secret_key() -> Result<()> {
+    let sh = Shell::new()?;
+    let dir = sh.create_temp_dir()?;
+
+    // Alice picks a passphrase.
+    let alice_passphrase = "excelsior";
+
+    // Alice generates a master secret key.
+    let secret_key_path = &dir.path().join("secret-key-a");
+    veil_cmd!(
+        sh,
+        "secret-key -o {secret_key_path:?} --time-cost=0 --memory-cost=0",
+        alice_passphrase
+    )
+    .run()?;
+
+    // Alice derives a child key for work.
+    let child_path = &dir.path().join("secret-key-work");
+    veil_cmd!(
+        sh,
+        "secret-key derive -k {secret_key_path:?} -l work/2026 -o {child_path:?} --time-cost=0 --memory-cost=0",
+        alice_passphrase
+    )
+    .run()?;
+
+    // Alice derives the same child key again, e.g. after restoring her master key from a backup.
+    let child_path_p = &dir.path().join("secret-key-work-restored");
+    veil_cmd!(
+        sh,
+        "secret-key derive -k {secret_key_path:?} -l work/2026 -o {child_path_p:?} --time-cost=0 --memory-cost=0",
+        alice_passphrase
+    )
+    .run()?;
+
+    // Both child keys have the same public key, which differs from the master public key.
+    let public_key_path = &dir.path().join("public-key-a");
+    veil_cmd!(sh, "public-key -k {secret_key_path:?} -o {public_key_path:?}", alice_passphrase)
+        .run()?;
+    let child_public_key_path = &dir.path().join("public-key-work");
+    veil_cmd!(sh, "public-key -k {child_path:?} -o {child_public_key_path:?}", alice_passphrase)
+        .run()?;
+    let child_public_key_path_p = &dir.path().join("public-key-work-restored");
+    veil_cmd!(
+        sh,
+        "public-key -k {child_path_p:?} -o {child_public_key_path_p:?}",
+        alice_passphrase
+    )
+    .run()?;
+    let child_public_key = fs::read_to_string(child_public_key_path)?;
+    assert_eq!(
+        child_public_key,
+        fs::read_to_string(child_public_key_path_p)?,
+        "child derivation should be deterministic"
+    );
+    assert_ne!(
+        child_public_key,
+        fs::read_to_string(public_key_path)?,
+        "child key should differ from master key"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn extract_signing_and_decryption_keys() -> Result<()> {
+    let sh = Shell::new()?;
+    let dir = sh.create_temp_dir()?;
+
+    // Alice picks a passphrase.
+    let alice_passphrase = "excelsior";
+
+    // Alice generates a secret key and a public key.
+    let secret_key_path = &dir.path().join("secret-key-a");
+    veil_cmd!(
+        sh,
+        "secret-key -o {secret_key_path:?} --time-cost=0 --memory-cost=0",
+        alice_passphrase
+    )
+    .run()?;
+    let public_key_path = &dir.path().join("public-key-a");
+    veil_cmd!(sh, "public-key -k {secret_key_path:?} -o {public_key_path:?}", alice_passphrase)
+        .run()?;
+
+    // Alice extracts a signing-only key for her build server.
+    let signing_key_path = &dir.path().join("signing-key-a");
+    veil_cmd!(
+        sh,
+        "secret-key extract -k {secret_key_path:?} --purpose sign -o {signing_key_path:?} --time-cost=0 --memory-cost=0",
+        alice_passphrase
+    )
+    .run()?;
+
+    // The build server exports its verifying key.
+    let verifying_key_path = &dir.path().join("verifying-key-a");
+    veil_cmd!(sh, "public-key -k {signing_key_path:?} -o {verifying_key_path:?}", alice_passphrase)
+        .run()?;
+
+    // The build server signs a message.
+    let message_file = &dir.path().join("message");
+    fs::write(message_file, "this is a public message")?;
+    let sig_file = &dir.path().join("message.sig");
+    veil_cmd!(
+        sh,
+        "sign -k {signing_key_path:?} -i {message_file:?} -o {sig_file:?}",
+        alice_passphrase
+    )
+    .run()?;
+
+    // Bea verifies the signature with either Alice's public key or verifying key.
+    cmd!(
+        sh,
+        "{VEIL_PATH} verify --signer {public_key_path} -i {message_file} --signature {sig_file}"
+    )
+    .run()?;
+    cmd!(
+        sh,
+        "{VEIL_PATH} verify --signer {verifying_key_path} -i {message_file} --signature {sig_file}"
+    )
+    .run()?;
+
+    // Alice extracts a decryption-only key for her mail server.
+    let decryption_key_path = &dir.path().join("decryption-key-a");
+    veil_cmd!(
+        sh,
+        "secret-key extract -k {secret_key_path:?} --purpose decrypt -o {decryption_key_path:?} --time-cost=0 --memory-cost=0",
+        alice_passphrase
+    )
+    .run()?;
+
+    // Alice encrypts a message to herself.
+    let ciphertext_path = &dir.path().join("message.veil");
+    veil_cmd!(
+        sh,
+        "encrypt -k {secret_key_path:?} -i {message_file:?} -o {ciphertext_path:?} -r {public_key_path:?}",
+        alice_passphrase
+    )
+    .run()?;
+
+    // The mail server decrypts the message.
+    let plaintext_path = &dir.path().join("message.txt");
+    veil_cmd!(
+        sh,
+        "decrypt -k {decryption_key_path:?} -i {ciphertext_path:?} -o {plaintext_path:?} -s {public_key_path:?}",
+        alice_passphrase
+    )
+    .run()?;
+    assert_eq!("this is a public message", fs::read_to_string(plaintext_path)?);
+
+    // The signing-only key can't decrypt messages.
+    assert!(veil_cmd!(
+        sh,
+        "decrypt -k {signing_key_path:?} -i {ciphertext_path:?} -o {plaintext_path:?} -s {public_key_path:?}",
+        alice_passphrase
+    )
+    .quiet()
+    .ignore_stderr()
+    .run()
+    .is_err());
+
+    Ok(())
+}
+
+#[test]
+fn generate_signing_key() -> Result<()> {
+    let sh = Shell::new()?;
+    let dir = sh.create_temp_dir()?;
+
+    // The build server generates its own signing-only key and exports its verifying key.
+    let passphrase = "excelsior";
+    let signing_key_path = &dir.path().join("signing-key");
+    veil_cmd!(
+        sh,
+        "secret-key -o {signing_key_path:?} --purpose sign --time-cost=0 --memory-cost=0",
+        passphrase
+    )
+    .run()?;
+    let verifying_key_path = &dir.path().join("verifying-key");
+    veil_cmd!(sh, "public-key -k {signing_key_path:?} -o {verifying_key_path:?}", passphrase)
+        .run()?;
+
+    // The build server signs a message, and Bea verifies it with the verifying key.
+    let message_file = &dir.path().join("message");
+    fs::write(message_file, "this is a public message")?;
+    let sig_file = &dir.path().join("message.sig");
+    veil_cmd!(sh, "sign -k {signing_key_path:?} -i {message_file:?} -o {sig_file:?}", passphrase)
+        .run()?;
+    cmd!(
+        sh,
+        "{VEIL_PATH} verify --signer {verifying_key_path} -i {message_file} --signature {sig_file}"
+    )
+    .run()?;
+
+    // Decryption-only keys have no public key to encrypt to, so they can't be generated.
+    let decryption_key_path = &dir.path().join("decryption-key");
+    assert!(veil_cmd!(
+        sh,
+        "secret-key -o {decryption_key_path:?} --purpose decrypt --time-cost=0 --memory-cost=0",
+        passphrase
+    )
+    .quiet()
+    .ignore_stderr()
+    .run()
+    .is_err());
+    assert!(!decryption_key_path.exists(), "should not create a decryption key file");
+
+    Ok(())
+}
+
+#[test]
+fn invalid_kdf_costs() -> Result<()> {
+    let sh = Shell::new()?;
+    let dir = sh.create_temp_dir()?;
+
+    // Alice asks for more memory than the KDF allows.
+    let secret_key_path = &dir.path().join("secret-key-a");
+    let output = veil_cmd!(
+        sh,
+        "secret-key -o {secret_key_path:?} --kdf argon2id --memory-cost=32",
+        "excelsior"
+    )
+    .ignore_status()
+    .output()?;
+    assert!(!output.status.success(), "should reject out-of-range KDF costs");
+    assert!(String::from_utf8(output.stderr)?.contains("KDF costs are out of range"));
+    assert!(!secret_key_path.exists(), "should not create a secret key file");
+
+    Ok(())
+}
+
+#[test]
+fn encrypt_to_self() -> Result<()> {
+    let sh = Shell::new()?;
+    let dir = sh.create_temp_dir()?;
+
+    // Alice and Bea generate secret keys and public keys.
+    let alice_passphrase = "excelsior";
+    let secret_key_path_a = &dir.path().join("secret-key-a");
+    veil_cmd!(
+        sh,
+        "secret-key -o {secret_key_path_a:?} --time-cost=0 --memory-cost=0",
+        alice_passphrase
+    )
+    .run()?;
+    let public_key_path_a = &dir.path().join("public-key-a");
+    veil_cmd!(sh, "public-key -k {secret_key_path_a:?} -o {public_key_path_a:?}", alice_passphrase)
+        .run()?;
+
+    let bea_passphrase = "dingus";
+    let secret_key_path_b = &dir.path().join("secret-key-b");
+    veil_cmd!(
+        sh,
+        "secret-key -o {secret_key_path_b:?} --time-cost=0 --memory-cost=0",
+        bea_passphrase
+    )
+    .run()?;
+    let public_key_path_b = &dir.path().join("public-key-b");
+    veil_cmd!(sh, "public-key -k {secret_key_path_b:?} -o {public_key_path_b:?}", bea_passphrase)
+        .run()?;
+
+    // Alice encrypts a message for Bea and herself.
+    let message_file = &dir.path().join("message");
+    fs::write(message_file, "this is a secret message")?;
+    let ciphertext_path = &dir.path().join("message.veil");
+    veil_cmd!(
+        sh,
+        "encrypt -k {secret_key_path_a:?} -i {message_file:?} -o {ciphertext_path:?} -r {public_key_path_b:?} --self",
+        alice_passphrase
+    )
+    .run()?;
+
+    // Alice decrypts her sent message.
+    let plaintext_path = &dir.path().join("message.txt");
+    veil_cmd!(
+        sh,
+        "decrypt -k {secret_key_path_a:?} -i {ciphertext_path:?} -o {plaintext_path:?} -s {public_key_path_a:?}",
+        alice_passphrase
+    )
+    .run()?;
+    assert_eq!("this is a secret message", fs::read_to_string(plaintext_path)?);
+
+    // Alice always encrypts to herself, except when she opts out.
+    let _env = sh.push_env("VEIL_ENCRYPT_TO_SELF", "true");
+    veil_cmd!(
+        sh,
+        "encrypt -k {secret_key_path_a:?} -i {message_file:?} -o {ciphertext_path:?} -r {public_key_path_b:?} --no-self",
+        alice_passphrase
+    )
+    .run()?;
+    assert!(veil_cmd!(
+        sh,
+        "decrypt -k {secret_key_path_a:?} -i {ciphertext_path:?} -o {plaintext_path:?} -s {public_key_path_a:?}",
+        alice_passphrase
+    )
+    .quiet()
+    .ignore_stderr()
+    .run()
+    .is_err());
+
+    Ok(())
+}
+
+#[test]
+fn encrypt_compressed() -> Result<()> {
+    let sh = Shell::new()?;
+    let dir = sh.create_temp_dir()?;
+
+    // Alice generates a secret key and public key.
+    let alice_passphrase = "excelsior";
+    let secret_key_path_a = &dir.path().join("secret-key-a");
+    veil_cmd!(
+        sh,
+        "secret-key -o {secret_key_path_a:?} --time-cost=0 --memory-cost=0",
+        alice_passphrase
+    )
+    .run()?;
+    let public_key_path_a = &dir.path().join("public-key-a");
+    veil_cmd!(sh, "public-key -k {secret_key_path_a:?} -o {public_key_path_a:?}", alice_passphrase)
+        .run()?;
+
+    // Alice compresses and encrypts some logs for herself.
+    let message = "{\"level\":\"info\",\"msg\":\"all is well\"}\n".repeat(10_000);
+    let message_file = &dir.path().join("message");
+    fs::write(message_file, &message)?;
+    let ciphertext_path = &dir.path().join("message.veil");
+    veil_cmd!(
+        sh,
+        "encrypt -k {secret_key_path_a:?} -i {message_file:?} -o {ciphertext_path:?} -r {public_key_path_a:?} --compress",
+        alice_passphrase
+    )
+    .run()?;
+    assert!(fs::metadata(ciphertext_path)?.len() < message.len() as u64 / 10);
+
+    // Alice decrypts the message.
+    let plaintext_path = &dir.path().join("message.txt");
+    veil_cmd!(
+        sh,
+        "decrypt -k {secret_key_path_a:?} -i {ciphertext_path:?} -o {plaintext_path:?} -s {public_key_path_a:?}",
+        alice_passphrase
+    )
+    .run()?;
+    assert_eq!(message, fs::read_to_string(plaintext_path)?);
+
+    Ok(())
+}
+
+#[test]
+fn encrypt_with_metadata() -> Result<()> {
+    let sh = Shell::new()?;
+    let dir = sh.create_temp_dir()?;
+
+    // Alice generates a secret key and public key.
+    let alice_passphrase = "excelsior";
+    let secret_key_path_a = &dir.path().join("secret-key-a");
+    veil_cmd!(
+        sh,
+        "secret-key -o {secret_key_path_a:?} --time-cost=0 --memory-cost=0",
+        alice_passphrase
+    )
+    .run()?;
+    let public_key_path_a = &dir.path().join("public-key-a");
+    veil_cmd!(sh, "public-key -k {secret_key_path_a:?} -o {public_key_path_a:?}", alice_passphrase)
+        .run()?;
+
+    // Alice encrypts a file for herself, including its name and modification time.
+    let message = "the quarterly numbers are in";
+    let message_file = &dir.path().join("report.txt");
+    fs::write(message_file, message)?;
+    let mtime = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000_000);
+    fs::File::options().write(true).open(message_file)?.set_modified(mtime)?;
+    let ciphertext_path = &dir.path().join("report.veil");
+    veil_cmd!(
+        sh,
+        "encrypt -k {secret_key_path_a:?} -i {message_file:?} -o {ciphertext_path:?} -r {public_key_path_a:?} --metadata",
+        alice_passphrase
+    )
+    .run()?;
+
+    // Alice decrypts the message into a directory, restoring the file's name and mtime.
+    let output_dir = &dir.path().join("out");
+    fs::create_dir(output_dir)?;
+    veil_cmd!(
+        sh,
+        "decrypt -k {secret_key_path_a:?} -i {ciphertext_path:?} -o {output_dir:?} -s {public_key_path_a:?} --restore-metadata",
+        alice_passphrase
+    )
+    .run()?;
+    let restored = &output_dir.join("report.txt");
+    assert_eq!(message, fs::read_to_string(restored)?);
+    assert_eq!(mtime, fs::metadata(restored)?.modified()?);
+    assert_eq!(1, fs::read_dir(output_dir)?.count(), "no temporary files should remain");
+
+    // Decrypting again refuses to overwrite the restored file.
+    let res = veil_cmd!(
+        sh,
+        "decrypt -k {secret_key_path_a:?} -i {ciphertext_path:?} -o {output_dir:?} -s {public_key_path_a:?} --restore-metadata",
+        alice_passphrase
+    )
+    .ignore_stderr()
+    .run();
+    assert!(res.is_err(), "should not overwrite an existing file");
+    assert_eq!(1, fs::read_dir(output_dir)?.count(), "no temporary files should remain");
+
+    Ok(())
+}
+
+#[test]
+fn encrypt_and_decrypt_a_directory() -> Result<()> {
+    let sh = Shell::new()?;
+    let dir = sh.create_temp_dir()?;
+
+    // Alice generates a secret key and public key.
+    let alice_passphrase = "excelsior";
+    let secret_key_path_a = &dir.path().join("secret-key-a");
+    veil_cmd!(
+        sh,
+        "secret-key -o {secret_key_path_a:?} --time-cost=0 --memory-cost=0",
+        alice_passphrase
+    )
+    .run()?;
+    let public_key_path_a = &dir.path().join("public-key-a");
+    veil_cmd!(sh, "public-key -k {secret_key_path_a:?} -o {public_key_path_a:?}", alice_passphrase)
+        .run()?;
+
+    // Alice encrypts a directory tree for herself.
+    let input_dir = &dir.path().join("project");
+    fs::create_dir_all(input_dir.join("src"))?;
+    fs::write(input_dir.join("README"), "read me")?;
+    fs::write(input_dir.join("src/main.rs"), "fn main() {}")?;
+    let ciphertext_path = &dir.path().join("project.veil");
+    veil_cmd!(
+        sh,
+        "encrypt-dir -k {secret_key_path_a:?} -i {input_dir:?} -o {ciphertext_path:?} -r {public_key_path_a:?} --compress",
+        alice_passphrase
+    )
+    .run()?;
+
+    // Alice decrypts it into a new directory.
+    let output_dir = &dir.path().join("restored");
+    veil_cmd!(
+        sh,
+        "decrypt-dir -k {secret_key_path_a:?} -i {ciphertext_path:?} -o {output_dir:?} -s {public_key_path_a:?}",
+        alice_passphrase
+    )
+    .run()?;
+    assert_eq!("read me", fs::read_to_string(output_dir.join("README"))?);
+    assert_eq!("fn main() {}", fs::read_to_string(output_dir.join("src/main.rs"))?);
+
+    // Decrypting into a non-empty directory fails.
+    let res = veil_cmd!(
+        sh,
+        "decrypt-dir -k {secret_key_path_a:?} -i {ciphertext_path:?} -o {output_dir:?} -s {public_key_path_a:?}",
+        alice_passphrase
+    )
+    .ignore_stderr()
+    .run();
+    assert!(res.is_err(), "should not extract into a non-empty directory");
+
+    Ok(())
+}
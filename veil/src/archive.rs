@@ -0,0 +1,486 @@
+//! Streaming archives of directory trees.
+//!
+//! An archive is a sequence of entries, each of which is encoded as `LE_32(|H|) ǁ H ǁ D`, where
+//! `H` is the entry's kind followed by its encoded [`FileMetadata`] and `D` is the file's contents,
+//! if any. The name of each entry is its path relative to the archive's root, with components
+//! separated by `/`. Parent directories always precede their contents, and the archive ends with
+//! an entry header of length zero.
+
+use std::{
+    fs::{self, File},
+    io::{self, Read, Take, Write},
+    mem,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+    vec,
+};
+
+use crate::{metadata::is_valid_name, DecryptError, FileMetadata};
+
+/// The maximum length of an encoded entry header.
+const MAX_ENTRY_HEADER_LEN: usize = 64 * 1024;
+
+/// The kind of an archive entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+enum EntryKind {
+    File = 0x00,
+    Directory = 0x01,
+}
+
+impl TryFrom<u8> for EntryKind {
+    type Error = u8;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        // inline when inline_const_pat lands
+        const FILE: u8 = EntryKind::File as u8;
+        const DIRECTORY: u8 = EntryKind::Directory as u8;
+        match value {
+            FILE => Ok(EntryKind::File),
+            DIRECTORY => Ok(EntryKind::Directory),
+            _ => Err(value),
+        }
+    }
+}
+
+/// A reader which produces an archive of a directory tree, reading files as it goes.
+///
+/// Entries are visited depth-first in lexicographic order. Anything other than regular files and
+/// directories (e.g. symbolic links) is skipped.
+pub struct Archiver {
+    stack: Vec<vec::IntoIter<(String, PathBuf)>>,
+    buf: Vec<u8>,
+    pos: usize,
+    file: Option<Take<File>>,
+    done: bool,
+}
+
+impl Archiver {
+    /// Creates an archiver for the directory tree at `root`.
+    pub fn new(root: &Path) -> io::Result<Archiver> {
+        Ok(Archiver {
+            stack: vec![children(root, "")?],
+            buf: Vec::new(),
+            pos: 0,
+            file: None,
+            done: false,
+        })
+    }
+
+    /// Moves to the next entry, buffering its header and opening its contents, if any. Returns
+    /// `false` if there are no more entries.
+    fn next_entry(&mut self) -> io::Result<bool> {
+        while let Some(entries) = self.stack.last_mut() {
+            let Some((name, path)) = entries.next() else {
+                self.stack.pop();
+                continue;
+            };
+
+            let metadata = fs::symlink_metadata(&path)?;
+            let kind = if metadata.is_dir() {
+                self.stack.push(children(&path, &name)?);
+                EntryKind::Directory
+            } else if metadata.is_file() {
+                self.file = Some(File::open(&path)?.take(metadata.len()));
+                EntryKind::File
+            } else {
+                continue;
+            };
+
+            let size = if kind == EntryKind::File { metadata.len() } else { 0 };
+            let entry = FileMetadata {
+                name,
+                size,
+                mode: mode(&metadata),
+                mtime: metadata.modified().map_or(0, to_unix_time),
+            };
+            self.buf_entry(kind, &entry)?;
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    /// Buffers an encoded entry header.
+    fn buf_entry(&mut self, kind: EntryKind, entry: &FileMetadata) -> io::Result<()> {
+        self.buf.clear();
+        self.pos = 0;
+
+        let encoded = entry.encode();
+        if encoded.len() + 1 > MAX_ENTRY_HEADER_LEN {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "path too long"));
+        }
+        let len = u32::try_from(encoded.len() + 1).expect("should be <= MAX_ENTRY_HEADER_LEN");
+        self.buf.extend_from_slice(&len.to_le_bytes());
+        self.buf.push(kind as u8);
+        self.buf.extend_from_slice(&encoded);
+        Ok(())
+    }
+}
+
+impl Read for Archiver {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        loop {
+            // Return buffered header bytes first.
+            if self.pos < self.buf.len() {
+                let n = out.len().min(self.buf.len() - self.pos);
+                out[..n].copy_from_slice(&self.buf[self.pos..self.pos + n]);
+                self.pos += n;
+                return Ok(n);
+            }
+
+            // Then the contents of the current file, which must not have shrunk.
+            if let Some(file) = &mut self.file {
+                let n = file.read(out)?;
+                if n > 0 || out.is_empty() {
+                    return Ok(n);
+                }
+                if file.limit() > 0 {
+                    return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "file truncated"));
+                }
+                self.file = None;
+            }
+
+            // Then move on to the next entry, ending the archive after the last one.
+            if !self.next_entry()? {
+                if mem::replace(&mut self.done, true) {
+                    return Ok(0);
+                }
+
+                // Buffer the end-of-archive marker.
+                self.buf.clear();
+                self.buf.extend_from_slice(&0u32.to_le_bytes());
+                self.pos = 0;
+            }
+        }
+    }
+}
+
+/// Returns the sorted children of the directory at `path`, along with their archive names.
+fn children(path: &Path, prefix: &str) -> io::Result<vec::IntoIter<(String, PathBuf)>> {
+    let mut children = fs::read_dir(path)?
+        .map(|entry| {
+            let entry = entry?;
+            let name = entry.file_name().into_string().ok().filter(|n| is_valid_name(n));
+            let name = name.ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidData, "unsupported file name")
+            })?;
+            let name = if prefix.is_empty() { name } else { format!("{prefix}/{name}") };
+            Ok((name, entry.path()))
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+    children.sort();
+    Ok(children.into_iter())
+}
+
+/// Returns the Unix permission bits of a file.
+fn mode(metadata: &fs::Metadata) -> u32 {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        metadata.permissions().mode() & 0o777
+    }
+    #[cfg(not(unix))]
+    if metadata.permissions().readonly() {
+        0o555
+    } else {
+        0o755
+    }
+}
+
+/// Converts a system time to seconds since the Unix epoch.
+fn to_unix_time(t: SystemTime) -> i64 {
+    match t.duration_since(UNIX_EPOCH) {
+        Ok(d) => i64::try_from(d.as_secs()).unwrap_or(i64::MAX),
+        Err(e) => i64::try_from(e.duration().as_secs()).map_or(i64::MIN, |s| -s),
+    }
+}
+
+/// Converts seconds since the Unix epoch to a system time, if representable.
+fn from_unix_time(t: i64) -> Option<SystemTime> {
+    let offset = Duration::from_secs(t.unsigned_abs());
+    if t < 0 {
+        UNIX_EPOCH.checked_sub(offset)
+    } else {
+        UNIX_EPOCH.checked_add(offset)
+    }
+}
+
+/// The parsing state of an [`Extractor`].
+enum State {
+    /// Reading the length of an entry header.
+    Len,
+    /// Reading an entry header of the given length.
+    Header(usize),
+    /// Writing the remaining bytes of a file.
+    Data(File, u64, FileMetadata),
+    /// The end of the archive has been reached.
+    End,
+}
+
+/// A writer which extracts an archive into a directory.
+///
+/// Every entry's path is checked to be a relative path of valid names before anything is created,
+/// and existing files are never overwritten, so entries can't be written outside of the directory.
+pub struct Extractor {
+    root: PathBuf,
+    state: State,
+    buf: Vec<u8>,
+    dirs: Vec<(PathBuf, FileMetadata)>,
+    invalid: bool,
+}
+
+impl Extractor {
+    /// Creates an extractor which writes to the directory at `root`, creating it if necessary.
+    ///
+    /// Returns an error if `root` exists and is not an empty directory.
+    pub fn new(root: &Path) -> io::Result<Extractor> {
+        fs::create_dir_all(root)?;
+        if fs::read_dir(root)?.next().is_some() {
+            return Err(io::Error::new(io::ErrorKind::AlreadyExists, "directory is not empty"));
+        }
+        Ok(Extractor {
+            root: root.to_path_buf(),
+            state: State::Len,
+            buf: Vec::with_capacity(size_of::<u32>()),
+            dirs: Vec::new(),
+            invalid: false,
+        })
+    }
+
+    /// Returns `true` if the archive was found to be invalid.
+    pub const fn is_invalid(&self) -> bool {
+        self.invalid
+    }
+
+    /// Finishes extraction, applying directory metadata. Returns
+    /// [`DecryptError::InvalidArchive`] if the archive was incomplete.
+    pub fn finish(self) -> Result<(), DecryptError> {
+        if !matches!(self.state, State::End) {
+            return Err(DecryptError::InvalidArchive);
+        }
+
+        // Apply directory metadata deepest-first, after all of their contents have been written.
+        for (path, metadata) in self.dirs.iter().rev() {
+            File::open(path)
+                .and_then(|dir| apply_metadata(&dir, metadata))
+                .map_err(DecryptError::WriteIo)?;
+        }
+        Ok(())
+    }
+
+    /// Marks the archive as invalid and returns an error.
+    fn invalid(&mut self) -> io::Error {
+        self.invalid = true;
+        io::Error::new(io::ErrorKind::InvalidData, "invalid archive")
+    }
+
+    /// Buffers bytes from `input` until `len` bytes have been buffered, returning the remaining
+    /// input if so.
+    fn fill<'a>(&mut self, input: &'a [u8], len: usize) -> Option<&'a [u8]> {
+        let n = input.len().min(len - self.buf.len());
+        self.buf.extend_from_slice(&input[..n]);
+        (self.buf.len() == len).then_some(&input[n..])
+    }
+
+    /// Decodes a buffered entry header, creating the entry.
+    fn create_entry(&mut self) -> io::Result<State> {
+        let header = mem::take(&mut self.buf);
+        let (&kind, entry) = header.split_first().ok_or_else(|| self.invalid())?;
+        let kind = EntryKind::try_from(kind).map_err(|_| self.invalid())?;
+        let entry = FileMetadata::decode_unchecked(entry)
+            .filter(FileMetadata::has_valid_path)
+            .ok_or_else(|| self.invalid())?;
+        let path = entry.name.split('/').fold(self.root.clone(), |path, name| path.join(name));
+
+        match kind {
+            EntryKind::Directory => {
+                fs::create_dir(&path)?;
+                self.dirs.push((path, entry));
+                Ok(State::Len)
+            }
+            EntryKind::File => {
+                let file = File::create_new(&path)?;
+                if entry.size == 0 {
+                    apply_metadata(&file, &entry)?;
+                    return Ok(State::Len);
+                }
+                let size = entry.size;
+                Ok(State::Data(file, size, entry))
+            }
+        }
+    }
+}
+
+impl Write for Extractor {
+    fn write(&mut self, input: &[u8]) -> io::Result<usize> {
+        let mut rest = input;
+        while !rest.is_empty() {
+            match mem::replace(&mut self.state, State::Len) {
+                State::Len => match self.fill(rest, size_of::<u32>()) {
+                    Some(r) => {
+                        rest = r;
+                        let len = u32::from_le_bytes(
+                            mem::take(&mut self.buf).try_into().expect("should be 4 bytes"),
+                        ) as usize;
+                        self.state = match len {
+                            0 => State::End,
+                            _ if len > MAX_ENTRY_HEADER_LEN => return Err(self.invalid()),
+                            _ => State::Header(len),
+                        };
+                    }
+                    None => rest = &[],
+                },
+                State::Header(len) => match self.fill(rest, len) {
+                    Some(r) => {
+                        rest = r;
+                        self.state = self.create_entry()?;
+                    }
+                    None => {
+                        rest = &[];
+                        self.state = State::Header(len);
+                    }
+                },
+                State::Data(mut file, remaining, entry) => {
+                    let n = rest.len().min(usize::try_from(remaining).unwrap_or(usize::MAX));
+                    file.write_all(&rest[..n])?;
+                    rest = &rest[n..];
+                    let remaining = remaining - n as u64;
+                    if remaining == 0 {
+                        apply_metadata(&file, &entry)?;
+                    } else {
+                        self.state = State::Data(file, remaining, entry);
+                    }
+                }
+                State::End => {
+                    self.state = State::End;
+                    return Err(self.invalid());
+                }
+            }
+        }
+        Ok(input.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Applies the modification time and permissions in `metadata` to an extracted file or directory.
+fn apply_metadata(file: &File, metadata: &FileMetadata) -> io::Result<()> {
+    if let Some(mtime) = from_unix_time(metadata.mtime) {
+        file.set_modified(mtime)?;
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(fs::Permissions::from_mode(metadata.mode & 0o777))?;
+    }
+    #[cfg(not(unix))]
+    {
+        let mut permissions = file.metadata()?.permissions();
+        permissions.set_readonly(metadata.mode & 0o200 == 0);
+        file.set_permissions(permissions)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_matches::assert_matches;
+    use tempfile::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let src = tree();
+        let mut archive = Vec::new();
+        Archiver::new(src.path())
+            .expect("should open")
+            .read_to_end(&mut archive)
+            .expect("should read");
+
+        let dst = TempDir::new().expect("should create temp dir");
+        let root = dst.path().join("out");
+        let mut extractor = Extractor::new(&root).expect("should create extractor");
+        for chunk in archive.chunks(7) {
+            extractor.write_all(chunk).expect("should extract");
+        }
+        extractor.finish().expect("should finish");
+
+        assert_eq!(b"alpha".to_vec(), fs::read(root.join("a.txt")).expect("should read"));
+        assert_eq!(b"".to_vec(), fs::read(root.join("empty")).expect("should read"));
+        assert_eq!(vec![7u8; 100_000], fs::read(root.join("sub/deep/b.bin")).expect("should read"));
+        assert_eq!(
+            from_unix_time(1_000_000_000),
+            fs::metadata(root.join("a.txt")).and_then(|m| m.modified()).ok(),
+            "modification time should be restored"
+        );
+    }
+
+    #[test]
+    fn path_traversal() {
+        for name in ["../escape", "/etc/passwd", "sub/../../escape", ""] {
+            let dst = TempDir::new().expect("should create temp dir");
+            let root = dst.path().join("out");
+            let mut extractor = Extractor::new(&root).expect("should create extractor");
+
+            let entry = FileMetadata { name: name.into(), size: 1, mode: 0o644, mtime: 0 };
+            let header = [&[EntryKind::File as u8], entry.encode().as_slice()].concat();
+            let len = u32::try_from(header.len()).expect("should fit");
+            let archive = [len.to_le_bytes().as_slice(), &header, b"x"].concat();
+
+            assert_matches!(extractor.write_all(&archive), Err(e) if e.kind() == io::ErrorKind::InvalidData);
+            assert!(extractor.is_invalid(), "{name:?} should be invalid");
+            assert!(!dst.path().join("escape").exists(), "{name:?} should not be extracted");
+        }
+    }
+
+    #[test]
+    fn truncated() {
+        let src = tree();
+        let mut archive = Vec::new();
+        Archiver::new(src.path())
+            .expect("should open")
+            .read_to_end(&mut archive)
+            .expect("should read");
+
+        let dst = TempDir::new().expect("should create temp dir");
+        let mut extractor = Extractor::new(dst.path()).expect("should create extractor");
+        extractor.write_all(&archive[..archive.len() - 4]).expect("should extract");
+        assert_matches!(extractor.finish(), Err(DecryptError::InvalidArchive));
+    }
+
+    #[test]
+    fn trailing_data() {
+        let dst = TempDir::new().expect("should create temp dir");
+        let mut extractor = Extractor::new(dst.path()).expect("should create extractor");
+        assert_matches!(extractor.write_all(&[0, 0, 0, 0, 1]), Err(_));
+        assert!(extractor.is_invalid(), "should be invalid");
+    }
+
+    #[test]
+    fn non_empty_dir() {
+        let dst = tree();
+        assert_matches!(
+            Extractor::new(dst.path()).map(|_| ()),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists
+        );
+    }
+
+    fn tree() -> TempDir {
+        let dir = TempDir::new().expect("should create temp dir");
+        fs::write(dir.path().join("a.txt"), b"alpha").expect("should write");
+        File::options()
+            .write(true)
+            .open(dir.path().join("a.txt"))
+            .and_then(|f| f.set_modified(from_unix_time(1_000_000_000).expect("should be valid")))
+            .expect("should set mtime");
+        fs::write(dir.path().join("empty"), b"").expect("should write");
+        fs::create_dir_all(dir.path().join("sub/deep")).expect("should create dirs");
+        fs::write(dir.path().join("sub/deep/b.bin"), vec![7u8; 100_000]).expect("should write");
+        dir
+    }
+}
@@ -0,0 +1,180 @@
+//! Deflate compression of message plaintexts.
+
+use std::io::{Read, Write};
+
+use flate2::{read::DeflateEncoder, Compression, Decompress, FlushDecompress, Status};
+
+use crate::DecryptError;
+
+/// The size of the buffer used for decompressed output.
+const BUF_LEN: usize = 64 * 1024;
+
+/// Returns a reader which compresses the contents of `reader`.
+pub fn compress(reader: impl Read) -> impl Read {
+    DeflateEncoder::new(reader, Compression::default())
+}
+
+/// A sink which decompresses blocks of compressed plaintext and writes them to a writer.
+///
+/// Unlike [`flate2::write::DeflateDecoder`], a corrupt stream is distinguishable from an error
+/// writing to the inner writer, and the total output can be limited to protect against
+/// decompression bombs.
+pub struct Decompressor<W> {
+    inner: W,
+    state: Decompress,
+    buf: Vec<u8>,
+    done: bool,
+    max_len: Option<u64>,
+}
+
+impl<W: Write> Decompressor<W> {
+    /// Creates a decompressor which writes to `inner` and returns [`DecryptError::LimitExceeded`]
+    /// if it would write more than `max_len` bytes.
+    pub fn new(inner: W, max_len: Option<u64>) -> Decompressor<W> {
+        Decompressor {
+            inner,
+            state: Decompress::new(false),
+            buf: vec![0u8; BUF_LEN],
+            done: false,
+            max_len,
+        }
+    }
+
+    /// Decompresses the given block of compressed data and writes the output.
+    pub fn write(&mut self, mut input: &[u8]) -> Result<(), DecryptError> {
+        while !input.is_empty() {
+            // Data after the end of the stream is invalid.
+            if self.done {
+                return Err(DecryptError::InvalidCiphertext);
+            }
+
+            let (consumed, produced) = self.decompress(input, FlushDecompress::None)?;
+            if consumed == 0 && produced == 0 {
+                return Err(DecryptError::InvalidCiphertext);
+            }
+            input = &input[consumed..];
+        }
+        Ok(())
+    }
+
+    /// Finishes decompression, returning the total number of bytes written.
+    pub fn finish(mut self) -> Result<u64, DecryptError> {
+        while !self.done {
+            // If no more output can be produced, the stream was truncated.
+            let (_, produced) = self.decompress(&[], FlushDecompress::Finish)?;
+            if !self.done && produced == 0 {
+                return Err(DecryptError::InvalidCiphertext);
+            }
+        }
+        Ok(self.state.total_out())
+    }
+
+    /// Decompresses as much of `input` as fits in the buffer and writes the output, returning the
+    /// number of input bytes consumed and output bytes produced.
+    fn decompress(
+        &mut self,
+        input: &[u8],
+        flush: FlushDecompress,
+    ) -> Result<(usize, usize), DecryptError> {
+        let (in_before, out_before) = (self.state.total_in(), self.state.total_out());
+        let status = self
+            .state
+            .decompress(input, &mut self.buf, flush)
+            .map_err(|_| DecryptError::InvalidCiphertext)?;
+        self.done = status == Status::StreamEnd;
+
+        // Stop before writing more output than allowed.
+        if self.max_len.is_some_and(|max| self.state.total_out() > max) {
+            return Err(DecryptError::LimitExceeded);
+        }
+
+        let produced = usize::try_from(self.state.total_out() - out_before)
+            .expect("output should be <= buffer length");
+        self.inner.write_all(&self.buf[..produced]).map_err(DecryptError::WriteIo)?;
+
+        let consumed = usize::try_from(self.state.total_in() - in_before)
+            .expect("consumed should be <= input length");
+        Ok((consumed, produced))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use assert_matches::assert_matches;
+    use rand::{RngCore, SeedableRng};
+    use rand_chacha::ChaChaRng;
+
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let mut rng = ChaChaRng::seed_from_u64(0xDEADBEEF);
+        let mut input = vec![0u8; 300 * 1024];
+        rng.fill_bytes(&mut input[..1024]);
+
+        let mut compressed = Vec::new();
+        compress(Cursor::new(&input)).read_to_end(&mut compressed).expect("should compress");
+        assert!(compressed.len() < input.len() / 10, "should be compressible");
+
+        let mut output = Vec::new();
+        let mut d = Decompressor::new(&mut output, None);
+        for block in compressed.chunks(1000) {
+            d.write(block).expect("should decompress");
+        }
+        assert_eq!(input.len() as u64, d.finish().expect("should finish"));
+        assert_eq!(input, output, "invalid output");
+    }
+
+    #[test]
+    fn truncated() {
+        let mut compressed = Vec::new();
+        compress(Cursor::new(vec![7u8; 10_000]))
+            .read_to_end(&mut compressed)
+            .expect("should compress");
+
+        let mut d = Decompressor::new(Vec::new(), None);
+        d.write(&compressed[..compressed.len() - 2]).expect("should decompress");
+        assert_matches!(d.finish(), Err(DecryptError::InvalidCiphertext));
+    }
+
+    #[test]
+    fn trailing_data() {
+        let mut compressed = Vec::new();
+        compress(Cursor::new(vec![7u8; 10_000]))
+            .read_to_end(&mut compressed)
+            .expect("should compress");
+        compressed.push(0);
+
+        let mut d = Decompressor::new(Vec::new(), None);
+        assert_matches!(d.write(&compressed), Err(DecryptError::InvalidCiphertext));
+    }
+
+    #[test]
+    fn max_len() {
+        let mut compressed = Vec::new();
+        compress(Cursor::new(vec![7u8; 1024 * 1024]))
+            .read_to_end(&mut compressed)
+            .expect("should compress");
+
+        let mut output = Vec::new();
+        let mut d = Decompressor::new(&mut output, Some(1024 * 1024));
+        d.write(&compressed).expect("should decompress");
+        assert_eq!(1024 * 1024, d.finish().expect("should finish"));
+
+        let mut output = Vec::new();
+        let mut d = Decompressor::new(&mut output, Some(100 * 1024));
+        assert_matches!(
+            d.write(&compressed).and_then(|()| d.finish().map(|_| ())),
+            Err(DecryptError::LimitExceeded)
+        );
+        assert!(output.len() <= 100 * 1024, "should stop writing at the limit");
+    }
+
+    #[test]
+    fn corrupt() {
+        let mut d = Decompressor::new(Vec::new(), None);
+        assert_matches!(d.write(&[0xFF; 64]), Err(DecryptError::InvalidCiphertext));
+    }
+}
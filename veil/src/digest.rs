@@ -1,15 +1,22 @@
-use std::{fmt, io, io::Read, str::FromStr};
+use std::{
+    fmt,
+    io::{self, Read, Write},
+    str::FromStr,
+};
 
 use lockstitch::Protocol;
 
-use crate::ParseDigestError;
+use crate::{
+    version::{self, FORMAT_VERSION},
+    ParseDigestError,
+};
 
 /// The length of a [`Digest`] in bytes.
 pub const DIGEST_LEN: usize = 32;
 
 /// The digest of a sequence of metadata values and a message.
 #[derive(Clone, Copy, Debug, Eq)]
-pub struct Digest([u8; DIGEST_LEN]);
+pub struct Digest(pub(crate) [u8; DIGEST_LEN]);
 
 impl Digest {
     /// Create a digest from a sequence of metadata values and a reader.
@@ -18,13 +25,8 @@ impl Digest {
     ///
     /// Returns any error returned by operations on `reader`.
     pub fn new(metadata: &[impl AsRef<[u8]>], mut reader: impl Read) -> io::Result<Digest> {
-        // Initialize a protocol.
-        let mut digest = Protocol::new("veil.digest");
-
-        // Mix the metadata values in order into the protocol.
-        for v in metadata {
-            digest.mix("metadata", v.as_ref());
-        }
+        // Initialize a protocol and mix the metadata into it.
+        let digest = init(metadata);
 
         // Mix the reader contents into the protocol.
         let mut writer = digest.mix_writer("message", io::sink());
@@ -35,6 +37,21 @@ impl Digest {
         Ok(Digest(digest.derive_array("digest")))
     }
 
+    /// Create a digest from a sequence of metadata values and a message slice.
+    ///
+    /// The digest is identical to one created by [`Digest::new`] for the same message.
+    #[must_use]
+    pub fn from_slice(metadata: &[impl AsRef<[u8]>], message: &[u8]) -> Digest {
+        // Initialize a protocol and mix the metadata into it.
+        let mut digest = init(metadata);
+
+        // Mix the message into the protocol.
+        digest.mix("message", message);
+
+        // Derive 32 bytes as a digest.
+        Digest(digest.derive_array("digest"))
+    }
+
     /// Create a digest from a 32-byte slice.
     #[must_use]
     pub fn decode(b: impl AsRef<[u8]>) -> Option<Digest> {
@@ -48,6 +65,130 @@ impl Digest {
     }
 }
 
+/// Initializes a `veil.digest` protocol and mixes the metadata values in order into it.
+fn init(metadata: &[impl AsRef<[u8]>]) -> Protocol {
+    let mut digest = Protocol::new("veil.digest");
+    for v in metadata {
+        digest.mix("metadata", v.as_ref());
+    }
+    digest
+}
+
+/// The length of the chunks a [`Hasher`] mixes the message in.
+const CHUNK_LEN: usize = 8 * 1024;
+
+/// The digest of a sequence of metadata values and a message created by a [`Hasher`].
+///
+/// Hasher digests use a different construction than [`Digest`], so the two are distinct types and
+/// a hasher digest can't be compared to or substituted for a digest of the same message.
+#[derive(Clone, Copy, Debug, Eq)]
+pub struct HasherDigest([u8; DIGEST_LEN]);
+
+impl HasherDigest {
+    /// Create a hasher digest from a 32-byte slice.
+    #[must_use]
+    pub fn decode(b: impl AsRef<[u8]>) -> Option<HasherDigest> {
+        Some(HasherDigest(b.as_ref().try_into().ok()?))
+    }
+
+    /// Encode the hasher digest as a 32-byte array.
+    #[must_use]
+    pub const fn encode(&self) -> [u8; DIGEST_LEN] {
+        self.0
+    }
+}
+
+/// An incremental hasher which creates a [`HasherDigest`] of a sequence of metadata values and a
+/// message written to it in any number of pieces.
+///
+/// The message is mixed into the protocol in fixed-size chunks, so the digest doesn't depend on how
+/// the message was split up, and a hasher can be cloned to fork it at any point. Hashers use their
+/// own `veil.hasher` domain, so their digests are unrelated to those created by [`Digest::new`] or
+/// [`Digest::from_slice`].
+#[derive(Clone, Debug)]
+pub struct Hasher {
+    protocol: Protocol,
+    chunk: Vec<u8>,
+}
+
+impl Hasher {
+    /// Create a hasher with a sequence of metadata values.
+    #[must_use]
+    pub fn new(metadata: &[impl AsRef<[u8]>]) -> Hasher {
+        // Initialize a protocol.
+        let mut protocol = Protocol::new(&version::domain("veil.hasher", FORMAT_VERSION));
+
+        // Mix the metadata values in order into the protocol.
+        for v in metadata {
+            protocol.mix("metadata", v.as_ref());
+        }
+
+        Hasher { protocol, chunk: Vec::with_capacity(CHUNK_LEN) }
+    }
+
+    /// Add a piece of the message to the hasher.
+    pub fn update(&mut self, mut message: &[u8]) {
+        // Top off a partially-filled chunk.
+        if !self.chunk.is_empty() {
+            let n = message.len().min(CHUNK_LEN - self.chunk.len());
+            self.chunk.extend_from_slice(&message[..n]);
+            message = &message[n..];
+
+            if self.chunk.len() < CHUNK_LEN {
+                return;
+            }
+
+            self.protocol.mix("chunk", &self.chunk);
+            self.chunk.clear();
+        }
+
+        // Mix whole chunks straight from the message and buffer the remainder.
+        let mut chunks = message.chunks_exact(CHUNK_LEN);
+        for chunk in &mut chunks {
+            self.protocol.mix("chunk", chunk);
+        }
+        self.chunk.extend_from_slice(chunks.remainder());
+    }
+
+    /// Finish hashing the message and return its digest.
+    #[must_use]
+    pub fn finalize(self) -> HasherDigest {
+        let mut protocol = self.finish();
+
+        // Derive 32 bytes as a digest.
+        HasherDigest(protocol.derive_array("digest"))
+    }
+
+    /// Finish hashing the message and fill `out` with output of an arbitrary length.
+    ///
+    /// The output depends on its length, so shorter outputs are not prefixes of longer ones. An
+    /// output of [`DIGEST_LEN`] bytes is equal to the encoded [`HasherDigest`].
+    pub fn finalize_xof(self, out: &mut [u8]) {
+        let mut protocol = self.finish();
+
+        // Derive the output.
+        protocol.derive("digest", out);
+    }
+
+    /// Mix the final, possibly empty, partial chunk into the protocol.
+    fn finish(self) -> Protocol {
+        let Hasher { mut protocol, chunk } = self;
+        protocol.mix("message", &chunk);
+        protocol
+    }
+}
+
+impl Write for Hasher {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
 impl FromStr for Digest {
     type Err = ParseDigestError;
 
@@ -69,10 +210,32 @@ impl PartialEq for Digest {
     }
 }
 
+impl FromStr for HasherDigest {
+    type Err = ParseDigestError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        HasherDigest::decode(bs58::decode(s).into_vec()?.as_slice())
+            .ok_or(ParseDigestError::InvalidLength)
+    }
+}
+
+impl fmt::Display for HasherDigest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", bs58::encode(self.0).into_string())
+    }
+}
+
+impl PartialEq for HasherDigest {
+    fn eq(&self, other: &Self) -> bool {
+        lockstitch::ct_eq(&self.0, &other.0)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::io::Cursor;
 
+    use expect_test::expect;
     use rand::{Rng, SeedableRng};
     use rand_chacha::ChaChaRng;
 
@@ -93,6 +256,19 @@ mod tests {
         assert_eq!(a, b, "inconsistent digests");
     }
 
+    #[test]
+    fn from_slice() {
+        let mut rng = ChaChaRng::seed_from_u64(0xDEADBEEF);
+        let message = rng.gen::<[u8; 64]>();
+        let md_one = rng.gen::<[u8; 16]>();
+
+        let a = Digest::new(&[&md_one], Cursor::new(&message))
+            .expect("cursor reads should be infallible");
+        let b = Digest::from_slice(&[&md_one], &message);
+
+        assert_eq!(a, b, "inconsistent digests");
+    }
+
     #[test]
     fn different_metadata() {
         let mut rng = ChaChaRng::seed_from_u64(0xDEADBEEF);
@@ -124,6 +300,129 @@ mod tests {
         assert_ne!(a, b, "collision on message");
     }
 
+    #[test]
+    fn incremental_updates() {
+        let mut rng = ChaChaRng::seed_from_u64(0xDEADBEEF);
+        let message = (0..3 * CHUNK_LEN + 17).map(|_| rng.gen()).collect::<Vec<u8>>();
+        let md_one = rng.gen::<[u8; 16]>();
+
+        let expected = hash(&[&md_one], &message);
+
+        for _ in 0..20 {
+            let mut hasher = Hasher::new(&[&md_one]);
+            let mut rest = message.as_slice();
+            while !rest.is_empty() {
+                let n = rng.gen_range(0..=rest.len().min(2 * CHUNK_LEN));
+                hasher.update(&rest[..n]);
+                rest = &rest[n..];
+            }
+            assert_eq!(expected, hasher.finalize(), "digest depends on update boundaries");
+        }
+    }
+
+    #[test]
+    fn chunk_boundaries() {
+        let mut rng = ChaChaRng::seed_from_u64(0xDEADBEEF);
+        let message = (0..2 * CHUNK_LEN).map(|_| rng.gen()).collect::<Vec<u8>>();
+        let no_metadata: [&[u8]; 0] = [];
+
+        let digests = [CHUNK_LEN - 1, CHUNK_LEN, CHUNK_LEN + 1, 2 * CHUNK_LEN]
+            .map(|n| hash(&no_metadata, &message[..n]));
+        for (i, a) in digests.iter().enumerate() {
+            for b in &digests[i + 1..] {
+                assert_ne!(a, b, "collision across chunk boundaries");
+            }
+        }
+    }
+
+    #[test]
+    fn forks() {
+        let mut rng = ChaChaRng::seed_from_u64(0xDEADBEEF);
+        let prefix = rng.gen::<[u8; 64]>();
+        let suffix_one = rng.gen::<[u8; 64]>();
+        let suffix_two = rng.gen::<[u8; 64]>();
+
+        let mut hasher = Hasher::new(&["fork"]);
+        hasher.update(&prefix);
+
+        let mut fork = hasher.clone();
+        fork.update(&suffix_two);
+        hasher.update(&suffix_one);
+
+        assert_eq!(
+            hash(&["fork"], &[&prefix[..], &suffix_one[..]].concat()),
+            hasher.finalize(),
+            "fork changed the original hasher"
+        );
+        assert_eq!(
+            hash(&["fork"], &[&prefix[..], &suffix_two[..]].concat()),
+            fork.finalize(),
+            "inconsistent fork"
+        );
+    }
+
+    #[test]
+    fn xof_output() {
+        let mut rng = ChaChaRng::seed_from_u64(0xDEADBEEF);
+        let message = rng.gen::<[u8; 64]>();
+        let mut hasher = Hasher::new(&["xof"]);
+        hasher.write_all(&message).expect("hasher writes should be infallible");
+
+        let mut out = [0u8; DIGEST_LEN];
+        hasher.clone().finalize_xof(&mut out);
+        assert_eq!(hasher.clone().finalize().encode(), out, "inconsistent digest output");
+
+        let mut long = [0u8; 100];
+        hasher.finalize_xof(&mut long);
+        assert_ne!(&long[..DIGEST_LEN], &out, "long output extends short output");
+    }
+
+    #[test]
+    fn digest_kat() {
+        let mut rng = ChaChaRng::seed_from_u64(0xDEADBEEF);
+        let message = (0..CHUNK_LEN + 64).map(|_| rng.gen()).collect::<Vec<u8>>();
+
+        let short = Digest::from_slice(&["kat"], &message[..64]);
+        let expected = expect!["6RzokMpqkYTkHMut5FG6ENM1JNCQxdNYuBw9NQmaoCRm"];
+        expected.assert_eq(&short.to_string());
+
+        let long = Digest::from_slice(&["kat"], &message);
+        let expected = expect!["34xCq5MCM6H9BpEHtPKnZaPBoVq9dLyxiHcPCPQXzsip"];
+        expected.assert_eq(&long.to_string());
+    }
+
+    #[test]
+    fn hasher_kat() {
+        let mut rng = ChaChaRng::seed_from_u64(0xDEADBEEF);
+        let message = (0..CHUNK_LEN + 64).map(|_| rng.gen()).collect::<Vec<u8>>();
+
+        let short = hash(&["kat"], &message[..64]);
+        let expected = expect!["G9phePiJwGFcTapQNuivT9uoUxrGg9b7efnFFQEPMv5a"];
+        expected.assert_eq(&short.to_string());
+
+        let long = hash(&["kat"], &message);
+        let expected = expect!["HdVVDw5ANn21ucRZCojbhMKxBha6UhwrSmzapA7DEs53"];
+        expected.assert_eq(&long.to_string());
+    }
+
+    #[test]
+    fn hasher_domain() {
+        let message = [0xAB; 64];
+
+        assert_ne!(
+            Digest::from_slice(&["domain"], &message).encode(),
+            hash(&["domain"], &message).encode(),
+            "hasher digest collides with a one-shot digest"
+        );
+    }
+
+    /// Returns the digest of `message` calculated with a hasher in a single update.
+    fn hash(metadata: &[impl AsRef<[u8]>], message: &[u8]) -> HasherDigest {
+        let mut hasher = Hasher::new(metadata);
+        hasher.update(message);
+        hasher.finalize()
+    }
+
     #[test]
     fn encoding() {
         let mut rng = ChaChaRng::seed_from_u64(0xDEADBEEF);
@@ -147,4 +446,21 @@ mod tests {
             "parsed invalid digest"
         );
     }
+
+    #[test]
+    fn hasher_digest_encoding() {
+        let mut rng = ChaChaRng::seed_from_u64(0xDEADBEEF);
+        let digest = HasherDigest(rng.gen());
+
+        assert_eq!(
+            Ok(digest),
+            digest.to_string().parse::<HasherDigest>(),
+            "error parsing hasher digest"
+        );
+        assert_eq!(
+            Some(digest),
+            HasherDigest::decode(digest.encode()),
+            "error decoding hasher digest"
+        );
+    }
 }
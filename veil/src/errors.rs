@@ -12,6 +12,16 @@ pub enum EncryptError {
     /// Encryption was unsuccessful due to an IO error writing the ciphertext.
     #[error("error writing ciphertext")]
     WriteIo(#[source] io::Error),
+
+    /// Encryption was unsuccessful because the file metadata had an invalid name or was too large
+    /// to fit in a single block.
+    #[error("invalid file metadata")]
+    InvalidMetadata,
+
+    /// Encryption was unsuccessful because the block length was not between
+    /// [`MIN_BLOCK_LEN`](crate::MIN_BLOCK_LEN) and [`MAX_BLOCK_LEN`](crate::MAX_BLOCK_LEN).
+    #[error("invalid block length")]
+    InvalidBlockLength,
 }
 
 /// An error returned when decrypting a message was unsuccessful.
@@ -24,10 +34,30 @@ pub enum DecryptError {
     #[error("invalid ciphertext")]
     InvalidCiphertext,
 
+    /// Loading a stored key was unsuccessful because it is a different type of key (e.g. a
+    /// signing-only key loaded as a [`SecretKey`](crate::SecretKey)).
+    #[error("invalid key type")]
+    InvalidKeyType,
+
     /// Decryption was unsuccessful due to an invalid block type.
     #[error("invalid block type: {0:02x}")]
     InvalidBlockType(u8),
 
+    /// Decryption was stopped because the ciphertext exceeded one of the given [`DecryptLimits`].
+    ///
+    /// [`DecryptLimits`]: crate::DecryptLimits
+    #[error("decryption limit exceeded")]
+    LimitExceeded,
+
+    /// Decryption was unsuccessful because the message is compressed and the `compression`
+    /// feature is not enabled.
+    #[error("message is compressed but compression is not supported")]
+    UnsupportedCompression,
+
+    /// Decryption was unsuccessful because the plaintext was not a valid archive.
+    #[error("invalid archive")]
+    InvalidArchive,
+
     /// Decryption was unsuccessful due to an IO error reading the ciphertext.
     #[error("error reading ciphertext")]
     ReadIo(#[source] io::Error),
@@ -52,6 +82,17 @@ pub enum VerifyError {
     ReadIo(#[from] io::Error),
 }
 
+/// An error returned when verifying a range proof was unsuccessful.
+#[derive(Clone, Copy, Debug, Eq, Error, PartialEq)]
+pub enum VerifyProofError {
+    /// Verification was unsuccessful due to a proof/data/digest mismatch.
+    ///
+    /// The data or proof may have been altered, or the data may not be part of the message with the
+    /// given digest.
+    #[error("invalid proof")]
+    InvalidProof,
+}
+
 /// An error returned when parsing a signature was unsuccessful.
 #[derive(Clone, Copy, Debug, Eq, Error, PartialEq)]
 pub enum ParseSignatureError {
@@ -87,3 +128,67 @@ pub enum ParseDigestError {
     #[error("invalid base58 encoding")]
     InvalidEncoding(#[from] bs58::decode::Error),
 }
+
+/// An error returned when parsing a MAC was unsuccessful.
+#[derive(Clone, Copy, Debug, Eq, Error, PartialEq)]
+pub enum ParseMacError {
+    /// Parsing failed because the value was not the correct length.
+    #[error("invalid MAC length")]
+    InvalidLength,
+
+    /// Parsing failed because the MAC was not valid base58.
+    #[error("invalid base58 encoding")]
+    InvalidEncoding(#[from] bs58::decode::Error),
+}
+
+/// An error returned when parsing a secret key share was unsuccessful.
+#[derive(Clone, Copy, Debug, Eq, Error, PartialEq)]
+pub enum ParseShareError {
+    /// Parsing failed because the value was not the correct length.
+    #[error("invalid share length")]
+    InvalidLength,
+
+    /// Parsing failed because the share was not valid base58.
+    #[error("invalid base58 encoding")]
+    InvalidEncoding(#[from] bs58::decode::Error),
+}
+
+/// An error returned when splitting a secret key into shares was unsuccessful.
+#[derive(Clone, Copy, Debug, Eq, Error, PartialEq)]
+pub enum SplitSharesError {
+    /// Splitting failed because the threshold was zero or greater than the number of shares.
+    #[error("invalid threshold")]
+    InvalidThreshold,
+}
+
+/// An error returned when combining secret key shares was unsuccessful.
+#[derive(Clone, Copy, Debug, Eq, Error, PartialEq)]
+pub enum CombineSharesError {
+    /// Combining failed because fewer shares than the threshold were provided.
+    #[error("not enough shares")]
+    NotEnoughShares,
+
+    /// Combining failed because the shares were from different keys or splits, or were duplicated.
+    #[error("inconsistent shares")]
+    InconsistentShares,
+
+    /// Combining failed because one or more shares have been modified.
+    #[error("invalid shares")]
+    InvalidShares,
+}
+
+/// An error returned when parsing a mnemonic was unsuccessful.
+#[derive(Clone, Copy, Debug, Eq, Error, PartialEq)]
+pub enum ParseMnemonicError {
+    /// Parsing failed because the mnemonic did not have the correct number of words.
+    #[error("invalid mnemonic length")]
+    InvalidLength,
+
+    /// Parsing failed because the word at the given position is not in the word list.
+    #[error("invalid word at position {0}")]
+    InvalidWord(usize),
+
+    /// Parsing failed because the mnemonic's checksum was invalid.
+    #[error("invalid mnemonic checksum")]
+    InvalidChecksum,
+}
@@ -89,7 +89,7 @@ fn encode_ct(mut rng: impl CryptoRng + Rng, c: [u8; ML_KEM_CT_LEN]) -> Option<[u
     Some(out)
 }
 
-fn decode_ct(mut ect: [u8; ENC_CT_LEN]) -> [u8; ML_KEM_CT_LEN] {
+pub(crate) fn decode_ct(mut ect: [u8; ENC_CT_LEN]) -> [u8; ML_KEM_CT_LEN] {
     // Unmask the top five bits of the encoded vector.
     ect[ENC_CT_LEN - 128 - 1] &= !BIT_MASK;
 
@@ -257,10 +257,101 @@ fn ring_decode_and_decompress10(b: [u8; 320]) -> RingElement {
 #[cfg(test)]
 mod tests {
     use fips203::{ml_kem_768, traits::KeyGen};
-    use rand::rngs::OsRng;
+    use rand::{rngs::OsRng, RngCore, SeedableRng};
+    use rand_chacha::ChaChaRng;
 
     use super::*;
 
+    /// A slow, straightforward model of Kemeleon for ML-KEM-768 ciphertexts, written directly from
+    /// FIPS 203 and the Kemeleon paper with exact integer arithmetic and no bit-twiddling.
+    ///
+    /// It doesn't sample encodings itself. Instead, it decides whether an encoding is one which
+    /// Kemeleon could have produced for a ciphertext, so the optimized encoder is checked by what it
+    /// outputs and how those outputs are distributed, not by how it consumes randomness.
+    mod reference {
+        use num_bigint::BigUint;
+
+        use crate::{kemeleon::ENC_CT_LEN, keys::ML_KEM_CT_LEN};
+
+        const Q: u32 = 3329;
+
+        /// Compress_d(x) = ⌈(2ᵈ/q)·x⌋ mod 2ᵈ, rounding ½ up (FIPS 203, Definition 4.7).
+        pub fn compress(x: u32, d: u32) -> u32 {
+            ((2 * (x << d) + Q) / (2 * Q)) % (1 << d)
+        }
+
+        /// Decompress_d(y) = ⌈(q/2ᵈ)·y⌋, rounding ½ up (FIPS 203, Definition 4.8).
+        pub fn decompress(y: u32, d: u32) -> u32 {
+            (2 * y * Q + (1 << d)) / (2 << d)
+        }
+
+        /// ByteDecode_d, bit by bit (FIPS 203, Algorithm 6).
+        fn byte_decode(b: &[u8], d: usize) -> Vec<u32> {
+            let bits = b.iter().flat_map(|&b| (0..8).map(move |j| u32::from(b >> j) & 1));
+            bits.collect::<Vec<_>>()
+                .chunks(d)
+                .map(|f| f.iter().enumerate().map(|(j, &bit)| bit << j).sum())
+                .collect()
+        }
+
+        /// ByteEncode_d, bit by bit (FIPS 203, Algorithm 5).
+        fn byte_encode(f: &[u32], d: usize) -> Vec<u8> {
+            let bits = f.iter().flat_map(|&f| (0..d).map(move |j| (f >> j) & 1));
+            bits.collect::<Vec<_>>()
+                .chunks(8)
+                .map(|b| b.iter().enumerate().map(|(j, &bit)| (bit as u8) << j).sum())
+                .collect()
+        }
+
+        /// Returns the compressed coefficients of u, the first part of the ciphertext.
+        pub fn compressed_u(c: &[u8; ML_KEM_CT_LEN]) -> Vec<u32> {
+            byte_decode(&c[..960], 10)
+        }
+
+        /// Returns the preimage of `y` under Compress₁₀: every element of ℤ_q which compresses to it.
+        pub fn preimage(y: u32) -> Vec<u32> {
+            (0..Q).filter(|&x| compress(x, 10) == y).collect()
+        }
+
+        /// Returns the integer encoded in the first 8987 bits of an encoded ciphertext, without the
+        /// five random bits which follow it.
+        pub fn vector(ect: &[u8; ENC_CT_LEN]) -> BigUint {
+            BigUint::from_bytes_le(&ect[..1124]) % (BigUint::from(1u8) << 8987)
+        }
+
+        /// Returns the low 768 base-q digits of `r`, which are the coefficients of u.
+        pub fn digits(r: &BigUint) -> Vec<u32> {
+            let mut r = r.clone();
+            (0..768)
+                .map(|_| {
+                    let x = &r % Q;
+                    r /= Q;
+                    x.try_into().expect("should be < q")
+                })
+                .collect()
+        }
+
+        /// Returns `true` if `ect` is a Kemeleon encoding of `c`: each coefficient of u is replaced
+        /// with an element of its preimage under Compress₁₀, the vector is written in base q in
+        /// 8987 bits, and v follows verbatim.
+        pub fn is_encoding_of(ect: &[u8; ENC_CT_LEN], c: &[u8; ML_KEM_CT_LEN]) -> bool {
+            let u = digits(&vector(ect));
+            let in_preimages =
+                compressed_u(c).into_iter().zip(u).all(|(y, x)| preimage(y).contains(&x));
+            in_preimages && ect[1124..] == c[960..]
+        }
+
+        pub fn decode_ct(ect: [u8; ENC_CT_LEN]) -> [u8; ML_KEM_CT_LEN] {
+            // Discard the 5 random bits and decode the vector's coefficients in base q.
+            let u = digits(&vector(&ect)).into_iter().map(|x| compress(x, 10)).collect::<Vec<_>>();
+
+            let mut c = [0u8; ML_KEM_CT_LEN];
+            c[..960].copy_from_slice(&byte_encode(&u, 10));
+            c[960..].copy_from_slice(&ect[1124..]);
+            c
+        }
+    }
+
     #[test]
     fn round_trip() {
         for _ in 0..10 {
@@ -271,4 +362,92 @@ mod tests {
             assert_eq!(sk, sk_p);
         }
     }
+
+    #[test]
+    fn fuzz_decode_ct() {
+        let (_, dk) = ml_kem_768::KG::keygen_from_seed([7; 32], [8; 32]);
+        bolero::check!().with_type::<Vec<u8>>().for_each(|b| {
+            // Pad or truncate the input to a full encoded ciphertext.
+            let mut ect = [0u8; ENC_CT_LEN];
+            let n = b.len().min(ENC_CT_LEN);
+            ect[..n].copy_from_slice(&b[..n]);
+
+            // Any encoded ciphertext should decode to an ML-KEM ciphertext which decapsulates to
+            // some shared secret.
+            let c = decode_ct(ect);
+            assert_eq!(c[960..], ect[1124..], "second part should be copied verbatim");
+            decapsulate(&dk, ect);
+        });
+    }
+
+    #[test]
+    fn compress_matches_reference() {
+        for d in [4, 10] {
+            for x in 0..Q {
+                assert_eq!(
+                    reference::compress(x.into(), d.into()),
+                    u32::from(compress(x, d)),
+                    "Compress_{d}({x})"
+                );
+            }
+            for y in 0..(1 << d) {
+                assert_eq!(
+                    reference::decompress(y.into(), d.into()),
+                    u32::from(decompress(y, d)),
+                    "Decompress_{d}({y})"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn encode_ct_is_valid() {
+        bolero::check!().with_type::<(u64, Vec<u8>)>().for_each(|(seed, b)| {
+            // Any 1088-byte string is a valid ML-KEM-768 ciphertext.
+            let mut c = [0u8; ML_KEM_CT_LEN];
+            ChaChaRng::seed_from_u64(*seed).fill_bytes(&mut c);
+            let n = b.len().min(ML_KEM_CT_LEN);
+            c[..n].copy_from_slice(&b[..n]);
+
+            if let Some(ect) = encode_ct(ChaChaRng::seed_from_u64(*seed), c) {
+                assert!(reference::is_encoding_of(&ect, &c), "not an encoding of the ciphertext");
+                assert_eq!(c, decode_ct(ect), "decode_ct(encode_ct(c)) != c");
+                assert_eq!(c, reference::decode_ct(ect), "reference decode_ct(encode_ct(c)) != c");
+            }
+        });
+    }
+
+    #[test]
+    fn decode_ct_matches_reference() {
+        bolero::check!().with_type::<Vec<u8>>().for_each(|b| {
+            let mut ect = [0u8; ENC_CT_LEN];
+            let n = b.len().min(ENC_CT_LEN);
+            ect[..n].copy_from_slice(&b[..n]);
+            assert_eq!(reference::decode_ct(ect), decode_ct(ect));
+        });
+    }
+
+    #[test]
+    fn encoded_bits_are_uniform() {
+        const SAMPLES: usize = 1000;
+
+        // Encode random ciphertexts until there are enough samples.
+        let mut rng = ChaChaRng::seed_from_u64(0xDEADBEEF);
+        let mut c = [0u8; ML_KEM_CT_LEN];
+        let mut samples = Vec::with_capacity(SAMPLES);
+        while samples.len() < SAMPLES {
+            rng.fill_bytes(&mut c);
+            samples.extend(encode_ct(&mut rng, c));
+        }
+
+        // Every bit of the encoded vector below the top few, and every masked bit, should be set
+        // in about half of the samples: within 5σ of the mean.
+        let bits = (0..8960).chain(8987..8992);
+        let bound = 5.0 * (SAMPLES as f64 / 4.0).sqrt();
+        for bit in bits {
+            let ones = samples.iter().filter(|ect| ect[bit / 8] >> (bit % 8) & 1 == 1).count();
+            let deviation = (ones as f64 - SAMPLES as f64 / 2.0).abs();
+            assert!(deviation < bound, "bit {bit} set in {ones} of {SAMPLES} samples");
+        }
+    }
 }
@@ -15,11 +15,16 @@ use zeroize::{Zeroize, ZeroizeOnDrop};
 pub(crate) const ML_KEM_PK_LEN: usize = ml_kem_768::EK_LEN;
 pub(crate) const ML_KEM_CT_LEN: usize = ml_kem_768::CT_LEN;
 pub(crate) const ML_KEM_SS_LEN: usize = 32;
+pub(crate) const ML_DSA_PK_LEN: usize = ml_dsa_65::PK_LEN;
 
-pub const PK_LEN: usize = ML_KEM_PK_LEN + ml_dsa_65::PK_LEN;
+pub const PK_LEN: usize = ML_KEM_PK_LEN + ML_DSA_PK_LEN;
 
 pub const SK_LEN: usize = 256;
 
+pub const SIGN_SK_LEN: usize = 32;
+
+pub const DEC_SK_LEN: usize = 64;
+
 pub type SigningKey = ml_dsa_65::PrivateKey;
 
 pub type VerifyingKey = ml_dsa_65::PublicKey;
@@ -28,6 +33,44 @@ pub type EncapsulationKey = ml_kem_768::EncapsKey;
 
 pub type DecapsulationKey = ml_kem_768::DecapsKey;
 
+/// A verifying key, including its canonical encoded form.
+#[derive(Clone)]
+pub struct VerKey {
+    /// The ML-DSA-65 verifying key.
+    pub vk: VerifyingKey,
+
+    /// The verifying key's canonical encoded form.
+    pub encoded: [u8; ML_DSA_PK_LEN],
+}
+
+impl VerKey {
+    /// Decodes the given slice as a verifying key, if possible.
+    #[must_use]
+    pub fn from_canonical_bytes(b: impl AsRef<[u8]>) -> Option<VerKey> {
+        let encoded = <[u8; ML_DSA_PK_LEN]>::try_from(b.as_ref()).ok()?;
+        let vk = VerifyingKey::try_from_bytes(encoded).ok()?;
+        Some(VerKey { vk, encoded })
+    }
+
+    fn from_vk(vk: VerifyingKey) -> VerKey {
+        VerKey { encoded: vk.clone().into_bytes(), vk }
+    }
+}
+
+impl Debug for VerKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:02x?}", self.encoded)
+    }
+}
+
+impl Eq for VerKey {}
+
+impl PartialEq for VerKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.encoded == other.encoded
+    }
+}
+
 /// A public key, including its canonical encoded form.
 #[derive(Clone)]
 pub struct PubKey {
@@ -35,7 +78,7 @@ pub struct PubKey {
     pub ek: EncapsulationKey,
 
     /// The ML-DSA-65 verifying key.
-    pub vk: VerifyingKey,
+    pub ver_key: VerKey,
 
     /// The public key's canonical encoded form.
     pub encoded: [u8; PK_LEN],
@@ -49,17 +92,23 @@ impl PubKey {
         let (ek, vk) = encoded.split_at(ML_KEM_PK_LEN);
         let ek =
             EncapsulationKey::try_from_bytes(ek.try_into().expect("should be 1184 bytes")).ok()?;
-        let vk = VerifyingKey::try_from_bytes(vk.try_into().expect("should be 1952 bytes")).ok()?;
-        Some(PubKey { ek, vk, encoded })
+        let ver_key = VerKey::from_canonical_bytes(vk)?;
+        Some(PubKey { ek, ver_key, encoded })
+    }
+
+    /// Returns the canonical encoded form of the ML-KEM-768 encapsulation key.
+    #[must_use]
+    pub fn ek_encoded(&self) -> &[u8] {
+        &self.encoded[..ML_KEM_PK_LEN]
     }
 
-    fn from_parts(ek: EncapsulationKey, vk: VerifyingKey) -> PubKey {
+    fn from_parts(ek: EncapsulationKey, ver_key: VerKey) -> PubKey {
         let mut encoded = [0u8; PK_LEN];
         let (enc_ek, enc_vk) = encoded.split_at_mut(ML_KEM_PK_LEN);
         enc_ek.copy_from_slice(&ek.clone().into_bytes());
-        enc_vk.copy_from_slice(&vk.clone().into_bytes());
+        enc_vk.copy_from_slice(&ver_key.encoded);
 
-        PubKey { ek, vk, encoded }
+        PubKey { ek, ver_key, encoded }
     }
 }
 
@@ -79,17 +128,134 @@ impl PartialEq for PubKey {
 
 impl AsRef<VerifyingKey> for &PubKey {
     fn as_ref(&self) -> &VerifyingKey {
-        &self.vk
+        &self.ver_key.vk
     }
 }
 
-/// A secret key, including its public key.
-pub struct SecKey {
+/// A signing-only secret key, including its verifying key.
+pub struct SignKey {
+    /// The ML-DSA-65 signing key.
+    pub sk: SigningKey,
+
+    /// The corresponding [`VerKey`] for the signing key.
+    pub ver_key: VerKey,
+
+    /// The signing key seed.
+    pub seed: [u8; SIGN_SK_LEN],
+}
+
+impl SignKey {
+    /// Generates a random signing key.
+    #[must_use]
+    pub fn random(mut rng: impl CryptoRng + RngCore) -> SignKey {
+        Self::from_canonical_bytes(rng.gen::<[u8; SIGN_SK_LEN]>()).expect("should parse")
+    }
+
+    /// Decodes the given slice as a signing key, if possible.
+    #[must_use]
+    pub fn from_canonical_bytes(b: impl AsRef<[u8]>) -> Option<SignKey> {
+        let seed = <[u8; SIGN_SK_LEN]>::try_from(b.as_ref()).ok()?;
+        let (vk, sk) = ml_dsa_65::KG::keygen_from_seed(&seed);
+        Some(SignKey { sk, ver_key: VerKey::from_vk(vk), seed })
+    }
+}
+
+impl Eq for SignKey {}
+
+impl PartialEq for SignKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.ver_key == other.ver_key
+    }
+}
+
+impl Debug for SignKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SignKey")
+            .field("sk", &"[redacted]")
+            .field("seed", &"[redacted]")
+            .field("ver_key", &self.ver_key)
+            .finish()
+    }
+}
+
+impl AsRef<SigningKey> for &SignKey {
+    fn as_ref(&self) -> &SigningKey {
+        &self.sk
+    }
+}
+
+impl Drop for SignKey {
+    fn drop(&mut self) {
+        // the signing key is zeroized on drop
+        self.seed.zeroize();
+    }
+}
+
+impl ZeroizeOnDrop for SignKey {}
+
+/// A decryption-only secret key, including its encapsulation key.
+pub struct DecKey {
     /// The ML-KEM-768 decapsulation key.
     pub dk: DecapsulationKey,
 
-    /// The ML-DSA-65 signing key.
-    pub sk: SigningKey,
+    /// The ML-KEM-768 encapsulation key.
+    pub ek: EncapsulationKey,
+
+    /// The canonical encoded form of the encapsulation key.
+    pub ek_encoded: [u8; ML_KEM_PK_LEN],
+
+    /// The decryption key seed.
+    pub seed: [u8; DEC_SK_LEN],
+}
+
+impl DecKey {
+    /// Decodes the given slice as a decryption key, if possible.
+    #[must_use]
+    pub fn from_canonical_bytes(b: impl AsRef<[u8]>) -> Option<DecKey> {
+        let seed = <[u8; DEC_SK_LEN]>::try_from(b.as_ref()).ok()?;
+        let (d, z) = seed.split_at(32);
+        let (ek, dk) = ml_kem_768::KG::keygen_from_seed(
+            d.try_into().expect("should be 32 bytes"),
+            z.try_into().expect("should be 32 bytes"),
+        );
+        Some(DecKey { dk, ek_encoded: ek.clone().into_bytes(), ek, seed })
+    }
+}
+
+impl Eq for DecKey {}
+
+impl PartialEq for DecKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.ek_encoded == other.ek_encoded
+    }
+}
+
+impl Debug for DecKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DecKey")
+            .field("dk", &"[redacted]")
+            .field("seed", &"[redacted]")
+            .field("ek", &self.ek_encoded)
+            .finish()
+    }
+}
+
+impl Drop for DecKey {
+    fn drop(&mut self) {
+        // the decapsulation key is zeroized on drop
+        self.seed.zeroize();
+    }
+}
+
+impl ZeroizeOnDrop for DecKey {}
+
+/// A secret key, including its public key.
+pub struct SecKey {
+    /// The decryption key.
+    pub dec_key: DecKey,
+
+    /// The signing key.
+    pub sign_key: SignKey,
 
     /// The corresponding [`PubKey`] for the secret key.
     pub pub_key: PubKey,
@@ -113,13 +279,36 @@ impl SecKey {
         let mut key = Protocol::new("veil.key");
         key.mix("seed", &seed);
 
-        let dk_d = key.derive_array::<32>("ml-kem-768-d");
-        let dk_z = key.derive_array::<32>("ml-kem-768-z");
-        let (ek, dk) = ml_kem_768::KG::keygen_from_seed(dk_d, dk_z);
-        let sk_x = key.derive_array::<32>("ml-dsa-65-x");
-        let (vk, sk) = ml_dsa_65::KG::keygen_from_seed(&sk_x);
+        let mut dk_seed = [0u8; DEC_SK_LEN];
+        key.derive("ml-kem-768-d", &mut dk_seed[..32]);
+        key.derive("ml-kem-768-z", &mut dk_seed[32..]);
+        let dec_key = DecKey::from_canonical_bytes(dk_seed).expect("should be a valid seed");
+        dk_seed.zeroize();
+
+        let mut sk_x = key.derive_array::<SIGN_SK_LEN>("ml-dsa-65-x");
+        let sign_key = SignKey::from_canonical_bytes(sk_x).expect("should be a valid seed");
+        sk_x.zeroize();
 
-        Some(SecKey { dk, sk, pub_key: PubKey::from_parts(ek, vk), seed })
+        let pub_key = PubKey::from_parts(dec_key.ek.clone(), sign_key.ver_key.clone());
+        Some(SecKey { dec_key, sign_key, pub_key, seed })
+    }
+
+    /// Deterministically derives a child secret key from this key's seed and the given label.
+    ///
+    /// Labels are split into `/`-separated segments, each of which is derived in turn, so deriving
+    /// `a/b` is equivalent to deriving `a` and then `b`.
+    #[must_use]
+    pub fn derive_child(&self, label: &str) -> SecKey {
+        let mut seed = label.split('/').fold(self.seed, |mut parent, segment| {
+            let mut child = Protocol::new("veil.key.child");
+            child.mix("parent", &parent);
+            child.mix("label", segment.as_bytes());
+            parent.zeroize();
+            child.derive_array::<SK_LEN>("seed")
+        });
+        let child = SecKey::from_canonical_bytes(seed).expect("should be a valid seed");
+        seed.zeroize();
+        child
     }
 }
 
@@ -134,8 +323,8 @@ impl PartialEq for SecKey {
 impl Debug for SecKey {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("SecKey")
-            .field("dk", &"[redacted]")
-            .field("sk", &"[redacted]")
+            .field("dec_key", &self.dec_key)
+            .field("sign_key", &self.sign_key)
             .field("seed", &"[redacted]")
             .field("pub_key", &self.pub_key)
             .finish()
@@ -144,7 +333,7 @@ impl Debug for SecKey {
 
 impl AsRef<SigningKey> for &SecKey {
     fn as_ref(&self) -> &SigningKey {
-        &self.sk
+        &self.sign_key.sk
     }
 }
 
@@ -172,6 +361,23 @@ mod tests {
         assert_eq!(ssk.pub_key, ssk_p.pub_key);
     }
 
+    #[test]
+    fn child_derivation() {
+        let rng = ChaChaRng::seed_from_u64(0xDEADBEEF);
+        let ssk = SecKey::random(rng);
+
+        let child = ssk.derive_child("work/2026");
+        assert_eq!(child, ssk.derive_child("work/2026"), "derivation should be deterministic");
+        assert_eq!(
+            child,
+            ssk.derive_child("work").derive_child("2026"),
+            "segments should be derived in turn"
+        );
+        assert_ne!(child, ssk, "child should differ from parent");
+        assert_ne!(child, ssk.derive_child("work/2025"), "labels should produce distinct keys");
+        assert_ne!(child, ssk.derive_child("work2026"), "segments should be delimited");
+    }
+
     #[test]
     fn pub_key_round_trip() {
         let rng = ChaChaRng::seed_from_u64(0xDEADBEEF);
@@ -15,7 +15,7 @@
 //! use std::io;
 //! use std::io::Cursor;
 //! use rand::rngs::OsRng;
-//! use veil::SecretKey;
+//! use veil::{EncryptOptions, SecretKey};
 //! # use std::error::Error;
 //! #
 //! # fn main() -> Result<(), Box<dyn Error>> {
@@ -36,7 +36,7 @@
 //!   Cursor::new("this is a secret message"),
 //!   &mut ciphertext,
 //!   &[bea_pub],
-//!   Some(20),
+//!   EncryptOptions { fakes: 20, include_self: true, ..Default::default() },
 //! )?;
 //!
 //! // Bea decrypts the message.
@@ -56,13 +56,39 @@
 #![forbid(unsafe_code)]
 #![warn(missing_docs)]
 
-pub use self::{digest::*, errors::*, sig::Signature, veil::*};
+pub use self::{
+    digest::*,
+    errors::*,
+    mac::*,
+    message::{DEFAULT_BLOCK_LEN, MAX_BLOCK_LEN, MIN_BLOCK_LEN},
+    metadata::FileMetadata,
+    pbenc::Kdf,
+    sig::Signature,
+    sss::Share,
+    tree::*,
+    veil::*,
+    version::FORMAT_VERSION,
+};
 
+mod archive;
+#[cfg(feature = "compression")]
+mod compression;
 mod digest;
 mod errors;
 mod kemeleon;
 mod keys;
+mod mac;
 mod message;
+mod metadata;
+mod mnemonic;
 mod pbenc;
+mod pipeline;
+#[cfg(test)]
+mod randomness;
 mod sig;
+mod sss;
+mod tree;
+#[cfg(test)]
+mod vectors;
 mod veil;
+mod version;
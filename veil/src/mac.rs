@@ -0,0 +1,197 @@
+use std::{fmt, io, io::Read, str::FromStr};
+
+use lockstitch::Protocol;
+
+use crate::{
+    version::{self, FORMAT_VERSION},
+    ParseMacError,
+};
+
+/// The length of a [`Mac`] in bytes.
+pub const MAC_LEN: usize = 32;
+
+/// A message authentication code of a sequence of metadata values and a message, created with a
+/// shared secret key.
+///
+/// Comparing two MACs with `==` takes constant time.
+#[derive(Clone, Copy, Debug, Eq)]
+pub struct Mac([u8; MAC_LEN]);
+
+impl Mac {
+    /// Create a MAC from a shared key, a sequence of metadata values, and a reader.
+    ///
+    /// Unlike a [`Digest`](crate::Digest) with the key passed as metadata, the key is
+    /// domain-separated from the metadata, so no metadata value can be mistaken for a key.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error returned by operations on `reader`.
+    pub fn new(
+        key: &[u8],
+        metadata: &[impl AsRef<[u8]>],
+        mut reader: impl Read,
+    ) -> io::Result<Mac> {
+        // Initialize a protocol with the key and metadata.
+        let mac = init(key, metadata);
+
+        // Mix the reader contents into the protocol.
+        let mut writer = mac.mix_writer("message", io::sink());
+        io::copy(&mut reader, &mut writer)?;
+        let (mut mac, _) = writer.into_inner();
+
+        // Derive 32 bytes as a MAC.
+        Ok(Mac(mac.derive_array("mac")))
+    }
+
+    /// Create a MAC from a shared key, a sequence of metadata values, and a message slice.
+    ///
+    /// The MAC is identical to one created by [`Mac::new`] for the same message.
+    #[must_use]
+    pub fn from_slice(key: &[u8], metadata: &[impl AsRef<[u8]>], message: &[u8]) -> Mac {
+        // Initialize a protocol with the key and metadata.
+        let mut mac = init(key, metadata);
+
+        // Mix the message into the protocol.
+        mac.mix("message", message);
+
+        // Derive 32 bytes as a MAC.
+        Mac(mac.derive_array("mac"))
+    }
+
+    /// Create a MAC from a 32-byte slice.
+    #[must_use]
+    pub fn decode(b: impl AsRef<[u8]>) -> Option<Mac> {
+        Some(Mac(b.as_ref().try_into().ok()?))
+    }
+
+    /// Encode the MAC as a 32-byte array.
+    #[must_use]
+    pub const fn encode(&self) -> [u8; MAC_LEN] {
+        self.0
+    }
+}
+
+/// Initializes a MAC protocol and mixes the key and metadata values into it.
+fn init(key: &[u8], metadata: &[impl AsRef<[u8]>]) -> Protocol {
+    let mut mac = Protocol::new(&version::domain("veil.mac", FORMAT_VERSION));
+
+    // Mix the key into the protocol.
+    mac.mix("key", key);
+
+    // Mix the metadata values in order into the protocol.
+    for v in metadata {
+        mac.mix("metadata", v.as_ref());
+    }
+
+    mac
+}
+
+impl FromStr for Mac {
+    type Err = ParseMacError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Mac::decode(bs58::decode(s).into_vec()?.as_slice()).ok_or(ParseMacError::InvalidLength)
+    }
+}
+
+impl fmt::Display for Mac {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", bs58::encode(self.0).into_string())
+    }
+}
+
+impl PartialEq for Mac {
+    fn eq(&self, other: &Self) -> bool {
+        lockstitch::ct_eq(&self.0, &other.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use expect_test::expect;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaChaRng;
+
+    use crate::Digest;
+
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let (key, md, message) = setup();
+
+        let a = Mac::new(&key, &[&md], Cursor::new(&message))
+            .expect("cursor reads should be infallible");
+        let b = Mac::from_slice(&key, &[&md], &message);
+
+        assert_eq!(a, b, "inconsistent MACs");
+    }
+
+    #[test]
+    fn different_keys() {
+        let (key, md, message) = setup();
+
+        let a = Mac::from_slice(&key, &[&md], &message);
+        let b = Mac::from_slice(&key[1..], &[&md], &message);
+
+        assert_ne!(a, b, "collision on key");
+    }
+
+    #[test]
+    fn different_messages() {
+        let (key, md, message) = setup();
+
+        let a = Mac::from_slice(&key, &[&md], &message);
+        let b = Mac::from_slice(&key, &[&md], &message[1..]);
+
+        assert_ne!(a, b, "collision on message");
+    }
+
+    #[test]
+    fn key_is_not_metadata() {
+        let (key, md, message) = setup();
+
+        let mac = Mac::from_slice(&key, &[&md], &message);
+        let digest = Digest::from_slice(&[&key[..], &md[..]], &message);
+        assert_ne!(mac.encode(), digest.encode(), "MAC collides with keyed digest");
+
+        let no_metadata: [&[u8]; 0] = [];
+        let a = Mac::from_slice(&key, &no_metadata, &message);
+        let b = Mac::from_slice(&[], &[&key], &message);
+        assert_ne!(a, b, "collision between key and metadata");
+    }
+
+    #[test]
+    fn mac_kat() {
+        let (key, md, message) = setup();
+        let mac = Mac::from_slice(&key, &[&md], &message);
+        let expected = expect!["6DMowAu1hoS4yabTxVNb3fYtyx89pPQb63C6SnT2sj88"];
+        expected.assert_eq(&mac.to_string());
+    }
+
+    #[test]
+    fn encoding() {
+        let mut rng = ChaChaRng::seed_from_u64(0xDEADBEEF);
+        let mac = Mac(rng.gen());
+
+        let decoded = mac.to_string().parse::<Mac>();
+        assert_eq!(Ok(mac), decoded, "error parsing MAC");
+
+        assert_eq!(
+            Err(ParseMacError::InvalidEncoding(bs58::decode::Error::InvalidCharacter {
+                character: 'l',
+                index: 4,
+            })),
+            "invalid mac".parse::<Mac>(),
+            "parsed invalid MAC"
+        );
+        assert_eq!(Err(ParseMacError::InvalidLength), "2g".parse::<Mac>());
+    }
+
+    fn setup() -> ([u8; 32], [u8; 16], [u8; 64]) {
+        let mut rng = ChaChaRng::seed_from_u64(0xDEADBEEF);
+        (rng.gen(), rng.gen(), rng.gen())
+    }
+}
@@ -1,14 +1,23 @@
 //! A multi-receiver cryptosystem.
 
-use std::io::{self, Read, Write};
+use std::{
+    io::{self, Read, Write},
+    num::NonZeroUsize,
+    thread,
+};
 
 use lockstitch::{Protocol, TAG_LEN};
-use rand::{CryptoRng, Rng, RngCore};
+use rand::{CryptoRng, Rng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
 
+#[cfg(feature = "compression")]
+use crate::compression;
 use crate::{
     kemeleon::{self, ENC_CT_LEN},
-    keys::{PubKey, SecKey},
-    sig, DecryptError, EncryptError,
+    keys::{DecKey, PubKey, SecKey, ML_KEM_SS_LEN},
+    sig::{self, SIG_LEN},
+    version::{self, FORMAT_VERSION, SUPPORTED_VERSIONS, UNVERSIONED},
+    DecryptError, DecryptLimits, EncryptError, EncryptOptions, FileMetadata,
 };
 
 /// The length of a plaintext block header. The first byte signifies the block type, the next three
@@ -18,47 +27,161 @@ const BLOCK_HEADER_LEN: usize = 4;
 /// The length of an encrypted block header and authentication tag.
 const ENC_BLOCK_HEADER_LEN: usize = BLOCK_HEADER_LEN + TAG_LEN;
 
-/// The length of a plaintext block.
-const BLOCK_LEN: usize = 64 * 1024;
+/// The default length of a plaintext block.
+pub const DEFAULT_BLOCK_LEN: usize = 64 * 1024;
+
+/// The minimum length of a plaintext block.
+pub const MIN_BLOCK_LEN: usize = 256;
+
+/// The maximum length of a plaintext block. Block lengths are encoded as 24-bit integers.
+pub const MAX_BLOCK_LEN: usize = 8 * 1024 * 1024;
 
 /// The length of the data encryption key.
 const DEK_LEN: usize = 32;
 
 /// The length of an encoded header.
-const HEADER_LEN: usize = DEK_LEN + size_of::<u64>();
+const HEADER_LEN: usize =
+    size_of::<u8>() + DEK_LEN + size_of::<u64>() + size_of::<u32>() + size_of::<u8>();
+
+/// The header flag which signifies that the plaintext was compressed before encryption.
+const FLAG_COMPRESSED: u8 = 0x01;
 
 /// The length of an encrypted header.
 const ENC_HEADER_LEN: usize = ENC_CT_LEN + HEADER_LEN + TAG_LEN;
 
-/// Encrypt the contents of `reader` such that they can be decrypted and verified by all members of
+/// The length of an encoded unversioned header, which has only the DEK and receiver count.
+const UNVERSIONED_HEADER_LEN: usize = DEK_LEN + size_of::<u64>();
+
+/// The length of an encrypted unversioned header.
+const ENC_UNVERSIONED_HEADER_LEN: usize = ENC_CT_LEN + UNVERSIONED_HEADER_LEN + TAG_LEN;
+
+/// The length of a plaintext block of an unversioned message.
+const UNVERSIONED_BLOCK_LEN: usize = 64 * 1024;
+
+/// Encrypt the contents of `reader`, optionally compressing them first, in blocks of
+/// `options.block_len` bytes such that they can be decrypted and verified by all members of
 /// `receivers` and write the ciphertext to `writer` with some padding bytes of random data added.
 pub fn encrypt(
-    mut rng: impl Rng + CryptoRng,
+    rng: impl Rng + CryptoRng,
     reader: impl Read,
+    writer: impl Write,
+    sender: &SecKey,
+    receivers: &[Option<PubKey>],
+    options: &EncryptOptions,
+) -> Result<u64, EncryptError> {
+    check_block_len(options.block_len)?;
+
+    #[cfg(feature = "compression")]
+    if options.compress() {
+        let reader = compression::compress(reader);
+        return encrypt_reader(rng, reader, writer, sender, receivers, options, true);
+    }
+
+    encrypt_reader(rng, reader, writer, sender, receivers, options, false)
+}
+
+/// Encrypt the contents of `reader` in blocks of `options.block_len` bytes, marking them as
+/// compressed or not.
+fn encrypt_reader(
+    rng: impl Rng + CryptoRng,
+    mut reader: impl Read,
+    writer: impl Write,
+    sender: &SecKey,
+    receivers: &[Option<PubKey>],
+    options: &EncryptOptions,
+    compressed: bool,
+) -> Result<u64, EncryptError> {
+    let next_block = |block: &mut Vec<u8>| {
+        let n = (&mut reader)
+            .take(options.block_len as u64)
+            .read_to_end(block)
+            .map_err(EncryptError::ReadIo)?;
+        Ok(n)
+    };
+    encrypt_blocks(rng, next_block, writer, sender, receivers, options, compressed)
+}
+
+/// Encrypt `plaintext` like [`encrypt`], copying blocks directly from the slice instead of reading
+/// them. The ciphertext is identical to that produced by [`encrypt`].
+pub fn encrypt_slice(
+    rng: impl Rng + CryptoRng,
+    plaintext: &[u8],
+    writer: impl Write,
+    sender: &SecKey,
+    receivers: &[Option<PubKey>],
+    options: &EncryptOptions,
+) -> Result<u64, EncryptError> {
+    check_block_len(options.block_len)?;
+
+    // Compressed plaintexts are read through the compressor instead.
+    if options.compress() {
+        return encrypt(rng, plaintext, writer, sender, receivers, options);
+    }
+
+    let mut blocks = plaintext.chunks(options.block_len);
+    let next_block = |block: &mut Vec<u8>| {
+        let chunk = blocks.next().unwrap_or_default();
+        block.extend_from_slice(chunk);
+        Ok(chunk.len())
+    };
+    encrypt_blocks(rng, next_block, writer, sender, receivers, options, false)
+}
+
+/// Returns [`EncryptError::InvalidBlockLength`] if `block_len` is out of bounds.
+fn check_block_len(block_len: usize) -> Result<(), EncryptError> {
+    if !(MIN_BLOCK_LEN..=MAX_BLOCK_LEN).contains(&block_len) {
+        return Err(EncryptError::InvalidBlockLength);
+    }
+    Ok(())
+}
+
+/// Encrypt the blocks returned by `next_block`, which appends up to `options.block_len` bytes of
+/// plaintext to the given buffer and returns the number of bytes appended, or zero at the end of
+/// the plaintext.
+fn encrypt_blocks(
+    mut rng: impl Rng + CryptoRng,
+    next_block: impl FnMut(&mut Vec<u8>) -> Result<usize, EncryptError>,
     mut writer: impl Write,
     sender: &SecKey,
     receivers: &[Option<PubKey>],
+    options: &EncryptOptions,
+    compressed: bool,
 ) -> Result<u64, EncryptError> {
-    // Initialize a protocol and mix the sender's public key into it.
-    let mut message = Protocol::new("veil.message");
-    message.mix("sender", &sender.pub_key.encoded);
+    let block_len = options.block_len;
+
+    // Encode the file metadata, if any, ensuring it has a valid name and fits in a single block.
+    let metadata = options.metadata.as_ref().map(FileMetadata::encode);
+    if let Some(encoded) = &metadata {
+        let valid = options.metadata.as_ref().is_some_and(FileMetadata::has_valid_name);
+        if !valid || encoded.len() > block_len {
+            return Err(EncryptError::InvalidMetadata);
+        }
+    }
+
+    // Initialize a protocol for the current format version and mix the sender's public key into it.
+    let mut message = init(FORMAT_VERSION, &sender.pub_key);
 
     // Generate a random DEK.
     let dek = rng.gen::<[u8; DEK_LEN]>();
 
-    // Encode a header with the DEK and receiver count.
-    let header = Header::new(dek, receivers.len()).encode();
+    // Encode a header with the format version, DEK, receiver count, block length, and compression
+    // flag.
+    let header = Header::new(dek, receivers.len(), block_len, compressed).encode();
+
+    // Encapsulate shared secrets for all receivers, in parallel if there are many.
+    let kems = encapsulate_all(&mut rng, receivers);
 
     // For each receiver, encrypt a copy of the header.
     let mut written = 0;
     let mut enc_header = [0u8; ENC_HEADER_LEN];
-    for receiver in receivers {
+    for (receiver, kem) in receivers.iter().zip(kems) {
         // Encrypt the header for the given receiver, if any, or use random data to create a fake
         // recipient.
-        if let Some(receiver) = receiver {
-            encrypt_header(message.clone(), &mut rng, receiver, &header, &mut enc_header);
-        } else {
-            rng.fill_bytes(&mut enc_header);
+        match (receiver, kem) {
+            (Some(receiver), Some(kem)) => {
+                encrypt_header(message.clone(), receiver, kem, &header, &mut enc_header);
+            }
+            _ => rng.fill_bytes(&mut enc_header),
         }
 
         // Mix the encrypted header into the protocol.
@@ -73,28 +196,77 @@ pub fn encrypt(
     message.mix("dek", &dek);
 
     // Encrypt the plaintext in blocks and write them, then sign the message.
-    written += encrypt_message(&mut rng, message, reader, writer, sender)?;
+    written += encrypt_message(
+        &mut rng,
+        message,
+        metadata.as_deref(),
+        next_block,
+        writer,
+        sender,
+        block_len,
+    )?;
 
     Ok(written)
 }
 
-/// Given an initialized protocol, the receiver's public key and a plaintext header, encrypts the
-/// given header and returns the ciphertext.
+/// The minimum number of receivers for which encapsulation is split across threads.
+const PARALLEL_RECEIVERS: usize = 16;
+
+/// An obfuscated ML-KEM-768 ciphertext and its shared secret.
+type Kem = ([u8; ENC_CT_LEN], [u8; ML_KEM_SS_LEN]);
+
+/// Encapsulates a shared secret for each of the given receivers, returning `None` for fake
+/// receivers.
+///
+/// Each receiver's encapsulation uses its own RNG, seeded from `rng` in receiver order, so the
+/// results are independent of how the work is split across threads.
+fn encapsulate_all(
+    mut rng: impl Rng + CryptoRng,
+    receivers: &[Option<PubKey>],
+) -> Vec<Option<Kem>> {
+    let jobs = receivers
+        .iter()
+        .map(|receiver| receiver.as_ref().map(|pk| (pk, rng.gen::<[u8; 32]>())))
+        .collect::<Vec<_>>();
+    let encapsulate = |job: &Option<(&PubKey, [u8; 32])>| {
+        job.map(|(pk, seed)| kemeleon::encapsulate(&pk.ek, ChaCha20Rng::from_seed(seed)))
+    };
+
+    // Encapsulate small receiver lists on the current thread.
+    let threads = thread::available_parallelism().map_or(1, NonZeroUsize::get);
+    if threads == 1 || jobs.len() < PARALLEL_RECEIVERS {
+        return jobs.iter().map(encapsulate).collect();
+    }
+
+    // Otherwise, split the receivers into contiguous chunks and encapsulate each on its own
+    // thread, preserving the receivers' order.
+    let chunk_len = jobs.len().div_ceil(threads);
+    thread::scope(|s| {
+        jobs.chunks(chunk_len)
+            .map(|chunk| s.spawn(move || chunk.iter().map(encapsulate).collect::<Vec<_>>()))
+            .collect::<Vec<_>>()
+            .into_iter()
+            .flat_map(|handle| handle.join().expect("encapsulation should not panic"))
+            .collect()
+    })
+}
+
+/// Given an initialized protocol, the receiver's public key, an encapsulated shared secret, and a
+/// plaintext header, encrypts the given header and returns the ciphertext.
 fn encrypt_header(
     mut protocol: Protocol,
-    mut rng: impl RngCore + CryptoRng,
     receiver: &PubKey,
+    (kem_ect, kem_ss): Kem,
     plaintext: &[u8; HEADER_LEN],
     ciphertext: &mut [u8; ENC_HEADER_LEN],
 ) {
     // Split up the output buffer.
     let (out_kem, out_ciphertext) = ciphertext.split_at_mut(ENC_CT_LEN);
 
-    // Mix the receiver's public key into the protocol.
-    protocol.mix("receiver", &receiver.encoded);
+    // Mix the receiver's encapsulation key into the protocol.
+    protocol.mix("receiver", receiver.ek_encoded());
 
-    // Encapsulate a shared secret with ML-KEM and mix it into the protocol's state.
-    let (kem_ect, kem_ss) = kemeleon::encapsulate(&receiver.ek, &mut rng);
+    // Mix the ML-KEM ciphertext into the protocol's state.
     out_kem.copy_from_slice(&kem_ect);
     protocol.mix("ml-kem-768-ect", out_kem);
 
@@ -106,27 +278,46 @@ fn encrypt_header(
     protocol.seal("header", out_ciphertext);
 }
 
-/// Given a protocol keyed with the DEK, read the entire contents of `reader` in blocks and write
-/// the encrypted blocks and authentication tags to `writer`, then add a signature of the protocol's
-/// final state.
+/// Given a protocol keyed with the DEK, write the encoded file metadata, if any, as a metadata
+/// block, read the entire plaintext from `next_block` in blocks and write the encrypted blocks and
+/// authentication tags to `writer`, then add a signature of the protocol's final state.
 fn encrypt_message(
     mut rng: impl Rng + CryptoRng,
     mut message: Protocol,
-    mut reader: impl Read,
+    metadata: Option<&[u8]>,
+    mut next_block: impl FnMut(&mut Vec<u8>) -> Result<usize, EncryptError>,
     mut writer: impl Write,
     sender: &SecKey,
+    block_len: usize,
 ) -> Result<u64, EncryptError> {
-    let mut block = Vec::with_capacity(BLOCK_LEN + TAG_LEN);
+    let mut block = Vec::with_capacity(block_len + TAG_LEN);
     let mut block_header = [0u8; ENC_BLOCK_HEADER_LEN];
     let mut read = 0;
     let mut written = 0;
 
+    // Write the metadata block first, if any.
+    if let Some(metadata) = metadata {
+        read += u64::try_from(metadata.len()).expect("usize should be <= u64");
+
+        // Encode, seal, and write a metadata block header.
+        block_header[0] = BlockType::Metadata as u8;
+        block_header[1..4].copy_from_slice(&(metadata.len() as u32).to_le_bytes()[..3]);
+        message.seal("block-header", &mut block_header);
+        writer.write_all(&block_header).map_err(EncryptError::WriteIo)?;
+        written += u64::try_from(block_header.len()).expect("usize should be <= u64");
+
+        // Seal the metadata block and write it.
+        block.extend_from_slice(metadata);
+        block.resize(metadata.len() + TAG_LEN, 0);
+        message.seal("block", &mut block);
+        writer.write_all(&block).map_err(EncryptError::WriteIo)?;
+        written += u64::try_from(block.len()).expect("usize should be <= u64");
+        block.truncate(0);
+    }
+
     loop {
         // Read a block of data.
-        let n = (&mut reader)
-            .take(BLOCK_LEN as u64)
-            .read_to_end(&mut block)
-            .map_err(EncryptError::ReadIo)?;
+        let n = next_block(&mut block)?;
         read += u64::try_from(n).expect("usize should be <= u64");
 
         // Break if we're at the end of the reader.
@@ -148,7 +339,7 @@ fn encrypt_message(
         written += u64::try_from(block.len()).expect("usize should be <= u64");
 
         // If the block was undersized, we're at the end of the reader.
-        if n < BLOCK_LEN {
+        if n < block_len {
             break;
         }
 
@@ -157,24 +348,34 @@ fn encrypt_message(
     }
 
     // Calculate the number of bytes to automatically pad the message with.
-    let padding_len = padding_len(read);
-
-    // Encode, seal, and write a padding block header.
-    block_header[0] = BlockType::Padding as u8;
-    block_header[1..4].copy_from_slice(&(padding_len as u32).to_le_bytes()[..3]);
-    message.seal("block-header", &mut block_header);
-    writer.write_all(&block_header).map_err(EncryptError::WriteIo)?;
-    written += u64::try_from(block_header.len()).expect("usize should be <= u64");
-
-    // Seal and write the padding block.
-    let mut padding = vec![0u8; padding_len + TAG_LEN];
-    rng.fill_bytes(&mut padding[..padding_len]);
-    message.seal("block", &mut padding);
-    writer.write_all(&padding).map_err(EncryptError::WriteIo)?;
-    written += u64::try_from(padding.len()).expect("usize should be <= u64");
+    let mut padding_len = padding_len(read);
+
+    // Write the padding in blocks, ending with an undersized (possibly empty) block.
+    loop {
+        let n = padding_len.min(block_len);
+        padding_len -= n;
+
+        // Encode, seal, and write a padding block header.
+        block_header[0] = BlockType::Padding as u8;
+        block_header[1..4].copy_from_slice(&(n as u32).to_le_bytes()[..3]);
+        message.seal("block-header", &mut block_header);
+        writer.write_all(&block_header).map_err(EncryptError::WriteIo)?;
+        written += u64::try_from(block_header.len()).expect("usize should be <= u64");
+
+        // Seal and write the padding block.
+        block.resize(n + TAG_LEN, 0);
+        rng.fill_bytes(&mut block[..n]);
+        message.seal("block", &mut block);
+        writer.write_all(&block).map_err(EncryptError::WriteIo)?;
+        written += u64::try_from(block.len()).expect("usize should be <= u64");
+
+        if n < block_len {
+            break;
+        }
+    }
 
     // Sign the protocol's final state with the sender's secret key and append the signature.
-    let sig = sig::sign_protocol(&mut rng, &mut message, sender);
+    let sig = sig::sign_protocol(&mut rng, &mut message, &sender.sign_key);
     writer.write_all(&sig).map_err(EncryptError::WriteIo)?;
     written += u64::try_from(sig.len()).expect("usize should be <= u64");
 
@@ -183,45 +384,142 @@ fn encrypt_message(
 }
 
 /// Decrypt the contents of `reader` iff they were originally encrypted by `q_s` for `q_r` and write
-/// the plaintext to `writer`.
+/// the plaintext to `writer`, returning the number of bytes written and the file metadata, if any.
+///
+/// Unversioned messages commit to the receiver's entire public key, so they can only be decrypted
+/// if `receiver_pk` is given.
 pub fn decrypt(
     mut reader: impl Read,
     mut writer: impl Write,
-    receiver: &SecKey,
+    receiver: &DecKey,
+    receiver_pk: Option<&PubKey>,
     sender: &PubKey,
-) -> Result<u64, DecryptError> {
-    // Initialize a protocol and mix the sender's public key into it.
-    let mut message = Protocol::new("veil.message");
-    message.mix("sender", &sender.encoded);
+    limits: &DecryptLimits,
+) -> Result<(u64, Option<FileMetadata>), DecryptError> {
+    // Initialize a protocol for each supported format version and mix the sender's public key into
+    // them.
+    let candidates = SUPPORTED_VERSIONS
+        .into_iter()
+        .filter_map(|version| {
+            let receiver = if version == UNVERSIONED {
+                &receiver_pk?.encoded[..]
+            } else {
+                &receiver.ek_encoded[..]
+            };
+            Some(Candidate { version, message: init(version, sender), receiver })
+        })
+        .collect();
+
+    // Track the number of ciphertext bytes which can be read.
+    let mut budget = ReadBudget(limits.max_ciphertext_len);
 
     // Find a header, decrypt it, and mix the entirety of the headers and padding into the protocol.
-    let (mut message, dek) = decrypt_headers(message, &mut reader, receiver)?;
+    let (mut message, header, rest) =
+        decrypt_headers(candidates, &mut reader, receiver, limits.max_headers, &mut budget)?;
+
+    // Any bytes read past the end of the headers belong to the first block.
+    let mut reader = io::Cursor::new(rest).chain(reader);
 
     // Mix the DEK into the protocol.
-    message.mix("dek", &dek);
+    message.mix("dek", &header.dek);
+
+    // Decrypt the message, decompressing it if necessary.
+    let (written, (sig, metadata)) = if header.compressed {
+        #[cfg(feature = "compression")]
+        {
+            let mut decompressor =
+                compression::Decompressor::new(&mut writer, limits.max_plaintext_len);
+            let result = decrypt_message(
+                &mut message,
+                &mut reader,
+                |plaintext| decompressor.write(plaintext),
+                header.version,
+                header.block_len,
+                limits.max_block_len,
+                &mut budget,
+            )?;
+            (decompressor.finish()?, result)
+        }
+        #[cfg(not(feature = "compression"))]
+        return Err(DecryptError::UnsupportedCompression);
+    } else {
+        let mut written = 0;
+        let result = decrypt_message(
+            &mut message,
+            &mut reader,
+            |plaintext| {
+                written += u64::try_from(plaintext.len()).expect("usize should be <= u64");
+                if limits.max_plaintext_len.is_some_and(|max| written > max) {
+                    return Err(DecryptError::LimitExceeded);
+                }
+                writer.write_all(plaintext).map_err(DecryptError::WriteIo)
+            },
+            header.version,
+            header.block_len,
+            limits.max_block_len,
+            &mut budget,
+        )?;
+        (written, result)
+    };
+
+    // Verify the signature and return the number of bytes written and the metadata.
+    sig::verify_protocol(&mut message, &sender.ver_key, sig)
+        .and(Some((written, metadata)))
+        .ok_or(DecryptError::InvalidCiphertext)
+}
 
-    // Decrypt the message.
-    let (written, sig) = decrypt_message(&mut message, &mut reader, &mut writer)?;
+/// The remaining number of ciphertext bytes which can be read, if limited.
+struct ReadBudget(Option<u64>);
 
-    // Verify the signature and return the number of bytes written.
-    sig::verify_protocol(&mut message, sender, sig.try_into().expect("should be signature sized"))
-        .and(Some(written))
-        .ok_or(DecryptError::InvalidCiphertext)
+impl ReadBudget {
+    /// Deducts `n` bytes from the budget, returning [`DecryptError::LimitExceeded`] if there aren't
+    /// enough remaining.
+    fn spend(&mut self, n: usize) -> Result<(), DecryptError> {
+        if let Some(remaining) = &mut self.0 {
+            let n = u64::try_from(n).expect("usize should be <= u64");
+            *remaining = remaining.checked_sub(n).ok_or(DecryptError::LimitExceeded)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the number of bytes, up to `n`, which can be read.
+    fn allowance(&self, n: usize) -> usize {
+        self.0.map_or(n, |remaining| usize::try_from(remaining).unwrap_or(usize::MAX).min(n))
+    }
+
+    /// Returns `n` bytes which were deducted from the budget but not consumed.
+    fn refund(&mut self, n: usize) {
+        if let Some(remaining) = &mut self.0 {
+            *remaining += u64::try_from(n).expect("usize should be <= u64");
+        }
+    }
 }
 
-/// Given a protocol keyed with the DEK, read the entire contents of `reader` in blocks and write
-/// the decrypted blocks `writer`.
+/// Given a protocol keyed with the DEK, read the entire contents of `reader` in blocks of at most
+/// `sealed_block_len` bytes, pass the decrypted data blocks to `sink`, and return the signature and
+/// the file metadata, if any.
+///
+/// Only the last data block and the last padding block may be shorter than `sealed_block_len`, and
+/// a metadata block may only be the first block. Unversioned messages have no metadata blocks and
+/// end with a single padding block of any length.
 fn decrypt_message(
     message: &mut Protocol,
     mut reader: impl Read,
-    mut writer: impl Write,
-) -> Result<(u64, Vec<u8>), DecryptError> {
+    mut sink: impl FnMut(&[u8]) -> Result<(), DecryptError>,
+    version: u8,
+    sealed_block_len: usize,
+    max_block_len: Option<usize>,
+    budget: &mut ReadBudget,
+) -> Result<([u8; SIG_LEN], Option<FileMetadata>), DecryptError> {
     let mut header = [0u8; ENC_BLOCK_HEADER_LEN];
-    let mut buf = Vec::with_capacity(BLOCK_LEN + TAG_LEN);
-    let mut written = 0;
+    let mut buf = Vec::with_capacity(sealed_block_len + TAG_LEN);
+    let mut data_done = false;
+    let mut first = true;
+    let mut metadata = None;
 
     loop {
         // Read and open a block header.
+        budget.spend(header.len())?;
         reader.read_exact(&mut header).map_err(DecryptError::ReadIo)?;
         let header =
             message.open("block-header", &mut header).ok_or(DecryptError::InvalidCiphertext)?;
@@ -229,118 +527,325 @@ fn decrypt_message(
             + ((header[2] as usize) << 8)
             + ((header[3] as usize) << 16)
             + TAG_LEN;
+        let block_type = BlockType::try_from(header[0]);
+        let unversioned_padding =
+            version == UNVERSIONED && matches!(block_type, Ok(BlockType::Padding));
+        if max_block_len.is_some_and(|max| block_len - TAG_LEN > max) {
+            return Err(DecryptError::LimitExceeded);
+        }
+        if block_len - TAG_LEN > sealed_block_len && !unversioned_padding {
+            return Err(DecryptError::InvalidCiphertext);
+        }
         buf.resize(block_len, 0);
 
         // Read and open the block.
+        budget.spend(block_len)?;
         reader.read_exact(&mut buf[..block_len]).map_err(DecryptError::ReadIo)?;
         let plaintext =
             message.open("block", &mut buf[..block_len]).ok_or(DecryptError::InvalidCiphertext)?;
-        match BlockType::try_from(header[0]) {
+        let is_first = std::mem::replace(&mut first, false);
+        match block_type {
+            Ok(BlockType::Metadata) if version == UNVERSIONED => {
+                return Err(DecryptError::InvalidBlockType(header[0]));
+            }
+            Ok(BlockType::Metadata) => {
+                // Metadata blocks must be first and must be valid.
+                if !is_first {
+                    return Err(DecryptError::InvalidCiphertext);
+                }
+                metadata =
+                    Some(FileMetadata::decode(plaintext).ok_or(DecryptError::InvalidCiphertext)?);
+            }
             Ok(BlockType::Data) => {
+                // Data blocks can't follow an undersized data block or padding.
+                if data_done {
+                    return Err(DecryptError::InvalidCiphertext);
+                }
+                data_done = plaintext.len() < sealed_block_len;
+
                 // Write the plaintext.
-                writer.write_all(plaintext).map_err(DecryptError::WriteIo)?;
-                written += u64::try_from(plaintext.len()).expect("usize should be <= u64");
+                sink(plaintext)?;
+            }
+            Ok(BlockType::Padding)
+                if plaintext.len() == sealed_block_len && !unversioned_padding =>
+            {
+                // Ignore full padding blocks, which must be followed by more padding.
+                data_done = true;
             }
             Ok(BlockType::Padding) => {
-                // Ignore the padding and read the final signature.
-                buf.truncate(0);
-                reader.read_to_end(&mut buf).map_err(DecryptError::ReadIo)?;
-                buf.shrink_to_fit();
-
-                // Return the number of written bytes and the signature.
-                return Ok((written, buf));
+                // Ignore the final padding block and read the final signature.
+                let mut sig = [0u8; SIG_LEN];
+                budget.spend(sig.len())?;
+                reader.read_exact(&mut sig).map_err(|e| {
+                    if e.kind() == io::ErrorKind::UnexpectedEof {
+                        DecryptError::InvalidCiphertext
+                    } else {
+                        DecryptError::ReadIo(e)
+                    }
+                })?;
+
+                // Ensure there's nothing after the signature.
+                if reader.read(&mut [0u8; 1]).map_err(DecryptError::ReadIo)? != 0 {
+                    return Err(DecryptError::InvalidCiphertext);
+                }
+
+                // Return the signature and metadata.
+                return Ok((sig, metadata));
             }
             Err(b) => return Err(DecryptError::InvalidBlockType(b)),
         }
     }
 }
 
+/// Initializes a message protocol for the given format version and mixes the sender's public key
+/// into it.
+fn init(version: u8, sender: &PubKey) -> Protocol {
+    let mut message = Protocol::new(&version::domain("veil.message", version));
+    message.mix("sender", &sender.encoded);
+    message
+}
+
+/// A format version's protocol, used to search for a header encrypted for the receiver.
+struct Candidate<'a> {
+    version: u8,
+    message: Protocol,
+
+    /// The receiver's key, as mixed into the version's headers: the encapsulation key, or the
+    /// entire public key for unversioned headers.
+    receiver: &'a [u8],
+}
+
+impl Candidate<'_> {
+    /// Returns the length of the version's encrypted headers.
+    const fn enc_header_len(&self) -> u64 {
+        if self.version == UNVERSIONED {
+            ENC_UNVERSIONED_HEADER_LEN as u64
+        } else {
+            ENC_HEADER_LEN as u64
+        }
+    }
+
+    /// Returns the offset of the start of the version's `i`th encrypted header.
+    const fn header_start(&self, i: u64) -> u64 {
+        i * self.enc_header_len()
+    }
+
+    /// Returns the offset of the end of the version's `i`th encrypted header.
+    const fn header_end(&self, i: u64) -> u64 {
+        (i + 1) * self.enc_header_len()
+    }
+
+    /// Returns the `i`th encrypted header from a buffer beginning at the given offset.
+    fn header<'b>(&self, buf: &'b [u8], buf_start: u64, i: u64) -> &'b [u8] {
+        let start = usize::try_from(self.header_start(i) - buf_start)
+            .expect("buffered headers should be addressable");
+        &buf[start..start + usize::try_from(self.enc_header_len()).expect("should be <= usize")]
+    }
+}
+
 /// Iterate through the contents of `reader` looking for a header which was encrypted by the given
-/// sender for the given receiver.
+/// sender for the given receiver with any of the candidate format versions' protocols. Returns the
+/// header's protocol, the header, and any bytes which were read past the end of the headers.
+///
+/// Each version's headers have their own length, so each candidate reads headers at its own
+/// offsets, and bytes are buffered until every remaining candidate has processed them.
 fn decrypt_headers(
-    mut message: Protocol,
+    mut candidates: Vec<Candidate<'_>>,
     mut reader: impl Read,
-    receiver: &SecKey,
-) -> Result<(Protocol, [u8; DEK_LEN]), DecryptError> {
-    let mut enc_header = [0u8; ENC_HEADER_LEN];
-    let mut dek = None;
+    receiver: &DecKey,
+    max_headers: Option<u64>,
+    budget: &mut ReadBudget,
+) -> Result<(Protocol, Header, Vec<u8>), DecryptError> {
+    let mut buf = Vec::new();
+    let mut buf_start = 0u64;
+    let mut header = None;
     let mut i = 0u64;
     let mut recv_count = u64::MAX;
 
     // Iterate through blocks, looking for an encrypted header that can be decrypted.
     while i < recv_count {
-        // Read a potential encrypted header. If the header is short, we're at the end of the
-        // reader.
-        reader.read_exact(&mut enc_header).map_err(|e| {
-            if e.kind() == io::ErrorKind::UnexpectedEof {
-                DecryptError::InvalidCiphertext
-            } else {
-                DecryptError::ReadIo(e)
-            }
-        })?;
-
-        // Clone the protocol at its state before this header is processed.
-        let clone = message.clone();
+        // Stop before trial-decrypting more headers than allowed.
+        if max_headers.is_some_and(|max| i >= max) {
+            return Err(DecryptError::LimitExceeded);
+        }
 
-        // Mix the encrypted header into the protocol.
-        message.mix("header", &enc_header);
+        // Read enough to hold each candidate's next potential encrypted header, as far as the
+        // budget allows.
+        let buf_end = buf_start + u64::try_from(buf.len()).expect("usize should be <= u64");
+        let end = candidates.iter().map(|c| c.header_end(i)).max().unwrap_or(buf_end);
+        let wanted = usize::try_from(end.saturating_sub(buf_end)).expect("should be <= usize");
+        let allowed = budget.allowance(wanted);
+        let n = (&mut reader)
+            .take(u64::try_from(allowed).expect("usize should be <= u64"))
+            .read_to_end(&mut buf)
+            .map_err(DecryptError::ReadIo)?;
+        budget.spend(n)?;
+
+        // If a candidate's header is short, we're at the end of the reader for that version.
+        let buf_end = buf_start + u64::try_from(buf.len()).expect("usize should be <= u64");
+        candidates.retain(|c| c.header_end(i) <= buf_end);
+        if candidates.is_empty() {
+            return Err(if allowed < wanted {
+                DecryptError::LimitExceeded
+            } else {
+                DecryptError::InvalidCiphertext
+            });
+        }
 
-        // If a header hasn't been decrypted yet, try to decrypt this one.
-        if dek.is_none() {
-            if let Some(hdr) = decrypt_header(clone, receiver, &mut enc_header) {
-                // If the header was successfully decrypted, keep the DEK and update the loop
-                // variable to not be effectively infinite.
-                let hdr = Header::decode(hdr);
+        // If a header hasn't been decrypted yet, try to decrypt this one with each candidate's
+        // protocol at its state before this header is processed. Candidates whose headers start at
+        // the same offset share an ML-KEM ciphertext, so each one is only decapsulated once.
+        if header.is_none() {
+            let mut secrets: Vec<(u64, [u8; ML_KEM_SS_LEN])> = Vec::with_capacity(candidates.len());
+            let found = candidates.iter().enumerate().find_map(|(j, c)| {
+                let enc_header = c.header(&buf, buf_start, i);
+                let start = c.header_start(i);
+                let kem_ss = if let Some(&(_, kem_ss)) = secrets.iter().find(|(s, _)| *s == start) {
+                    kem_ss
+                } else {
+                    let kem_ss = decapsulate(receiver, enc_header);
+                    secrets.push((start, kem_ss));
+                    kem_ss
+                };
+                decrypt_header(c, &kem_ss, enc_header).map(|hdr| (j, hdr))
+            });
+            if let Some((j, hdr)) = found {
+                // If the header was successfully decrypted, keep it and update the loop variable to
+                // not be effectively infinite.
+                let version = candidates[j].version;
+                let hdr = if version == UNVERSIONED {
+                    Some(Header::decode_unversioned(&hdr))
+                } else {
+                    Header::decode(&hdr, version)
+                }
+                .ok_or(DecryptError::InvalidCiphertext)?;
+                if max_headers.is_some_and(|max| hdr.recv_count > max) {
+                    return Err(DecryptError::LimitExceeded);
+                }
                 recv_count = hdr.recv_count;
-                dek = Some(hdr.dek);
+                header = Some(hdr);
+
+                // Only the header's version needs to be processed from here on.
+                candidates = vec![candidates.swap_remove(j)];
             }
         }
 
+        // Mix the encrypted header into the protocols.
+        for c in &mut candidates {
+            c.message.mix("header", c.header(&buf, buf_start, i));
+        }
+
+        // Discard the bytes which every candidate has processed.
+        let processed =
+            candidates.iter().map(|c| c.header_end(i)).min().expect("should have candidates");
+        buf.drain(..usize::try_from(processed - buf_start).expect("should be <= usize"));
+        buf_start = processed;
+
         i += 1;
     }
 
-    // Return the protocol and DEK.
-    Ok((message, dek.ok_or(DecryptError::InvalidCiphertext)?))
+    // Return the protocol, header, and any bytes read past the headers, which no longer count
+    // against the budget.
+    let header = header.ok_or(DecryptError::InvalidCiphertext)?;
+    let candidate = candidates.pop().expect("should have the header's version");
+    budget.refund(buf.len());
+    Ok((candidate.message, header, buf))
+}
+
+#[cfg(test)]
+thread_local! {
+    /// The number of ML-KEM ciphertexts decapsulated by [`decapsulate`] on this thread.
+    static DECAPSULATIONS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+/// Given the receiver's key pair and an encrypted header, decapsulates the ML-KEM shared secret
+/// from the ciphertext at the start of the header.
+#[must_use]
+fn decapsulate(receiver: &DecKey, enc_header: &[u8]) -> [u8; ML_KEM_SS_LEN] {
+    #[cfg(test)]
+    DECAPSULATIONS.with(|n| n.set(n.get() + 1));
+
+    let kem_ect = &enc_header[..ENC_CT_LEN];
+    kemeleon::decapsulate(&receiver.dk, kem_ect.try_into().expect("should be 1252 bytes"))
 }
 
-/// Given the receiver's key pair and an encrypted header, decrypts the ciphertext and returns the
-/// plaintext iff the ciphertext was encrypted for the receiver.
+/// Given a candidate protocol, the ML-KEM shared secret decapsulated from an encrypted header, and
+/// the encrypted header, decrypts the ciphertext and returns the plaintext iff the ciphertext was
+/// encrypted for the receiver with the candidate's version.
 #[must_use]
-fn decrypt_header<'a>(
-    mut message: Protocol,
-    receiver: &SecKey,
-    in_out: &'a mut [u8; ENC_HEADER_LEN],
-) -> Option<&'a [u8]> {
+fn decrypt_header(
+    candidate: &Candidate<'_>,
+    kem_ss: &[u8; ML_KEM_SS_LEN],
+    enc_header: &[u8],
+) -> Option<Vec<u8>> {
     // Split the ciphertext into its components.
-    let (kem_ect, ciphertext) = in_out.split_at_mut(ENC_CT_LEN);
+    let (kem_ect, ciphertext) = enc_header.split_at(ENC_CT_LEN);
+    let mut message = candidate.message.clone();
 
-    // Mix the receiver's public key into the protocol.
-    message.mix("receiver", &receiver.pub_key.encoded);
+    // Mix the receiver's key into the protocol.
+    message.mix("receiver", candidate.receiver);
 
-    // Mix the ML-KEM ciphertext into the protocol, decapsulate the ML-KEM shared secret, then mix
-    // the shared secret into the protocol.
+    // Mix the ML-KEM ciphertext and shared secret into the protocol.
     message.mix("ml-kem-768-ect", kem_ect);
-    let kem_ss =
-        kemeleon::decapsulate(&receiver.dk, kem_ect.try_into().expect("should be 1252 bytes"));
-    message.mix("ml-kem-768-ss", &kem_ss);
+    message.mix("ml-kem-768-ss", kem_ss);
 
     // Open the plaintext.
-    message.open("header", ciphertext)
+    let mut in_out = ciphertext.to_vec();
+    let len = message.open("header", &mut in_out)?.len();
+    in_out.truncate(len);
+    Some(in_out)
 }
 
 struct Header {
+    version: u8,
     dek: [u8; DEK_LEN],
     recv_count: u64,
+    block_len: usize,
+    compressed: bool,
 }
 
 impl Header {
-    fn new(dek: [u8; DEK_LEN], recv_count: usize) -> Header {
-        Header { dek, recv_count: recv_count.try_into().expect("usize should be <= u64") }
+    fn new(dek: [u8; DEK_LEN], recv_count: usize, block_len: usize, compressed: bool) -> Header {
+        let recv_count = recv_count.try_into().expect("usize should be <= u64");
+        Header { version: FORMAT_VERSION, dek, recv_count, block_len, compressed }
     }
 
+    /// Decodes a header which was opened with the given format version's protocol, returning `None`
+    /// if it commits to a different version, the block length is out of bounds, or any unknown
+    /// flags are set.
     #[inline]
     #[must_use]
-    fn decode(header: &[u8]) -> Header {
+    fn decode(header: &[u8], version: u8) -> Option<Header> {
+        // Split header into components.
+        let (hdr_version, header) = header.split_at(size_of::<u8>());
+        let (dek, header) = header.split_at(DEK_LEN);
+        let (recv_count, header) = header.split_at(size_of::<u64>());
+        let (block_len, flags) = header.split_at(size_of::<u32>());
+
+        // Decode components.
+        if hdr_version[0] != version {
+            return None;
+        }
+        let dek = dek.try_into().expect("should be DEK-sized");
+        let recv_count = u64::from_le_bytes(recv_count.try_into().expect("should be 8 bytes"));
+        let block_len = u32::from_le_bytes(block_len.try_into().expect("should be 4 bytes"));
+        let block_len = usize::try_from(block_len).expect("u32 should be <= usize");
+        if !(MIN_BLOCK_LEN..=MAX_BLOCK_LEN).contains(&block_len) {
+            return None;
+        }
+        let compressed = match flags[0] {
+            0 => false,
+            FLAG_COMPRESSED => true,
+            _ => return None,
+        };
+
+        Some(Header { version, dek, recv_count, block_len, compressed })
+    }
+
+    /// Decodes an unversioned header, which has fixed-length blocks and no flags.
+    #[inline]
+    #[must_use]
+    fn decode_unversioned(header: &[u8]) -> Header {
         // Split header into components.
         let (dek, recv_count) = header.split_at(DEK_LEN);
 
@@ -348,16 +853,30 @@ impl Header {
         let dek = dek.try_into().expect("should be DEK-sized");
         let recv_count = u64::from_le_bytes(recv_count.try_into().expect("should be 8 bytes"));
 
-        Header { dek, recv_count }
+        Header {
+            version: UNVERSIONED,
+            dek,
+            recv_count,
+            block_len: UNVERSIONED_BLOCK_LEN,
+            compressed: false,
+        }
     }
 
     #[inline]
     #[must_use]
     fn encode(&self) -> [u8; HEADER_LEN] {
         let mut header = [0u8; HEADER_LEN];
-        let (hdr_dek, hdr_recv_count) = header.split_at_mut(DEK_LEN);
+        let (hdr_version, hdr) = header.split_at_mut(size_of::<u8>());
+        let (hdr_dek, hdr) = hdr.split_at_mut(DEK_LEN);
+        let (hdr_recv_count, hdr) = hdr.split_at_mut(size_of::<u64>());
+        let (hdr_block_len, hdr_flags) = hdr.split_at_mut(size_of::<u32>());
+        hdr_version[0] = self.version;
         hdr_dek.copy_from_slice(&self.dek);
         hdr_recv_count.copy_from_slice(&self.recv_count.to_le_bytes());
+        hdr_block_len.copy_from_slice(
+            &u32::try_from(self.block_len).expect("block length should be <= u32").to_le_bytes(),
+        );
+        hdr_flags[0] = if self.compressed { FLAG_COMPRESSED } else { 0 };
         header
     }
 }
@@ -375,11 +894,12 @@ fn padding_len(len: u64) -> usize {
     usize::try_from(((len + mask) & !mask) - len).expect("should be <= usize")
 }
 
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug)]
 #[repr(u8)]
 enum BlockType {
     Data = 0x00,
     Padding = 0x01,
+    Metadata = 0x02,
 }
 
 impl TryFrom<u8> for BlockType {
@@ -389,9 +909,11 @@ impl TryFrom<u8> for BlockType {
         // inline when inline_const_pat lands
         const DATA: u8 = BlockType::Data as u8;
         const PADDING: u8 = BlockType::Padding as u8;
+        const METADATA: u8 = BlockType::Metadata as u8;
         match value {
             DATA => Ok(BlockType::Data),
             PADDING => Ok(BlockType::Padding),
+            METADATA => Ok(BlockType::Metadata),
             _ => Err(value),
         }
     }
@@ -413,13 +935,40 @@ mod tests {
 
         let mut writer = Cursor::new(Vec::new());
 
-        let ptx_len = decrypt(Cursor::new(ciphertext), &mut writer, &receiver, &sender.pub_key)
-            .expect("decryption should be ok");
+        let (ptx_len, _) = decrypt(
+            Cursor::new(ciphertext),
+            &mut writer,
+            &receiver.dec_key,
+            Some(&receiver.pub_key),
+            &sender.pub_key,
+            &DecryptLimits::default(),
+        )
+        .expect("decryption should be ok");
 
         assert_eq!(writer.position(), ptx_len, "returned/observed plaintext length mismatch");
         assert_eq!(plaintext.to_vec(), writer.into_inner(), "incorrect plaintext");
     }
 
+    #[test]
+    fn shared_header_decapsulated_once() {
+        let (_, sender, receiver, _, ciphertext) = setup(64);
+
+        // The receiver's header is the second one, and the first is at the same offset for every
+        // version, so each header should only be decapsulated once.
+        DECAPSULATIONS.with(|n| n.set(0));
+        decrypt(
+            Cursor::new(ciphertext),
+            Cursor::new(Vec::new()),
+            &receiver.dec_key,
+            Some(&receiver.pub_key),
+            &sender.pub_key,
+            &DecryptLimits::default(),
+        )
+        .expect("decryption should be ok");
+
+        assert_eq!(2, DECAPSULATIONS.with(std::cell::Cell::get), "redundant decapsulations");
+    }
+
     #[test]
     fn wrong_sender() {
         let (mut rng, _, receiver, _, ciphertext) = setup(64);
@@ -430,8 +979,10 @@ mod tests {
             decrypt(
                 Cursor::new(ciphertext),
                 Cursor::new(Vec::new()),
-                &receiver,
-                &wrong_sender.pub_key
+                &receiver.dec_key,
+                Some(&receiver.pub_key),
+                &wrong_sender.pub_key,
+                &DecryptLimits::default(),
             ),
             Err(DecryptError::InvalidCiphertext)
         );
@@ -447,8 +998,10 @@ mod tests {
             decrypt(
                 Cursor::new(ciphertext),
                 Cursor::new(Vec::new()),
-                &wrong_receiver,
-                &sender.pub_key
+                &wrong_receiver.dec_key,
+                Some(&wrong_receiver.pub_key),
+                &sender.pub_key,
+                &DecryptLimits::default(),
             ),
             Err(DecryptError::InvalidCiphertext)
         );
@@ -456,29 +1009,789 @@ mod tests {
 
     #[test]
     fn multi_block_message() {
-        let (_, sender, receiver, plaintext, ciphertext) = setup(BLOCK_LEN * 5 + 102);
+        let (_, sender, receiver, plaintext, ciphertext) = setup(DEFAULT_BLOCK_LEN * 5 + 102);
 
         let mut writer = Cursor::new(Vec::new());
-        let ptx_len = decrypt(Cursor::new(ciphertext), &mut writer, &receiver, &sender.pub_key)
-            .expect("decryption should be ok");
+        let (ptx_len, _) = decrypt(
+            Cursor::new(ciphertext),
+            &mut writer,
+            &receiver.dec_key,
+            Some(&receiver.pub_key),
+            &sender.pub_key,
+            &DecryptLimits::default(),
+        )
+        .expect("decryption should be ok");
+
+        assert_eq!(writer.position(), ptx_len, "returned/observed plaintext length mismatch");
+        assert_eq!(plaintext.to_vec(), writer.into_inner(), "incorrect plaintext");
+    }
+
+    #[test]
+    fn small_blocks() {
+        // The padding for this message spans multiple blocks.
+        let (_, sender, receiver, plaintext, ciphertext) =
+            setup_with_block_len(100_000, MIN_BLOCK_LEN);
+
+        let mut writer = Cursor::new(Vec::new());
+        let (ptx_len, _) = decrypt(
+            Cursor::new(ciphertext),
+            &mut writer,
+            &receiver.dec_key,
+            Some(&receiver.pub_key),
+            &sender.pub_key,
+            &DecryptLimits::default(),
+        )
+        .expect("decryption should be ok");
 
         assert_eq!(writer.position(), ptx_len, "returned/observed plaintext length mismatch");
         assert_eq!(plaintext.to_vec(), writer.into_inner(), "incorrect plaintext");
     }
 
+    #[test]
+    fn large_blocks() {
+        let (_, sender, receiver, plaintext, ciphertext) =
+            setup_with_block_len(MAX_BLOCK_LEN + 37, MAX_BLOCK_LEN);
+
+        let mut writer = Cursor::new(Vec::new());
+        decrypt(
+            Cursor::new(ciphertext),
+            &mut writer,
+            &receiver.dec_key,
+            Some(&receiver.pub_key),
+            &sender.pub_key,
+            &DecryptLimits::default(),
+        )
+        .expect("decryption should be ok");
+
+        assert_eq!(plaintext.to_vec(), writer.into_inner(), "incorrect plaintext");
+    }
+
+    #[test]
+    fn header_block_len_bounds() {
+        let encoded = Header::new([7; DEK_LEN], 3, MIN_BLOCK_LEN, true).encode();
+        let header = Header::decode(&encoded, FORMAT_VERSION).expect("header should be valid");
+        assert_eq!(
+            ([7; DEK_LEN], 3, MIN_BLOCK_LEN, true),
+            (header.dek, header.recv_count, header.block_len, header.compressed)
+        );
+
+        for block_len in [0, MIN_BLOCK_LEN - 1, MAX_BLOCK_LEN + 1] {
+            let mut header = Header::new([7; DEK_LEN], 3, MIN_BLOCK_LEN, false);
+            header.block_len = block_len;
+            assert!(
+                Header::decode(&header.encode(), FORMAT_VERSION).is_none(),
+                "accepted block length of {block_len}"
+            );
+        }
+
+        let mut encoded = Header::new([7; DEK_LEN], 3, MIN_BLOCK_LEN, false).encode();
+        encoded[HEADER_LEN - 1] = 0x02;
+        assert!(Header::decode(&encoded, FORMAT_VERSION).is_none(), "accepted unknown flags");
+
+        let encoded = Header::new([7; DEK_LEN], 3, MIN_BLOCK_LEN, false).encode();
+        assert!(Header::decode(&encoded, FORMAT_VERSION + 1).is_none(), "accepted wrong version");
+    }
+
+    #[test]
+    #[cfg(feature = "compression")]
+    fn compressed() {
+        let mut rng = ChaChaRng::seed_from_u64(0xDEADBEEF);
+        let sender = SecKey::random(&mut rng);
+        let plaintext = b"{\"level\":\"info\",\"msg\":\"hello\"}\n".repeat(10_000);
+
+        let mut ciphertext = Vec::new();
+        encrypt_slice(
+            &mut rng,
+            &plaintext,
+            &mut ciphertext,
+            &sender,
+            &[Some(sender.pub_key.clone())],
+            &EncryptOptions { compress: true, ..Default::default() },
+        )
+        .expect("encryption should be ok");
+        assert!(ciphertext.len() < plaintext.len() / 10, "should be compressed");
+
+        let mut writer = Cursor::new(Vec::new());
+        let (ptx_len, _) = decrypt(
+            Cursor::new(ciphertext),
+            &mut writer,
+            &sender.dec_key,
+            Some(&sender.pub_key),
+            &sender.pub_key,
+            &DecryptLimits::default(),
+        )
+        .expect("decryption should be ok");
+
+        assert_eq!(writer.position(), ptx_len, "returned/observed plaintext length mismatch");
+        assert_eq!(plaintext, writer.into_inner(), "incorrect plaintext");
+    }
+
+    #[test]
+    fn metadata() {
+        let mut rng = ChaChaRng::seed_from_u64(0xDEADBEEF);
+        let sender = SecKey::random(&mut rng);
+        let plaintext = vec![9u8; 1000];
+        let metadata =
+            FileMetadata { name: "notes.txt".into(), size: 1000, mode: 0o600, mtime: 42 };
+
+        let mut ciphertext = Vec::new();
+        encrypt_slice(
+            &mut rng,
+            &plaintext,
+            &mut ciphertext,
+            &sender,
+            &[Some(sender.pub_key.clone())],
+            &EncryptOptions {
+                block_len: MIN_BLOCK_LEN,
+                metadata: Some(metadata.clone()),
+                ..Default::default()
+            },
+        )
+        .expect("encryption should be ok");
+
+        let mut writer = Cursor::new(Vec::new());
+        let (ptx_len, decrypted) = decrypt(
+            Cursor::new(ciphertext),
+            &mut writer,
+            &sender.dec_key,
+            Some(&sender.pub_key),
+            &sender.pub_key,
+            &DecryptLimits::default(),
+        )
+        .expect("decryption should be ok");
+
+        assert_eq!(writer.position(), ptx_len, "returned/observed plaintext length mismatch");
+        assert_eq!(plaintext, writer.into_inner(), "incorrect plaintext");
+        assert_eq!(Some(metadata), decrypted, "incorrect metadata");
+    }
+
+    #[test]
+    fn invalid_metadata() {
+        let mut rng = ChaChaRng::seed_from_u64(0xDEADBEEF);
+        let sender = SecKey::random(&mut rng);
+
+        for metadata in [
+            FileMetadata { name: "../passwd".into(), ..Default::default() },
+            FileMetadata { name: "a".repeat(MIN_BLOCK_LEN), ..Default::default() },
+        ] {
+            let mut ciphertext = Vec::new();
+            assert_matches!(
+                encrypt_slice(
+                    &mut rng,
+                    b"hello",
+                    &mut ciphertext,
+                    &sender,
+                    &[Some(sender.pub_key.clone())],
+                    &EncryptOptions {
+                        block_len: MIN_BLOCK_LEN,
+                        metadata: Some(metadata),
+                        ..Default::default()
+                    },
+                ),
+                Err(EncryptError::InvalidMetadata)
+            );
+            assert!(ciphertext.is_empty(), "nothing should be written");
+        }
+    }
+
+    #[test]
+    fn invalid_block_len() {
+        let mut rng = ChaChaRng::seed_from_u64(0xDEADBEEF);
+        let sender = SecKey::random(&mut rng);
+        let receivers = [Some(sender.pub_key.clone())];
+
+        for block_len in [0, MIN_BLOCK_LEN - 1, MAX_BLOCK_LEN + 1] {
+            let options = EncryptOptions { block_len, ..Default::default() };
+
+            let mut ciphertext = Vec::new();
+            assert_matches!(
+                encrypt_slice(&mut rng, b"hello", &mut ciphertext, &sender, &receivers, &options),
+                Err(EncryptError::InvalidBlockLength)
+            );
+            assert_matches!(
+                encrypt(&mut rng, &b"hello"[..], &mut ciphertext, &sender, &receivers, &options),
+                Err(EncryptError::InvalidBlockLength)
+            );
+            assert!(ciphertext.is_empty(), "nothing should be written");
+        }
+    }
+
     #[test]
     fn split_sig() {
         let (_, sender, receiver, plaintext, ciphertext) = setup(32 * 1024 - 37);
 
         let mut writer = Cursor::new(Vec::new());
-        let ptx_len = decrypt(Cursor::new(ciphertext), &mut writer, &receiver, &sender.pub_key)
-            .expect("decryption should be ok");
+        let (ptx_len, _) = decrypt(
+            Cursor::new(ciphertext),
+            &mut writer,
+            &receiver.dec_key,
+            Some(&receiver.pub_key),
+            &sender.pub_key,
+            &DecryptLimits::default(),
+        )
+        .expect("decryption should be ok");
 
         assert_eq!(writer.position(), ptx_len, "returned/observed plaintext length mismatch");
         assert_eq!(plaintext.to_vec(), writer.into_inner(), "incorrect plaintext");
     }
 
+    #[test]
+    fn many_receivers() {
+        let mut rng = ChaChaRng::seed_from_u64(0xDEADBEEF);
+        let sender = SecKey::random(&mut rng);
+        let receivers =
+            (0..PARALLEL_RECEIVERS * 2).map(|_| SecKey::random(&mut rng)).collect::<Vec<_>>();
+        let pks = receivers
+            .iter()
+            .map(|r| Some(r.pub_key.clone()))
+            .chain([None, None])
+            .collect::<Vec<_>>();
+
+        let encrypt_with_seed = |seed| {
+            let mut ciphertext = Vec::new();
+            encrypt(
+                ChaChaRng::seed_from_u64(seed),
+                Cursor::new(b"hello"),
+                &mut ciphertext,
+                &sender,
+                &pks,
+                &EncryptOptions::default(),
+            )
+            .expect("encryption should be ok");
+            ciphertext
+        };
+        let ciphertext = encrypt_with_seed(0xC0FFEE);
+        assert_eq!(ciphertext, encrypt_with_seed(0xC0FFEE), "encryption should be deterministic");
+
+        for receiver in [&receivers[0], &receivers[PARALLEL_RECEIVERS + 3]] {
+            let mut writer = Cursor::new(Vec::new());
+            decrypt(
+                Cursor::new(&ciphertext),
+                &mut writer,
+                &receiver.dec_key,
+                Some(&receiver.pub_key),
+                &sender.pub_key,
+                &DecryptLimits::default(),
+            )
+            .expect("decryption should be ok");
+            assert_eq!(b"hello".to_vec(), writer.into_inner(), "incorrect plaintext");
+        }
+    }
+
+    #[test]
+    fn trailing_data() {
+        let (_, sender, receiver, _, mut ciphertext) = setup(64);
+        ciphertext.push(0);
+
+        assert_matches!(
+            decrypt(
+                Cursor::new(ciphertext),
+                Cursor::new(Vec::new()),
+                &receiver.dec_key,
+                Some(&receiver.pub_key),
+                &sender.pub_key,
+                &DecryptLimits::default(),
+            ),
+            Err(DecryptError::InvalidCiphertext)
+        );
+    }
+
+    #[test]
+    fn within_limits() {
+        let (_, sender, receiver, plaintext, ciphertext) = setup(64);
+
+        let limits = DecryptLimits {
+            max_headers: Some(3),
+            max_ciphertext_len: Some(ciphertext.len() as u64),
+            max_block_len: Some(64),
+            max_plaintext_len: Some(64),
+        };
+        let mut writer = Cursor::new(Vec::new());
+        decrypt(
+            Cursor::new(ciphertext),
+            &mut writer,
+            &receiver.dec_key,
+            Some(&receiver.pub_key),
+            &sender.pub_key,
+            &limits,
+        )
+        .expect("decryption should be ok");
+
+        assert_eq!(plaintext.to_vec(), writer.into_inner(), "incorrect plaintext");
+    }
+
+    #[test]
+    fn max_headers() {
+        let (_, sender, receiver, _, ciphertext) = setup(64);
+
+        // The receiver's header is the second of three.
+        for max_headers in [1, 2] {
+            assert_matches!(
+                decrypt(
+                    Cursor::new(&ciphertext),
+                    Cursor::new(Vec::new()),
+                    &receiver.dec_key,
+                    Some(&receiver.pub_key),
+                    &sender.pub_key,
+                    &DecryptLimits { max_headers: Some(max_headers), ..Default::default() },
+                ),
+                Err(DecryptError::LimitExceeded)
+            );
+        }
+    }
+
+    #[test]
+    fn max_ciphertext_len() {
+        let (_, sender, receiver, _, ciphertext) = setup(64);
+
+        // Garbage input stops being processed once the limit is reached.
+        assert_matches!(
+            decrypt(
+                Cursor::new(vec![0u8; 100 * ENC_HEADER_LEN]),
+                Cursor::new(Vec::new()),
+                &receiver.dec_key,
+                Some(&receiver.pub_key),
+                &sender.pub_key,
+                &DecryptLimits {
+                    max_ciphertext_len: Some(10 * ENC_HEADER_LEN as u64),
+                    ..Default::default()
+                },
+            ),
+            Err(DecryptError::LimitExceeded)
+        );
+
+        assert_matches!(
+            decrypt(
+                Cursor::new(&ciphertext),
+                Cursor::new(Vec::new()),
+                &receiver.dec_key,
+                Some(&receiver.pub_key),
+                &sender.pub_key,
+                &DecryptLimits {
+                    max_ciphertext_len: Some(ciphertext.len() as u64 - 1),
+                    ..Default::default()
+                },
+            ),
+            Err(DecryptError::LimitExceeded)
+        );
+    }
+
+    #[test]
+    fn max_block_len() {
+        let (_, sender, receiver, _, ciphertext) = setup(64);
+
+        assert_matches!(
+            decrypt(
+                Cursor::new(ciphertext),
+                Cursor::new(Vec::new()),
+                &receiver.dec_key,
+                Some(&receiver.pub_key),
+                &sender.pub_key,
+                &DecryptLimits { max_block_len: Some(63), ..Default::default() },
+            ),
+            Err(DecryptError::LimitExceeded)
+        );
+    }
+
+    #[test]
+    fn max_plaintext_len() {
+        let (_, sender, receiver, _, ciphertext) = setup(64);
+
+        assert_matches!(
+            decrypt(
+                Cursor::new(ciphertext),
+                Cursor::new(Vec::new()),
+                &receiver.dec_key,
+                Some(&receiver.pub_key),
+                &sender.pub_key,
+                &DecryptLimits { max_plaintext_len: Some(63), ..Default::default() },
+            ),
+            Err(DecryptError::LimitExceeded)
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "compression")]
+    fn decompression_bomb() {
+        let mut rng = ChaChaRng::seed_from_u64(0xDEADBEEF);
+        let sender = SecKey::random(&mut rng);
+        let plaintext = vec![0u8; 16 * 1024 * 1024];
+
+        let mut ciphertext = Vec::new();
+        encrypt_slice(
+            &mut rng,
+            &plaintext,
+            &mut ciphertext,
+            &sender,
+            &[Some(sender.pub_key.clone())],
+            &EncryptOptions { compress: true, ..Default::default() },
+        )
+        .expect("encryption should be ok");
+
+        // The ciphertext is well within the ciphertext length limit, but the plaintext isn't.
+        let mut writer = Cursor::new(Vec::new());
+        assert_matches!(
+            decrypt(
+                Cursor::new(&ciphertext),
+                &mut writer,
+                &sender.dec_key,
+                Some(&sender.pub_key),
+                &sender.pub_key,
+                &DecryptLimits {
+                    max_ciphertext_len: Some(ciphertext.len() as u64),
+                    max_plaintext_len: Some(1024 * 1024),
+                    ..Default::default()
+                },
+            ),
+            Err(DecryptError::LimitExceeded)
+        );
+        assert!(writer.position() <= 1024 * 1024, "should stop writing at the limit");
+    }
+
+    #[test]
+    fn encrypt_slice_matches_reader() {
+        let mut rng = ChaChaRng::seed_from_u64(0xDEADBEEF);
+        let sender = SecKey::random(&mut rng);
+        let receivers = [Some(sender.pub_key.clone()), None];
+
+        for n in [0, 64, DEFAULT_BLOCK_LEN, DEFAULT_BLOCK_LEN * 2 + 5] {
+            let mut plaintext = vec![0u8; n];
+            rng.fill_bytes(&mut plaintext);
+
+            let mut from_reader = Vec::new();
+            encrypt(
+                ChaChaRng::seed_from_u64(n as u64),
+                Cursor::new(&plaintext),
+                &mut from_reader,
+                &sender,
+                &receivers,
+                &EncryptOptions::default(),
+            )
+            .expect("encryption should be ok");
+
+            let mut from_slice = Vec::new();
+            let ctx_len = encrypt_slice(
+                ChaChaRng::seed_from_u64(n as u64),
+                &plaintext,
+                &mut from_slice,
+                &sender,
+                &receivers,
+                &EncryptOptions::default(),
+            )
+            .expect("encryption should be ok");
+
+            assert_eq!(from_slice.len() as u64, ctx_len, "returned/observed length mismatch");
+            assert_eq!(from_reader, from_slice, "inconsistent ciphertext for {n} bytes");
+        }
+    }
+
+    /// A structure-aware fuzzed message: a well-formed sequence of blocks, which is then mutated.
+    #[derive(Debug, bolero::TypeGenerator)]
+    struct FuzzMessage {
+        block_len: u16,
+        metadata: Option<String>,
+        data_len: u16,
+        padding_len: u16,
+        #[generator(bolero::gen::<Vec<FuzzMutation>>().with().len(0usize..4))]
+        mutations: Vec<FuzzMutation>,
+        tail: FuzzTail,
+    }
+
+    /// A mutation of a structure-aware fuzzed message's blocks. Indexes wrap around.
+    #[derive(Debug, bolero::TypeGenerator)]
+    enum FuzzMutation {
+        Remove { index: u8 },
+        Duplicate { index: u8 },
+        Swap { a: u8, b: u8 },
+        SetType { index: u8, block_type: u8 },
+        Resize { index: u8, len: u16 },
+    }
+
+    /// The bytes following the blocks of a structure-aware fuzzed message.
+    #[derive(Debug, bolero::TypeGenerator)]
+    enum FuzzTail {
+        Signature,
+        Truncated { len: u16 },
+        Trailing { extra: Vec<u8> },
+    }
+
+    impl FuzzMessage {
+        /// Returns the block length and the blocks' types and plaintexts.
+        fn blocks(&self) -> (usize, Vec<(u8, Vec<u8>)>) {
+            let block_len = MIN_BLOCK_LEN + usize::from(self.block_len) % 1024;
+
+            // Encode a well-formed message, like encrypt_message.
+            let mut blocks = Vec::new();
+            if let Some(name) = &self.metadata {
+                let metadata = FileMetadata { name: name.clone(), size: 1, mode: 0o600, mtime: 0 };
+                blocks.push((BlockType::Metadata as u8, metadata.encode()));
+            }
+            let data = vec![0xDA; usize::from(self.data_len) % (4 * block_len)];
+            blocks.extend(data.chunks(block_len).map(|b| (BlockType::Data as u8, b.to_vec())));
+            let padding = vec![0xEE; usize::from(self.padding_len) % (4 * block_len)];
+            blocks
+                .extend(padding.chunks(block_len).map(|b| (BlockType::Padding as u8, b.to_vec())));
+            if padding.len().is_multiple_of(block_len) {
+                blocks.push((BlockType::Padding as u8, Vec::new()));
+            }
+
+            // Apply the mutations.
+            for mutation in &self.mutations {
+                let n = blocks.len();
+                let i = |index: u8| usize::from(index) % n;
+                match *mutation {
+                    FuzzMutation::Remove { index } if blocks.len() > 1 => {
+                        blocks.remove(i(index));
+                    }
+                    FuzzMutation::Remove { .. } => {}
+                    FuzzMutation::Duplicate { index } => {
+                        let block = blocks[i(index)].clone();
+                        blocks.insert(i(index), block);
+                    }
+                    FuzzMutation::Swap { a, b } => blocks.swap(i(a), i(b)),
+                    FuzzMutation::SetType { index, block_type } => blocks[i(index)].0 = block_type,
+                    FuzzMutation::Resize { index, len } => {
+                        blocks[i(index)].1.resize(usize::from(len) % (block_len + 2), 0x0F);
+                    }
+                }
+            }
+
+            (block_len, blocks)
+        }
+    }
+
+    impl FuzzTail {
+        fn encode(&self) -> Vec<u8> {
+            match self {
+                FuzzTail::Signature => vec![0x51; SIG_LEN],
+                FuzzTail::Truncated { len } => vec![0x51; usize::from(*len) % SIG_LEN],
+                FuzzTail::Trailing { extra } => {
+                    let mut tail = vec![0x51; SIG_LEN + 1];
+                    tail.extend_from_slice(extra);
+                    tail
+                }
+            }
+        }
+    }
+
+    /// Fuzzes the block parsing of `decrypt_message` with mutated messages sealed by a DEK-keyed
+    /// protocol, skipping the header trial decryption which random ciphertexts almost never get
+    /// past, and checks the results against a model of the block framing rules.
+    #[test]
+    fn fuzz_decrypt_message() {
+        bolero::check!().with_type::<FuzzMessage>().for_each(|input| {
+            let (block_len, blocks) = input.blocks();
+            let tail = input.tail.encode();
+
+            // Seal the blocks with a DEK-keyed protocol, followed by the tail bytes.
+            let mut keyed = Protocol::new("veil.message.fuzz");
+            keyed.mix("dek", &[0xDE; DEK_LEN]);
+            let mut message = keyed.clone();
+            let mut ciphertext = Vec::new();
+            for (block_type, plaintext) in &blocks {
+                let mut block_header = [0u8; ENC_BLOCK_HEADER_LEN];
+                block_header[0] = *block_type;
+                block_header[1..4].copy_from_slice(&(plaintext.len() as u32).to_le_bytes()[..3]);
+                message.seal("block-header", &mut block_header);
+                ciphertext.extend_from_slice(&block_header);
+
+                let mut block = plaintext.clone();
+                block.resize(plaintext.len() + TAG_LEN, 0);
+                message.seal("block", &mut block);
+                ciphertext.extend_from_slice(&block);
+            }
+            ciphertext.extend_from_slice(&tail);
+
+            let mut plaintext = Vec::new();
+            let result = decrypt_message(
+                &mut keyed,
+                Cursor::new(&ciphertext),
+                |block| {
+                    plaintext.extend_from_slice(block);
+                    Ok(())
+                },
+                FORMAT_VERSION,
+                block_len,
+                None,
+                &mut ReadBudget(None),
+            );
+
+            match (result, model(&blocks, &tail, block_len)) {
+                (Ok((sig, metadata)), Some((expected, expected_metadata))) => {
+                    assert_eq!(expected, plaintext, "incorrect plaintext");
+                    assert_eq!(expected_metadata, metadata, "incorrect metadata");
+                    assert_eq!(
+                        &ciphertext[ciphertext.len() - SIG_LEN..],
+                        &sig,
+                        "incorrect signature"
+                    );
+                }
+                (Err(_), None) => {}
+                (result, expected) => panic!("expected {expected:?}, got {result:?}"),
+            }
+        });
+
+        /// Returns the plaintext and metadata of a sequence of blocks followed by `tail`, or `None`
+        /// if the message is invalid.
+        #[allow(clippy::type_complexity)]
+        fn model(
+            blocks: &[(u8, Vec<u8>)],
+            tail: &[u8],
+            block_len: usize,
+        ) -> Option<(Vec<u8>, Option<FileMetadata>)> {
+            let mut plaintext = Vec::new();
+            let mut metadata = None;
+            let mut data_done = false;
+            for (i, (block_type, block)) in blocks.iter().enumerate() {
+                if block.len() > block_len {
+                    return None;
+                }
+                match BlockType::try_from(*block_type).ok()? {
+                    BlockType::Metadata if i == 0 => metadata = Some(FileMetadata::decode(block)?),
+                    BlockType::Metadata => return None,
+                    BlockType::Data if data_done => return None,
+                    BlockType::Data => {
+                        data_done = block.len() < block_len;
+                        plaintext.extend_from_slice(block);
+                    }
+                    BlockType::Padding if block.len() == block_len => data_done = true,
+                    BlockType::Padding => {
+                        // The final padding block must be followed by exactly a signature's worth
+                        // of bytes.
+                        let rest = blocks[i + 1..]
+                            .iter()
+                            .map(|(_, b)| ENC_BLOCK_HEADER_LEN + b.len() + TAG_LEN)
+                            .sum::<usize>();
+                        return (rest + tail.len() == SIG_LEN).then_some((plaintext, metadata));
+                    }
+                }
+            }
+            None
+        }
+    }
+
+    #[test]
+    fn unversioned() {
+        let mut rng = ChaChaRng::seed_from_u64(0xDEADBEEF);
+        let sender = SecKey::random(&mut rng);
+        let receiver = SecKey::random(&mut rng);
+        let mut plaintext = vec![0u8; UNVERSIONED_BLOCK_LEN + 1000];
+        rng.fill_bytes(&mut plaintext);
+
+        // Unversioned messages end with a single padding block, which may be longer than a block.
+        let (full, rest) = plaintext.split_at(UNVERSIONED_BLOCK_LEN);
+        let padding = vec![0u8; UNVERSIONED_BLOCK_LEN + 1];
+        let blocks =
+            [(BlockType::Data, full), (BlockType::Data, rest), (BlockType::Padding, &padding[..])];
+        let ciphertext = encrypt_unversioned(&mut rng, &sender, &receiver.pub_key, &blocks);
+
+        let mut writer = Vec::new();
+        let (ptx_len, metadata) = decrypt(
+            Cursor::new(&ciphertext),
+            &mut writer,
+            &receiver.dec_key,
+            Some(&receiver.pub_key),
+            &sender.pub_key,
+            &DecryptLimits::default(),
+        )
+        .expect("decryption should be ok");
+        assert_eq!(plaintext.len() as u64, ptx_len, "returned/observed plaintext length mismatch");
+        assert_eq!(plaintext, writer, "incorrect plaintext");
+        assert_eq!(None, metadata);
+
+        // Unversioned headers commit to the receiver's entire public key.
+        assert_matches!(
+            decrypt(
+                Cursor::new(&ciphertext),
+                io::sink(),
+                &receiver.dec_key,
+                None,
+                &sender.pub_key,
+                &DecryptLimits::default(),
+            ),
+            Err(DecryptError::InvalidCiphertext)
+        );
+    }
+
+    #[test]
+    fn unversioned_metadata_block() {
+        let mut rng = ChaChaRng::seed_from_u64(0xDEADBEEF);
+        let sender = SecKey::random(&mut rng);
+        let receiver = SecKey::random(&mut rng);
+
+        let metadata = FileMetadata { name: "a".into(), size: 1, mode: 0o644, mtime: 0 }.encode();
+        let blocks: [(BlockType, &[u8]); 3] =
+            [(BlockType::Metadata, &metadata), (BlockType::Data, b"a"), (BlockType::Padding, b"")];
+        let ciphertext = encrypt_unversioned(&mut rng, &sender, &receiver.pub_key, &blocks);
+
+        assert_matches!(
+            decrypt(
+                Cursor::new(&ciphertext),
+                io::sink(),
+                &receiver.dec_key,
+                Some(&receiver.pub_key),
+                &sender.pub_key,
+                &DecryptLimits::default(),
+            ),
+            Err(DecryptError::InvalidBlockType(0x02))
+        );
+    }
+
+    /// Encrypts the given blocks in the unversioned format for a fake receiver and the given
+    /// receiver.
+    fn encrypt_unversioned(
+        mut rng: impl Rng + CryptoRng,
+        sender: &SecKey,
+        receiver: &PubKey,
+        blocks: &[(BlockType, &[u8])],
+    ) -> Vec<u8> {
+        let mut message = init(UNVERSIONED, &sender.pub_key);
+        let dek = rng.gen::<[u8; DEK_LEN]>();
+
+        // Write a fake header, then the receiver's header, which has the DEK and receiver count.
+        let mut ciphertext = vec![0u8; ENC_UNVERSIONED_HEADER_LEN * 2];
+        let (fake, enc_header) = ciphertext.split_at_mut(ENC_UNVERSIONED_HEADER_LEN);
+        rng.fill_bytes(fake);
+        message.mix("header", fake);
+
+        let mut header = message.clone();
+        header.mix("receiver", &receiver.encoded);
+        let (kem_ect, kem_ss) = kemeleon::encapsulate(&receiver.ek, &mut rng);
+        let (out_kem, out_header) = enc_header.split_at_mut(ENC_CT_LEN);
+        out_kem.copy_from_slice(&kem_ect);
+        header.mix("ml-kem-768-ect", &kem_ect);
+        header.mix("ml-kem-768-ss", &kem_ss);
+        out_header[..DEK_LEN].copy_from_slice(&dek);
+        out_header[DEK_LEN..UNVERSIONED_HEADER_LEN].copy_from_slice(&2u64.to_le_bytes());
+        header.seal("header", out_header);
+        message.mix("header", enc_header);
+        message.mix("dek", &dek);
+
+        // Seal the blocks and sign the message.
+        for (block_type, plaintext) in blocks {
+            let mut block_header = [0u8; ENC_BLOCK_HEADER_LEN];
+            block_header[0] = *block_type as u8;
+            block_header[1..4].copy_from_slice(&(plaintext.len() as u32).to_le_bytes()[..3]);
+            message.seal("block-header", &mut block_header);
+            ciphertext.extend_from_slice(&block_header);
+
+            let mut block = plaintext.to_vec();
+            block.resize(plaintext.len() + TAG_LEN, 0);
+            message.seal("block", &mut block);
+            ciphertext.extend_from_slice(&block);
+        }
+        ciphertext.extend_from_slice(&sig::sign_protocol(&mut rng, &mut message, &sender.sign_key));
+        ciphertext
+    }
+
     fn setup(n: usize) -> (ChaChaRng, SecKey, SecKey, Vec<u8>, Vec<u8>) {
+        setup_with_block_len(n, DEFAULT_BLOCK_LEN)
+    }
+
+    fn setup_with_block_len(
+        n: usize,
+        block_len: usize,
+    ) -> (ChaChaRng, SecKey, SecKey, Vec<u8>, Vec<u8>) {
         let mut rng = ChaChaRng::seed_from_u64(0xDEADBEEF);
         let sender = SecKey::random(&mut rng);
         let receiver = SecKey::random(&mut rng);
@@ -492,6 +1805,7 @@ mod tests {
             Cursor::new(&mut ciphertext),
             &sender,
             &[Some(sender.pub_key.clone()), Some(receiver.pub_key.clone()), None],
+            &EncryptOptions { block_len, ..Default::default() },
         )
         .expect("encryption should be ok");
 
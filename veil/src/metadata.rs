@@ -0,0 +1,113 @@
+//! Encrypted file metadata.
+
+/// The length of the fixed-size fields of encoded file metadata.
+const FIXED_LEN: usize = size_of::<u64>() + size_of::<u32>() + size_of::<i64>();
+
+/// Metadata about a file, which can be encrypted along with its contents.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FileMetadata {
+    /// The file's name, without any directory components.
+    pub name: String,
+
+    /// The file's size, in bytes.
+    pub size: u64,
+
+    /// The file's Unix permission bits.
+    pub mode: u32,
+
+    /// The file's modification time, in seconds since the Unix epoch.
+    pub mtime: i64,
+}
+
+impl FileMetadata {
+    /// Returns `true` if the file name is a single, non-empty path component.
+    #[must_use]
+    pub fn has_valid_name(&self) -> bool {
+        is_valid_name(&self.name)
+    }
+
+    /// Returns `true` if the file name is a relative path of one or more `/`-separated components,
+    /// each of which is a valid name.
+    pub(crate) fn has_valid_path(&self) -> bool {
+        self.name.split('/').all(is_valid_name)
+    }
+
+    /// Encodes the metadata as `LE_64(size) ǁ LE_32(mode) ǁ LE_64(mtime) ǁ name`.
+    pub(crate) fn encode(&self) -> Vec<u8> {
+        let mut b = Vec::with_capacity(FIXED_LEN + self.name.len());
+        b.extend_from_slice(&self.size.to_le_bytes());
+        b.extend_from_slice(&self.mode.to_le_bytes());
+        b.extend_from_slice(&self.mtime.to_le_bytes());
+        b.extend_from_slice(self.name.as_bytes());
+        b
+    }
+
+    /// Decodes metadata, returning `None` if it's malformed or has an invalid name.
+    pub(crate) fn decode(b: &[u8]) -> Option<FileMetadata> {
+        FileMetadata::decode_unchecked(b).filter(FileMetadata::has_valid_name)
+    }
+
+    /// Decodes metadata without checking its name, returning `None` if it's malformed.
+    pub(crate) fn decode_unchecked(b: &[u8]) -> Option<FileMetadata> {
+        if b.len() < FIXED_LEN {
+            return None;
+        }
+        let (size, b) = b.split_at(size_of::<u64>());
+        let (mode, b) = b.split_at(size_of::<u32>());
+        let (mtime, name) = b.split_at(size_of::<i64>());
+
+        Some(FileMetadata {
+            name: String::from_utf8(name.to_vec()).ok()?,
+            size: u64::from_le_bytes(size.try_into().expect("should be 8 bytes")),
+            mode: u32::from_le_bytes(mode.try_into().expect("should be 4 bytes")),
+            mtime: i64::from_le_bytes(mtime.try_into().expect("should be 8 bytes")),
+        })
+    }
+}
+
+/// Returns `true` if the name is a single, non-empty path component other than `.` or `..`.
+pub(crate) fn is_valid_name(name: &str) -> bool {
+    !name.is_empty() && name != "." && name != ".." && !name.contains(['/', '\\', '\0'])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let metadata =
+            FileMetadata { name: "report.pdf".into(), size: 1234, mode: 0o640, mtime: -5 };
+        assert_eq!(Some(metadata.clone()), FileMetadata::decode(&metadata.encode()));
+    }
+
+    #[test]
+    fn invalid_names() {
+        for name in ["", ".", "..", "../etc/passwd", "a/b", "a\\b", "a\0b"] {
+            let metadata = FileMetadata { name: name.into(), ..Default::default() };
+            assert!(!metadata.has_valid_name(), "accepted {name:?}");
+            assert_eq!(None, FileMetadata::decode(&metadata.encode()), "decoded {name:?}");
+        }
+    }
+
+    #[test]
+    fn paths() {
+        for (name, valid) in [
+            ("a", true),
+            ("a/b/c.txt", true),
+            ("/etc/passwd", false),
+            ("a//b", false),
+            ("a/../../b", false),
+            ("a/./b", false),
+            ("a/", false),
+        ] {
+            let metadata = FileMetadata { name: name.into(), ..Default::default() };
+            assert_eq!(valid, metadata.has_valid_path(), "{name:?}");
+        }
+    }
+
+    #[test]
+    fn short() {
+        assert_eq!(None, FileMetadata::decode(&[0u8; FIXED_LEN - 1]));
+    }
+}
@@ -0,0 +1,138 @@
+//! Checksummed mnemonic encoding of secret key seeds.
+
+use std::sync::LazyLock;
+
+use lockstitch::Protocol;
+use zeroize::Zeroize;
+
+use crate::{keys::SK_LEN, ParseMnemonicError};
+
+/// The BIP-39 English word list, which is sorted and has 2,048 words.
+static WORDS: LazyLock<Vec<&'static str>> =
+    LazyLock::new(|| include_str!("english.txt").lines().collect());
+
+/// The number of bits encoded by each word.
+const BITS_PER_WORD: usize = 11;
+
+/// The length of the checksum, in bytes.
+const CHECKSUM_LEN: usize = 8;
+
+/// The number of words in a mnemonic. The seed and checksum are exactly 192 words long.
+pub const MNEMONIC_LEN: usize = (SK_LEN + CHECKSUM_LEN) * 8 / BITS_PER_WORD;
+
+/// Encodes the given seed as a sequence of words separated by spaces.
+pub fn encode(seed: &[u8; SK_LEN]) -> String {
+    let mut data = [0u8; SK_LEN + CHECKSUM_LEN];
+    data[..SK_LEN].copy_from_slice(seed);
+    data[SK_LEN..].copy_from_slice(&checksum(seed));
+
+    let words = (0..MNEMONIC_LEN).map(|i| WORDS[read_bits(&data, i)]).collect::<Vec<_>>().join(" ");
+    data.zeroize();
+    words
+}
+
+/// Decodes the given sequence of words as a seed, if possible.
+pub fn decode(s: &str) -> Result<[u8; SK_LEN], ParseMnemonicError> {
+    let words = s.split_whitespace().collect::<Vec<_>>();
+    if words.len() != MNEMONIC_LEN {
+        return Err(ParseMnemonicError::InvalidLength);
+    }
+
+    // Decode each word as an 11-bit value.
+    let mut data = [0u8; SK_LEN + CHECKSUM_LEN];
+    for (i, word) in words.iter().enumerate() {
+        let idx = WORDS
+            .binary_search(&word.to_ascii_lowercase().as_str())
+            .map_err(|_| ParseMnemonicError::InvalidWord(i))?;
+        write_bits(&mut data, i, idx);
+    }
+
+    // Split the data into seed and checksum and check the checksum.
+    let mut seed = [0u8; SK_LEN];
+    seed.copy_from_slice(&data[..SK_LEN]);
+    let valid = lockstitch::ct_eq(&checksum(&seed), &data[SK_LEN..]);
+    data.zeroize();
+    if !valid {
+        seed.zeroize();
+        return Err(ParseMnemonicError::InvalidChecksum);
+    }
+
+    Ok(seed)
+}
+
+fn checksum(seed: &[u8; SK_LEN]) -> [u8; CHECKSUM_LEN] {
+    let mut mnemonic = Protocol::new("veil.mnemonic");
+    mnemonic.mix("seed", seed);
+    mnemonic.derive_array("checksum")
+}
+
+/// Reads the `i`th big-endian 11-bit value from the given buffer.
+fn read_bits(data: &[u8], i: usize) -> usize {
+    (0..BITS_PER_WORD).fold(0, |acc, j| {
+        let bit = i * BITS_PER_WORD + j;
+        (acc << 1) | usize::from((data[bit / 8] >> (7 - bit % 8)) & 1)
+    })
+}
+
+/// Writes the `i`th big-endian 11-bit value to the given buffer.
+fn write_bits(data: &mut [u8], i: usize, value: usize) {
+    for j in 0..BITS_PER_WORD {
+        let bit = i * BITS_PER_WORD + j;
+        let b = ((value >> (BITS_PER_WORD - 1 - j)) & 1) as u8;
+        data[bit / 8] |= b << (7 - bit % 8);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_matches::assert_matches;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaChaRng;
+
+    use super::*;
+
+    #[test]
+    fn word_list() {
+        assert_eq!(2048, WORDS.len(), "invalid word list length");
+        assert!(WORDS.is_sorted(), "word list should be sorted");
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut rng = ChaChaRng::seed_from_u64(0xDEADBEEF);
+        let seed = rng.gen::<[u8; SK_LEN]>();
+
+        let mnemonic = encode(&seed);
+        assert_eq!(MNEMONIC_LEN, mnemonic.split(' ').count(), "invalid mnemonic length");
+        assert_eq!(Ok(seed), decode(&mnemonic), "invalid decoded seed");
+        assert_eq!(
+            Ok(seed),
+            decode(&mnemonic.to_uppercase().replace(' ', "\n")),
+            "invalid decoded seed"
+        );
+    }
+
+    #[test]
+    fn invalid_length() {
+        let mut rng = ChaChaRng::seed_from_u64(0xDEADBEEF);
+        let mnemonic = encode(&rng.gen());
+        let short = mnemonic.rsplit_once(' ').expect("should have words").0;
+        assert_matches!(decode(short), Err(ParseMnemonicError::InvalidLength));
+    }
+
+    #[test]
+    fn invalid_word() {
+        let mut rng = ChaChaRng::seed_from_u64(0xDEADBEEF);
+        let mnemonic = encode(&rng.gen());
+        let mnemonic = format!("veil {}", mnemonic.split_once(' ').expect("should have words").1);
+        assert_matches!(decode(&mnemonic), Err(ParseMnemonicError::InvalidWord(0)));
+    }
+
+    #[test]
+    fn invalid_checksum() {
+        let mut rng = ChaChaRng::seed_from_u64(0xDEADBEEF);
+        let mut words = encode(&rng.gen()).split(' ').map(String::from).collect::<Vec<_>>();
+        words.swap(3, 4);
+        assert_matches!(decode(&words.join(" ")), Err(ParseMnemonicError::InvalidChecksum));
+    }
+}
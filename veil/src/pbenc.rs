@@ -1,45 +1,135 @@
-//! Passphrase-based encryption based on Balloon Hashing.
+//! Passphrase-based encryption based on Balloon Hashing or Argon2id.
 
 use std::thread;
 
+use argon2::{Algorithm, Argon2, Block, Params, Version};
 use lockstitch::{Protocol, TAG_LEN};
 use rand::{CryptoRng, Rng};
+use zeroize::Zeroize;
+
+use crate::version::{self, FORMAT_VERSION, SUPPORTED_VERSIONS, UNVERSIONED};
 
 /// The number of bytes encryption adds to a plaintext.
-pub const OVERHEAD: usize = (size_of::<u8>() * 3) + SALT_LEN + TAG_LEN;
+pub const OVERHEAD: usize = (size_of::<u8>() * 4) + SALT_LEN + TAG_LEN;
+
+/// The number of bytes encryption added to a plaintext in the unversioned format, which had no KDF
+/// identifier and always used balloon hashing.
+pub const LEGACY_OVERHEAD: usize = (size_of::<u8>() * 3) + SALT_LEN + TAG_LEN;
+
+/// A passphrase-based key derivation function and its parameters.
+///
+/// All costs are expressed as base-2 logarithms, so `memory_cost: 10` uses 1 MiB of memory.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Kdf {
+    /// Balloon hashing over Lockstitch.
+    Balloon {
+        /// The time cost, in 2^t iterations.
+        time_cost: u8,
+        /// The memory cost, in 2^m KiB.
+        memory_cost: u8,
+        /// The number of parallel sub-keys, in 2^p threads.
+        parallelism: u8,
+    },
+
+    /// Argon2id, as specified in RFC 9106.
+    Argon2id {
+        /// The time cost, in 2^t passes.
+        time_cost: u8,
+        /// The memory cost, in 2^m KiB.
+        memory_cost: u8,
+        /// The number of lanes, in 2^p lanes.
+        parallelism: u8,
+    },
+}
+
+impl Kdf {
+    /// The maximum time cost.
+    pub const MAX_TIME_COST: u8 = 24;
+
+    /// The maximum total memory cost. For balloon hashing, which uses 2^m KiB per thread, this
+    /// bounds the sum of the memory cost and the parallelism.
+    pub const MAX_MEMORY_COST: u8 = 24;
+
+    /// The maximum parallelism.
+    pub const MAX_PARALLELISM: u8 = 8;
+
+    /// Returns `true` if the KDF's costs are within [`Kdf::MAX_TIME_COST`],
+    /// [`Kdf::MAX_MEMORY_COST`], and [`Kdf::MAX_PARALLELISM`].
+    #[must_use]
+    pub const fn is_valid(&self) -> bool {
+        let (time_cost, memory_cost, parallelism) = self.costs();
+        let total_memory_cost = match self {
+            Kdf::Balloon { .. } => memory_cost.saturating_add(parallelism),
+            Kdf::Argon2id { .. } => memory_cost,
+        };
+        time_cost <= Self::MAX_TIME_COST
+            && parallelism <= Self::MAX_PARALLELISM
+            && total_memory_cost <= Self::MAX_MEMORY_COST
+    }
+
+    const fn id(&self) -> u8 {
+        match self {
+            Kdf::Balloon { .. } => BALLOON,
+            Kdf::Argon2id { .. } => ARGON2ID,
+        }
+    }
+
+    const fn costs(&self) -> (u8, u8, u8) {
+        match *self {
+            Kdf::Balloon { time_cost, memory_cost, parallelism }
+            | Kdf::Argon2id { time_cost, memory_cost, parallelism } => {
+                (time_cost, memory_cost, parallelism)
+            }
+        }
+    }
+
+    const fn from_parts(id: u8, time_cost: u8, memory_cost: u8, parallelism: u8) -> Option<Kdf> {
+        match id {
+            BALLOON => Some(Kdf::Balloon { time_cost, memory_cost, parallelism }),
+            ARGON2ID => Some(Kdf::Argon2id { time_cost, memory_cost, parallelism }),
+            _ => None,
+        }
+    }
+}
 
 /// Encrypt the given plaintext using the given passphrase.
+///
+/// Returns `None` if the KDF's costs are out of bounds or its memory cannot be allocated.
+#[must_use]
 pub fn encrypt(
     mut rng: impl Rng + CryptoRng,
     passphrase: &[u8],
-    time_cost: u8,
-    memory_cost: u8,
-    parallelism: u8,
+    kdf: Kdf,
     plaintext: &[u8],
     ciphertext: &mut [u8],
-) {
+) -> Option<()> {
     debug_assert_eq!(ciphertext.len(), plaintext.len() + OVERHEAD);
 
+    if !kdf.is_valid() {
+        return None;
+    }
+
     // Split up the output buffer.
-    let (t, m) = ciphertext.split_at_mut(size_of::<u8>());
+    let (k, t) = ciphertext.split_at_mut(size_of::<u8>());
+    let (t, m) = t.split_at_mut(size_of::<u8>());
     let (m, p) = m.split_at_mut(size_of::<u8>());
     let (p, salt) = p.split_at_mut(size_of::<u8>());
     let (salt, ciphertext) = salt.split_at_mut(SALT_LEN);
 
-    // Encode the parameters.
-    t[0] = time_cost;
-    m[0] = memory_cost;
-    p[0] = parallelism;
+    // Encode the KDF and its parameters.
+    k[0] = kdf.id();
+    (t[0], m[0], p[0]) = kdf.costs();
 
     // Generate a random salt.
     rng.fill_bytes(salt);
 
-    // Perform the balloon hashing.
-    let mut pbenc = init(passphrase, salt, time_cost, memory_cost, parallelism);
+    // Perform the key derivation.
+    let mut pbenc = init(passphrase, salt, kdf, &[FORMAT_VERSION])?.pop()?;
 
     // Encrypt the plaintext.
     ciphertext[..plaintext.len()].copy_from_slice(plaintext);
     pbenc.seal("secret", ciphertext);
+    Some(())
 }
 
 /// Decrypt the given ciphertext using the given passphrase.
@@ -49,42 +139,163 @@ pub fn decrypt<'a>(passphrase: &[u8], in_out: &'a mut [u8]) -> Option<&'a [u8]>
         return None;
     }
 
+    // Split up the input buffer.
+    let (k, t) = in_out.split_at_mut(size_of::<u8>());
+    let (t, m) = t.split_at_mut(size_of::<u8>());
+    let (m, p) = m.split_at_mut(size_of::<u8>());
+    let (p, salt) = p.split_at_mut(size_of::<u8>());
+    let (salt, ciphertext) = salt.split_at_mut(SALT_LEN);
+
+    // Decode the KDF and perform the key derivation once for all supported versions. Unversioned
+    // ciphertexts have a different layout and are decrypted by `decrypt_legacy` instead.
+    let kdf = Kdf::from_parts(k[0], t[0], m[0], p[0]).filter(Kdf::is_valid)?;
+    let versions = SUPPORTED_VERSIONS.into_iter().filter(|&v| v != UNVERSIONED).collect::<Vec<_>>();
+    let protocols = init(passphrase, salt, kdf, &versions)?;
+
+    // Try to decrypt a copy of the ciphertext with each version.
+    let mut buf = ciphertext.to_vec();
+    for mut pbenc in protocols {
+        buf.copy_from_slice(ciphertext);
+        if let Some(n) = pbenc.open("secret", &mut buf).map(<[u8]>::len) {
+            ciphertext[..n].copy_from_slice(&buf[..n]);
+            buf.zeroize();
+            return Some(&ciphertext[..n]);
+        }
+    }
+    None
+}
+
+/// Decrypt the given ciphertext, which is in the unversioned format, using the given passphrase.
+#[must_use]
+pub fn decrypt_legacy<'a>(passphrase: &[u8], in_out: &'a mut [u8]) -> Option<&'a [u8]> {
+    if in_out.len() < LEGACY_OVERHEAD {
+        return None;
+    }
+
     // Split up the input buffer.
     let (t, m) = in_out.split_at_mut(size_of::<u8>());
     let (m, p) = m.split_at_mut(size_of::<u8>());
     let (p, salt) = p.split_at_mut(size_of::<u8>());
     let (salt, ciphertext) = salt.split_at_mut(SALT_LEN);
 
-    // Perform the balloon hashing.
-    let mut pbenc = init(passphrase, salt, t[0], m[0], p[0]);
+    // Decode the balloon hashing parameters and perform the key derivation.
+    let kdf = Kdf::Balloon { time_cost: t[0], memory_cost: m[0], parallelism: p[0] };
+    if !kdf.is_valid() {
+        return None;
+    }
+    let keys = expand_keys(passphrase, salt, t[0], m[0], p[0])?;
+    let mut pbenc = balloon_protocol(&version::domain("veil.pbenc", UNVERSIONED), &keys);
 
     // Decrypt the ciphertext.
     pbenc.open("secret", ciphertext)
 }
 
-fn init(
+/// Derives a key from the passphrase and salt with the given KDF, then returns a protocol keyed
+/// with it for each of the given format versions.
+fn init(passphrase: &[u8], salt: &[u8], kdf: Kdf, versions: &[u8]) -> Option<Vec<Protocol>> {
+    match kdf {
+        Kdf::Balloon { time_cost, memory_cost, parallelism } => {
+            let keys = expand_keys(passphrase, salt, time_cost, memory_cost, parallelism)?;
+            Some(
+                versions
+                    .iter()
+                    .map(|&version| {
+                        balloon_protocol(&version::domain("veil.pbenc", version), &keys)
+                    })
+                    .collect(),
+            )
+        }
+        Kdf::Argon2id { time_cost, memory_cost, parallelism } => {
+            init_argon2id(passphrase, salt, time_cost, memory_cost, parallelism, versions)
+        }
+    }
+}
+
+fn init_argon2id(
     passphrase: &[u8],
     salt: &[u8],
     time_cost: u8,
     memory_cost: u8,
     parallelism: u8,
-) -> Protocol {
+    versions: &[u8],
+) -> Option<Vec<Protocol>> {
+    // Convert the logarithmic costs to Argon2id parameters. Argon2id requires at least 8 KiB of
+    // memory per lane, so the memory cost is raised to that minimum if necessary.
+    let t_cost = 1u32.checked_shl(time_cost.into())?;
+    let p_cost = 1u32.checked_shl(parallelism.into())?;
+    let m_cost = 1u32.checked_shl(memory_cost.into())?.max(p_cost.checked_mul(8)?);
+    let params = Params::new(m_cost, t_cost, p_cost, Some(ARGON2ID_KEY_LEN)).ok()?;
+
+    // Derive a key from the passphrase and salt.
+    let mut key = [0u8; ARGON2ID_KEY_LEN];
+    argon2id(params, &[], passphrase, salt, &mut key)?;
+
+    // Mix the derived key into a protocol for each version.
+    let protocols = versions
+        .iter()
+        .map(|&version| {
+            let mut pbenc = Protocol::new(&version::domain("veil.pbenc.argon2id", version));
+            pbenc.mix("key", &key);
+            pbenc
+        })
+        .collect();
+    key.zeroize();
+    Some(protocols)
+}
+
+/// Hashes the passphrase and salt into the output with Argon2id, returning `None` if its memory
+/// cannot be allocated.
+fn argon2id(
+    params: Params,
+    secret: &[u8],
+    passphrase: &[u8],
+    salt: &[u8],
+    out: &mut [u8],
+) -> Option<()> {
+    let mut blocks = Vec::new();
+    blocks.try_reserve_exact(params.block_count()).ok()?;
+    blocks.resize(params.block_count(), Block::default());
+    Argon2::new_with_secret(secret, Algorithm::Argon2id, Version::V0x13, params)
+        .ok()?
+        .hash_password_into_with_memory(passphrase, salt, out, &mut blocks)
+        .ok()
+}
+
+/// Expands a sub-key in parallel for each of the 2^p threads.
+fn expand_keys(
+    passphrase: &[u8],
+    salt: &[u8],
+    time_cost: u8,
+    memory_cost: u8,
+    parallelism: u8,
+) -> Option<Vec<[u8; N]>> {
     thread::scope(|s| {
         // Spawn threads to expand each sub-key.
-        let handles = (1..=(1 << parallelism))
+        let handles = (1..=(1u64 << parallelism))
             .map(|p| s.spawn(move || expand_key(passphrase, salt, time_cost, memory_cost, p)))
             .collect::<Vec<_>>();
 
-        // Mix the expanded sub-keys into the protocol in the order in which they were spawned.
-        let mut pbenc = Protocol::new("veil.pbenc");
-        for key in handles {
-            pbenc.mix("expanded-key", &key.join().expect("should expand sub-key"));
-        }
-        pbenc
+        // Collect the expanded sub-keys in the order in which they were spawned.
+        handles.into_iter().map(|key| key.join().expect("should expand sub-key")).collect()
     })
 }
 
-fn expand_key(passphrase: &[u8], salt: &[u8], time_cost: u8, memory_cost: u8, p: u8) -> [u8; N] {
+/// Mixes the expanded sub-keys into a new protocol with the given domain.
+fn balloon_protocol(domain: &str, keys: &[[u8; N]]) -> Protocol {
+    let mut pbenc = Protocol::new(domain);
+    for key in keys {
+        pbenc.mix("expanded-key", key);
+    }
+    pbenc
+}
+
+fn expand_key(
+    passphrase: &[u8],
+    salt: &[u8],
+    time_cost: u8,
+    memory_cost: u8,
+    p: u64,
+) -> Option<[u8; N]> {
     // A macro for the common hash operations. This is a macro rather than a function so it can
     // accept both immutable references to blocks in the buffer as well as a mutable reference to a
     // block in the same buffer for output. Accepts a template protocol, a counter variable, an
@@ -113,10 +324,13 @@ fn expand_key(passphrase: &[u8], salt: &[u8], time_cost: u8, memory_cost: u8, p:
 
     // Allocate buffer, initialize counter and default protocol state.
     let mut ctr = 0u64;
-    let mut buf = vec![[0u8; N]; 1usize << memory_cost];
+    let mut buf = Vec::new();
+    let blocks = 1usize.checked_shl(memory_cost.into())?;
+    buf.try_reserve_exact(blocks).ok()?;
+    buf.resize(blocks, [0u8; N]);
     let buf_len = u64::try_from(buf.len()).expect("usize should be <= u64");
     let mut h = Protocol::new("veil.pbenc.iter");
-    h.mix_int("thread", p as u64);
+    h.mix_int("thread", p);
 
     // Step 1: Expand input into buffer.
     hash!(h, ctr, &mut buf[0], passphrase, salt);
@@ -156,23 +370,41 @@ fn expand_key(passphrase: &[u8], salt: &[u8], time_cost: u8, memory_cost: u8, p:
     }
 
     // Step 3: Extract key from buffer.
-    buf[buf.len() - 1]
+    Some(buf[buf.len() - 1])
 }
 
 const SALT_LEN: usize = 16;
+const ARGON2ID_KEY_LEN: usize = 32;
+const BALLOON: u8 = 0x00;
+const ARGON2ID: u8 = 0x01;
 const DELTA: u64 = 3;
 const N: usize = 1024;
 
 #[cfg(test)]
 mod tests {
-    use rand::SeedableRng;
+    use argon2::{AssociatedData, ParamsBuilder};
+    use expect_test::expect;
+    use rand::{rngs::OsRng, SeedableRng};
     use rand_chacha::ChaChaRng;
 
     use super::*;
 
+    const BALLOON_KDF: Kdf = Kdf::Balloon { time_cost: 1, memory_cost: 6, parallelism: 4 };
+    const ARGON2ID_KDF: Kdf = Kdf::Argon2id { time_cost: 1, memory_cost: 6, parallelism: 2 };
+
     #[test]
     fn round_trip() {
-        let (_, passphrase, plaintext, mut ciphertext) = setup();
+        let (_, passphrase, plaintext, mut ciphertext) = setup(BALLOON_KDF);
+        assert_eq!(
+            Some(plaintext.as_slice()),
+            decrypt(&passphrase, &mut ciphertext),
+            "invalid plaintext"
+        );
+    }
+
+    #[test]
+    fn argon2id_round_trip() {
+        let (_, passphrase, plaintext, mut ciphertext) = setup(ARGON2ID_KDF);
         assert_eq!(
             Some(plaintext.as_slice()),
             decrypt(&passphrase, &mut ciphertext),
@@ -182,7 +414,7 @@ mod tests {
 
     #[test]
     fn wrong_passphrase() {
-        let (mut rng, _, _, mut ciphertext) = setup();
+        let (mut rng, _, _, mut ciphertext) = setup(BALLOON_KDF);
         let wrong_passphrase = rng.gen::<[u8; 32]>();
         assert_eq!(
             None,
@@ -192,47 +424,169 @@ mod tests {
     }
 
     #[test]
-    fn modified_time_cost() {
-        let (_, passphrase, _, mut ciphertext) = setup();
+    fn argon2id_wrong_passphrase() {
+        let (mut rng, _, _, mut ciphertext) = setup(ARGON2ID_KDF);
+        let wrong_passphrase = rng.gen::<[u8; 32]>();
+        assert_eq!(
+            None,
+            decrypt(&wrong_passphrase, &mut ciphertext),
+            "decrypted an invalid ciphertext"
+        );
+    }
+
+    #[test]
+    fn modified_kdf() {
+        let (_, passphrase, _, mut ciphertext) = setup(BALLOON_KDF);
         ciphertext[0] ^= 1;
         assert_eq!(None, decrypt(&passphrase, &mut ciphertext), "decrypted an invalid ciphertext");
     }
 
     #[test]
-    fn modified_memory_cost() {
-        let (_, passphrase, _, mut ciphertext) = setup();
+    fn unknown_kdf() {
+        let (_, passphrase, _, mut ciphertext) = setup(BALLOON_KDF);
+        ciphertext[0] = 0xFF;
+        assert_eq!(None, decrypt(&passphrase, &mut ciphertext), "decrypted an invalid ciphertext");
+    }
+
+    #[test]
+    fn modified_time_cost() {
+        let (_, passphrase, _, mut ciphertext) = setup(BALLOON_KDF);
         ciphertext[1] ^= 1;
         assert_eq!(None, decrypt(&passphrase, &mut ciphertext), "decrypted an invalid ciphertext");
     }
 
+    #[test]
+    fn modified_memory_cost() {
+        let (_, passphrase, _, mut ciphertext) = setup(BALLOON_KDF);
+        ciphertext[2] ^= 1;
+        assert_eq!(None, decrypt(&passphrase, &mut ciphertext), "decrypted an invalid ciphertext");
+    }
+
     #[test]
     fn modified_salt() {
-        let (_, passphrase, _, mut ciphertext) = setup();
+        let (_, passphrase, _, mut ciphertext) = setup(BALLOON_KDF);
         ciphertext[9] ^= 1;
         assert_eq!(None, decrypt(&passphrase, &mut ciphertext), "decrypted an invalid ciphertext");
     }
 
     #[test]
     fn modified_ciphertext() {
-        let (_, passphrase, _, mut ciphertext) = setup();
+        let (_, passphrase, _, mut ciphertext) = setup(BALLOON_KDF);
         ciphertext[OVERHEAD - TAG_LEN + 1] ^= 1;
         assert_eq!(None, decrypt(&passphrase, &mut ciphertext), "decrypted an invalid ciphertext");
     }
 
     #[test]
     fn modified_tag() {
-        let (_, passphrase, plaintext, mut ciphertext) = setup();
+        let (_, passphrase, plaintext, mut ciphertext) = setup(BALLOON_KDF);
         ciphertext[plaintext.len() + OVERHEAD - 1] ^= 1;
         assert_eq!(None, decrypt(&passphrase, &mut ciphertext), "decrypted an invalid ciphertext");
     }
 
-    fn setup() -> (ChaChaRng, [u8; 32], [u8; 64], Vec<u8>) {
+    #[test]
+    fn balloon_kat() {
+        let (_, _, _, ciphertext) = setup(BALLOON_KDF);
+        let expected = expect!["1XKNnmADiW496on9QyVsHbtDhmqZnU8PFoMKarWEL9mRLpuMsfdCUFq3DpHaRFSgPaUXmWfAd5BtsmakdTdbKerz2whrgtbYNmPFnqpNWZ8jFVvQqppGGK2x2BwgE5S8qNUiKkP"];
+        expected.assert_eq(&bs58::encode(ciphertext).into_string());
+    }
+
+    #[test]
+    fn argon2id_kat() {
+        let (_, _, _, ciphertext) = setup(ARGON2ID_KDF);
+        let expected = expect!["3GGBbMWvF8h57NeCaDA4MYH2k2M8eZshe7hJa4z9oRpbczZ7tPNQMeJkWo89AWyV4A1dYC4m3CWsLDdj7kK8D9aQksBuy1LDAS7JGg23Jvrnr9HruRdiSbMqUzz6yeYty34hTPtD"];
+        expected.assert_eq(&bs58::encode(ciphertext).into_string());
+    }
+
+    #[test]
+    fn argon2id_rfc9106() {
+        // RFC 9106, Section 5.3.
+        let params = ParamsBuilder::new()
+            .m_cost(32)
+            .t_cost(3)
+            .p_cost(4)
+            .data(AssociatedData::new(&[0x04; 12]).expect("should be valid associated data"))
+            .output_len(32)
+            .build()
+            .expect("should be valid parameters");
+        let mut tag = [0u8; 32];
+        argon2id(params, &[0x03; 8], &[0x01; 32], &[0x02; 16], &mut tag)
+            .expect("should hash the password");
+        assert_eq!(
+            "0d640df58d78766c08c037a34a8b53c9d01ef0452d75b65eb52520e96b01e659",
+            hex::encode(tag),
+        );
+    }
+
+    #[test]
+    fn out_of_range_costs() {
+        // Balloon hashing uses 2^m KiB per thread, so its memory cost includes the parallelism.
+        assert!(!Kdf::Balloon { time_cost: 0, memory_cost: 20, parallelism: 5 }.is_valid());
+        assert!(Kdf::Argon2id { time_cost: 0, memory_cost: 20, parallelism: 5 }.is_valid());
+
+        for (t, m, p) in [(25, 0, 0), (0, 25, 0), (0, 0, 9), (0xFF, 0xFF, 0xFF)] {
+            let kdf = Kdf::Balloon { time_cost: t, memory_cost: m, parallelism: p };
+            assert!(!kdf.is_valid(), "{kdf:?} should be invalid");
+
+            let mut ciphertext = vec![0u8; OVERHEAD];
+            assert_eq!(None, encrypt(OsRng, b"passphrase", kdf, &[], &mut ciphertext));
+
+            for id in [BALLOON, ARGON2ID] {
+                let mut ciphertext = vec![0u8; OVERHEAD];
+                (ciphertext[0], ciphertext[1], ciphertext[2], ciphertext[3]) = (id, t, m, p);
+                assert_eq!(None, decrypt(b"passphrase", &mut ciphertext));
+            }
+
+            let mut ciphertext = vec![0u8; LEGACY_OVERHEAD];
+            (ciphertext[0], ciphertext[1], ciphertext[2]) = (t, m, p);
+            assert_eq!(None, decrypt_legacy(b"passphrase", &mut ciphertext));
+        }
+    }
+
+    #[test]
+    fn fuzz_decrypt() {
+        bolero::check!().with_type::<(Vec<u8>, Vec<u8>)>().for_each(|(passphrase, ciphertext)| {
+            // Mostly pick known KDFs and clamp their costs to keep each iteration cheap.
+            let mut ciphertext = ciphertext.clone();
+            if let Some(k) = ciphertext.first_mut() {
+                *k %= 3;
+            }
+            for cost in ciphertext.iter_mut().skip(1).take(3) {
+                *cost %= 2;
+            }
+            let _ = decrypt(passphrase, &mut ciphertext);
+        });
+    }
+
+    #[test]
+    fn fuzz_round_trip() {
+        bolero::check!().with_type::<(u64, Vec<u8>, Vec<u8>, bool)>().for_each(
+            |(seed, passphrase, plaintext, argon2id)| {
+                let kdf = if *argon2id {
+                    Kdf::Argon2id { time_cost: 0, memory_cost: 0, parallelism: 0 }
+                } else {
+                    Kdf::Balloon { time_cost: 0, memory_cost: 0, parallelism: 0 }
+                };
+                let mut ciphertext = vec![0u8; plaintext.len() + OVERHEAD];
+                encrypt(
+                    ChaChaRng::seed_from_u64(*seed),
+                    passphrase,
+                    kdf,
+                    plaintext,
+                    &mut ciphertext,
+                )
+                .expect("should encrypt");
+                assert_eq!(Some(plaintext.as_slice()), decrypt(passphrase, &mut ciphertext));
+            },
+        );
+    }
+
+    fn setup(kdf: Kdf) -> (ChaChaRng, [u8; 32], [u8; 64], Vec<u8>) {
         let mut rng = ChaChaRng::seed_from_u64(0xDEADBEEF);
         let passphrase = rng.gen::<[u8; 32]>();
         let plaintext = rng.gen::<[u8; 64]>();
 
         let mut ciphertext = vec![0u8; plaintext.len() + OVERHEAD];
-        encrypt(&mut rng, &passphrase, 1, 6, 4, &plaintext, &mut ciphertext);
+        encrypt(&mut rng, &passphrase, kdf, &plaintext, &mut ciphertext).expect("should encrypt");
 
         (rng, passphrase, plaintext, ciphertext)
     }
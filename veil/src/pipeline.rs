@@ -0,0 +1,204 @@
+//! Pipelined I/O, which overlaps reading and writing with cryptographic operations.
+
+use std::{
+    io::{self, Read, Write},
+    mem,
+    sync::mpsc::{self, Receiver, SyncSender},
+    thread,
+};
+
+/// The size of the chunks passed between threads.
+const CHUNK_LEN: usize = 64 * 1024;
+
+/// The number of chunks which can be buffered between threads.
+const DEPTH: usize = 4;
+
+/// Runs `f` with a reader and writer which are backed by separate threads reading from `reader`
+/// and writing to `writer` via bounded channels.
+///
+/// Returns the result of `f` and the result of writing to `writer`. If writing failed, the writer
+/// passed to `f` will have returned [`io::ErrorKind::BrokenPipe`] errors, so the write result
+/// should take precedence.
+///
+/// The reader thread is never joined, so if `f` returns before `reader` is exhausted (e.g. with an
+/// error), this returns without waiting for a blocked read to finish. The reader thread exits after
+/// its current read.
+pub fn pipeline<T>(
+    mut reader: impl Read + Send + 'static,
+    mut writer: impl Write + Send,
+    f: impl FnOnce(ChannelReader, ChannelWriter) -> T,
+) -> (T, io::Result<()>) {
+    let (read_tx, read_rx) = mpsc::sync_channel::<io::Result<Vec<u8>>>(DEPTH);
+    let (write_tx, write_rx) = mpsc::sync_channel::<Vec<u8>>(DEPTH);
+
+    // Read chunks from the reader until it's exhausted, it fails, or the receiver is dropped.
+    thread::spawn(move || loop {
+        let mut chunk = Vec::with_capacity(CHUNK_LEN);
+        match (&mut reader).take(CHUNK_LEN as u64).read_to_end(&mut chunk) {
+            Ok(0) => break,
+            Ok(_) => {
+                if read_tx.send(Ok(chunk)).is_err() {
+                    break;
+                }
+            }
+            Err(e) => {
+                let _ = read_tx.send(Err(e));
+                break;
+            }
+        }
+    });
+
+    thread::scope(|s| {
+        // Write chunks to the writer until the sender is dropped or writing fails.
+        let write = s.spawn(move || {
+            for chunk in write_rx {
+                writer.write_all(&chunk)?;
+            }
+            writer.flush()
+        });
+
+        let out = f(
+            ChannelReader { rx: read_rx, chunk: Vec::new(), pos: 0 },
+            ChannelWriter { tx: write_tx, buf: Vec::with_capacity(CHUNK_LEN) },
+        );
+        (out, write.join().expect("writer thread should not panic"))
+    })
+}
+
+/// A reader which receives chunks from a reader thread.
+#[derive(Debug)]
+pub struct ChannelReader {
+    rx: Receiver<io::Result<Vec<u8>>>,
+    chunk: Vec<u8>,
+    pos: usize,
+}
+
+impl Read for ChannelReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pos == self.chunk.len() {
+            match self.rx.recv() {
+                Ok(chunk) => {
+                    self.chunk = chunk?;
+                    self.pos = 0;
+                }
+                // The reader thread has exited, so the reader is exhausted.
+                Err(_) => return Ok(0),
+            }
+        }
+
+        let n = buf.len().min(self.chunk.len() - self.pos);
+        buf[..n].copy_from_slice(&self.chunk[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+/// A writer which sends chunks to a writer thread.
+///
+/// Any buffered data is sent when the writer is flushed or dropped.
+#[derive(Debug)]
+pub struct ChannelWriter {
+    tx: SyncSender<Vec<u8>>,
+    buf: Vec<u8>,
+}
+
+impl ChannelWriter {
+    fn send(&mut self) -> io::Result<()> {
+        if self.buf.is_empty() {
+            return Ok(());
+        }
+        let chunk = mem::replace(&mut self.buf, Vec::with_capacity(CHUNK_LEN));
+        self.tx.send(chunk).map_err(|_| io::ErrorKind::BrokenPipe.into())
+    }
+}
+
+impl Write for ChannelWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = buf.len().min(CHUNK_LEN - self.buf.len());
+        self.buf.extend_from_slice(&buf[..n]);
+        if self.buf.len() == CHUNK_LEN {
+            self.send()?;
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.send()
+    }
+}
+
+impl Drop for ChannelWriter {
+    fn drop(&mut self) {
+        // If the writer thread has failed, its error will be returned instead.
+        let _ = self.send();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use rand::{RngCore, SeedableRng};
+    use rand_chacha::ChaChaRng;
+
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let mut rng = ChaChaRng::seed_from_u64(0xDEADBEEF);
+        let mut input = vec![0u8; CHUNK_LEN * 5 + 37];
+        rng.fill_bytes(&mut input);
+
+        let mut output = Vec::new();
+        let (copied, written) =
+            pipeline(Cursor::new(input.clone()), &mut output, |mut r, mut w| {
+                io::copy(&mut r, &mut w)
+            });
+
+        assert_eq!(input.len() as u64, copied.expect("copy should be ok"));
+        written.expect("write should be ok");
+        assert_eq!(input, output, "invalid output");
+    }
+
+    #[test]
+    fn write_error() {
+        struct Failing;
+
+        impl Write for Failing {
+            fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+                Err(io::Error::other("nope"))
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let input = vec![0u8; CHUNK_LEN * 10];
+        let (_, written) =
+            pipeline(Cursor::new(input), Failing, |mut r, mut w| io::copy(&mut r, &mut w));
+
+        assert_eq!("nope", written.expect_err("write should fail").to_string());
+    }
+
+    #[test]
+    fn early_return() {
+        /// A reader which blocks until its sender is dropped, like stdin with no input.
+        struct Blocking(Receiver<()>);
+
+        impl Read for Blocking {
+            fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+                let _ = self.0.recv();
+                Ok(0)
+            }
+        }
+
+        let (tx, rx) = mpsc::channel();
+        let (res, written) =
+            pipeline(Blocking(rx), io::sink(), |_, _| Err::<(), _>(io::Error::other("early")));
+        drop(tx);
+
+        assert_eq!("early", res.expect_err("should return early").to_string());
+        written.expect("write should be ok");
+    }
+}
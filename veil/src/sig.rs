@@ -2,7 +2,7 @@
 
 use std::{
     fmt,
-    io::{self, Read},
+    io::{self, Read, Write},
     str::FromStr,
 };
 
@@ -14,8 +14,9 @@ use lockstitch::Protocol;
 use rand::{CryptoRng, Rng};
 
 use crate::{
-    keys::{PubKey, SecKey},
-    ParseSignatureError, VerifyError, DIGEST_LEN,
+    keys::{PubKey, SignKey, VerKey},
+    version::{self, FORMAT_VERSION, SUPPORTED_VERSIONS, UNVERSIONED},
+    Digest, ParseSignatureError, VerifyError, DIGEST_LEN,
 };
 
 /// The length of a signature, in bytes.
@@ -57,14 +58,11 @@ impl fmt::Display for Signature {
 /// Create an encrypted ML-DSA-65 signature of the given message using the given key pair.
 pub fn sign(
     rng: impl Rng + CryptoRng,
-    signer: &SecKey,
+    signer: &SignKey,
     mut message: impl Read,
 ) -> io::Result<Signature> {
-    // Initialize a protocol.
-    let mut sig = Protocol::new("veil.sig");
-
-    // Mix the signer's public key into the protocol.
-    sig.mix("signer", &signer.pub_key.encoded);
+    // Initialize a protocol with the signer's verifying key.
+    let sig = init(MESSAGE_DOMAIN, FORMAT_VERSION, &signer.ver_key);
 
     // Mix the message into the protocol.
     let mut writer = sig.mix_writer("message", io::sink());
@@ -75,25 +73,139 @@ pub fn sign(
     Ok(Signature(sign_protocol(rng, &mut sig, signer)))
 }
 
-/// Verify a ML-DSA-65 signature of the given message using the given public key.
+/// Create an encrypted ML-DSA-65 signature of the given message slice using the given key pair.
+///
+/// The signature is identical to one created by [`sign`] for the same message.
+pub fn sign_slice(rng: impl Rng + CryptoRng, signer: &SignKey, message: &[u8]) -> Signature {
+    // Initialize a protocol with the signer's verifying key.
+    let mut sig = init(MESSAGE_DOMAIN, FORMAT_VERSION, &signer.ver_key);
+
+    // Mix the message into the protocol.
+    sig.mix("message", message);
+
+    // Create a ML-DSA-65 signature of the protocol state.
+    Signature(sign_protocol(rng, &mut sig, signer))
+}
+
+/// Verify a ML-DSA-65 signature of the given message using the given verifying key.
+///
+/// Unversioned signatures commit to the signer's entire public key, so they can only be verified if
+/// it is given.
 pub fn verify(
-    signer: &PubKey,
+    signer: &VerKey,
+    pub_key: Option<&PubKey>,
     mut message: impl Read,
     signature: &Signature,
 ) -> Result<(), VerifyError> {
-    // Initialize a protocol.
-    let mut sig = Protocol::new("veil.sig");
+    // Initialize a protocol for each supported version and mix the message into all of them.
+    let mut writers = candidates(MESSAGE_DOMAIN, signer, pub_key)
+        .into_iter()
+        .map(|sig| sig.mix_writer("message", io::sink()))
+        .collect::<Vec<_>>();
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let n = match message.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into()),
+        };
+        for writer in &mut writers {
+            writer.write_all(&buf[..n])?;
+        }
+    }
 
-    // Mix the signer's public key into the protocol.
-    sig.mix("signer", &signer.encoded);
+    // Verify the signature with any of the supported versions.
+    writers
+        .into_iter()
+        .map(|writer| writer.into_inner().0)
+        .find_map(|mut sig| verify_protocol(&mut sig, signer, signature.0))
+        .ok_or(VerifyError::InvalidSignature)
+}
 
-    // Mix the message into the protocol.
-    let mut writer = sig.mix_writer("message", io::sink());
-    io::copy(&mut message, &mut writer)?;
-    let (mut sig, _) = writer.into_inner();
+/// Verify a ML-DSA-65 signature of the given message slice using the given verifying key.
+///
+/// Unversioned signatures commit to the signer's entire public key, so they can only be verified if
+/// it is given.
+pub fn verify_slice(
+    signer: &VerKey,
+    pub_key: Option<&PubKey>,
+    message: &[u8],
+    signature: &Signature,
+) -> Result<(), VerifyError> {
+    // Verify the signature with any of the supported versions.
+    candidates(MESSAGE_DOMAIN, signer, pub_key)
+        .into_iter()
+        .find_map(|mut sig| {
+            sig.mix("message", message);
+            verify_protocol(&mut sig, signer, signature.0)
+        })
+        .ok_or(VerifyError::InvalidSignature)
+}
+
+/// Create an encrypted ML-DSA-65 signature of the given pre-computed digest using the given key
+/// pair.
+///
+/// Digest signatures use a separate domain from message signatures, so a signature of a digest is
+/// never valid as a signature of a message, or vice versa.
+pub fn sign_digest(rng: impl Rng + CryptoRng, signer: &SignKey, digest: &Digest) -> Signature {
+    // Initialize a protocol with the signer's verifying key.
+    let mut sig = init(DIGEST_DOMAIN, FORMAT_VERSION, &signer.ver_key);
 
-    // Verify the signature.
-    verify_protocol(&mut sig, signer, signature.0).ok_or(VerifyError::InvalidSignature)
+    // Mix the digest into the protocol.
+    sig.mix("digest", &digest.encode());
+
+    // Create a ML-DSA-65 signature of the protocol state.
+    Signature(sign_protocol(rng, &mut sig, signer))
+}
+
+/// Verify a ML-DSA-65 signature of the given pre-computed digest using the given verifying key.
+pub fn verify_digest(
+    signer: &VerKey,
+    digest: &Digest,
+    signature: &Signature,
+) -> Result<(), VerifyError> {
+    // Verify the signature with any of the supported versions. Digest signatures were introduced
+    // after format versions, so there are no unversioned ones.
+    candidates(DIGEST_DOMAIN, signer, None)
+        .into_iter()
+        .find_map(|mut sig| {
+            sig.mix("digest", &digest.encode());
+            verify_protocol(&mut sig, signer, signature.0)
+        })
+        .ok_or(VerifyError::InvalidSignature)
+}
+
+/// The domain of signatures of messages.
+const MESSAGE_DOMAIN: &str = "veil.sig";
+
+/// The domain of signatures of pre-computed digests.
+const DIGEST_DOMAIN: &str = "veil.sig.digest";
+
+/// Initializes a signature protocol in the given domain for the given format version and mixes the
+/// signer's verifying key into it.
+fn init(domain: &str, version: u8, signer: &VerKey) -> Protocol {
+    let mut sig = Protocol::new(&version::domain(domain, version));
+    sig.mix("signer", &signer.encoded);
+    sig
+}
+
+/// Initializes a signature protocol in the given domain for each supported format version. The
+/// unversioned protocol mixes in the signer's entire public key instead of its verifying key, so it
+/// is omitted if the public key isn't given.
+fn candidates(domain: &str, signer: &VerKey, pub_key: Option<&PubKey>) -> Vec<Protocol> {
+    SUPPORTED_VERSIONS
+        .into_iter()
+        .filter_map(|version| {
+            if version == UNVERSIONED {
+                let mut sig = Protocol::new(&version::domain(domain, version));
+                sig.mix("signer", &pub_key?.encoded);
+                Some(sig)
+            } else {
+                Some(init(domain, version, signer))
+            }
+        })
+        .collect()
 }
 
 /// Create an encrypted ML-DSA-65 signature of the given protocol's state using the given signing
@@ -101,7 +213,7 @@ pub fn verify(
 pub fn sign_protocol(
     mut rng: impl Rng + CryptoRng,
     protocol: &mut Protocol,
-    signer: &SecKey,
+    signer: &SignKey,
 ) -> [u8; SIG_LEN] {
     // Derive a 256-bit digest.
     let h = protocol.derive_array::<DIGEST_LEN>("digest");
@@ -116,11 +228,11 @@ pub fn sign_protocol(
     sig
 }
 
-/// Verify a ML-DSA-65 signature of the given protocol's state using the given public key.
+/// Verify a ML-DSA-65 signature of the given protocol's state using the given verifying key.
 #[must_use]
 pub fn verify_protocol(
     protocol: &mut Protocol,
-    signer: &PubKey,
+    signer: &VerKey,
     mut sig: [u8; SIG_LEN],
 ) -> Option<()> {
     // Derive a counterfactual digest from the protocol state.
@@ -143,12 +255,13 @@ mod tests {
     use rand_chacha::ChaChaRng;
 
     use super::*;
+    use crate::keys::SecKey;
 
     #[test]
     fn sign_and_verify() {
         let (_, signer, message, sig) = setup();
         assert_matches!(
-            verify(&signer.pub_key, Cursor::new(message), &sig),
+            verify(&signer.ver_key, None, Cursor::new(message), &sig),
             Ok(()),
             "should have verified a valid signature"
         );
@@ -159,7 +272,7 @@ mod tests {
         let (mut rng, signer, _, sig) = setup();
         let wrong_message = rng.gen::<[u8; 64]>();
         assert_matches!(
-            verify(&signer.pub_key, Cursor::new(wrong_message), &sig),
+            verify(&signer.ver_key, None, Cursor::new(wrong_message), &sig),
             Err(VerifyError::InvalidSignature)
         );
     }
@@ -167,9 +280,9 @@ mod tests {
     #[test]
     fn wrong_signer() {
         let (mut rng, _, message, sig) = setup();
-        let wrong_signer = SecKey::random(&mut rng);
+        let wrong_signer = SignKey::random(&mut rng);
         assert_matches!(
-            verify(&wrong_signer.pub_key, Cursor::new(message), &sig),
+            verify(&wrong_signer.ver_key, None, Cursor::new(message), &sig),
             Err(VerifyError::InvalidSignature)
         );
     }
@@ -179,7 +292,49 @@ mod tests {
         let (_, signer, message, mut sig) = setup();
         sig.0[22] ^= 1;
         assert_matches!(
-            verify(&signer.pub_key, Cursor::new(message), &sig),
+            verify(&signer.ver_key, None, Cursor::new(message), &sig),
+            Err(VerifyError::InvalidSignature)
+        );
+    }
+
+    #[test]
+    fn slices() {
+        let (mut rng, signer, message, sig) = setup();
+        assert_matches!(verify_slice(&signer.ver_key, None, &message, &sig), Ok(()));
+
+        let sig = sign_slice(&mut rng, &signer, &message);
+        assert_matches!(verify(&signer.ver_key, None, Cursor::new(&message), &sig), Ok(()));
+        assert_matches!(
+            verify_slice(&signer.ver_key, None, &message[1..], &sig),
+            Err(VerifyError::InvalidSignature)
+        );
+    }
+
+    #[test]
+    fn digests() {
+        let (mut rng, signer, message, sig) = setup();
+        let digest = Digest::from_slice(&["metadata"], &message);
+        let digest_sig = sign_digest(&mut rng, &signer, &digest);
+        assert_matches!(verify_digest(&signer.ver_key, &digest, &digest_sig), Ok(()));
+
+        let wrong_digest = Digest::from_slice(&["metadata"], &message[1..]);
+        assert_matches!(
+            verify_digest(&signer.ver_key, &wrong_digest, &digest_sig),
+            Err(VerifyError::InvalidSignature)
+        );
+
+        // Digest signatures and message signatures must not be interchangeable.
+        assert_matches!(
+            verify_slice(&signer.ver_key, None, &digest.encode(), &digest_sig),
+            Err(VerifyError::InvalidSignature)
+        );
+        let message_sig = sign_slice(&mut rng, &signer, &digest.encode());
+        assert_matches!(
+            verify_digest(&signer.ver_key, &digest, &message_sig),
+            Err(VerifyError::InvalidSignature)
+        );
+        assert_matches!(
+            verify_digest(&signer.ver_key, &digest, &sig),
             Err(VerifyError::InvalidSignature)
         );
     }
@@ -187,10 +342,31 @@ mod tests {
     #[test]
     fn signature_kat() {
         let (_, _, _, sig) = setup();
-        let expected = expect!["ps3H9GzNz9cGv5AXJeQ74TvaVxHGGaYFwKYV2M1KKQKcuRBWgS6C2QyazzCqwCbSC1toXUXV45VwEQRU18w5SSJb78DrJLYY47rmCPnUnem4njmFFt8HMSXFco2fGBGn53gDEcEqicsdACF8DmoawFp7ghVpquBwGacKDzsbfqEkYK2ktbXKSRb4rstBYBS12F5v7N9Tq1Jq2BRjCUfGQJmax1QxbDSX1eL5MTp11vjwATwDfgAVaQRJfkGYGdR34ZtTtHBNQJj8HLvBQA72BajbkxNvf5JDJeogf3BNWQgdGfrmibayJ9a9xxgTVPBSoKigrkuDUR9bVs257Zwp9dEPEzLh8vYMQqo3HuPm6EPiNpgHM8tS5YJvpmym1wVLBDdt9T4LaogSu3U4bM5zfUykW7q6d3iHcDp3gktaqm38amW5W37SfYyaXdYkxvMgx1gQz1iPo6Bt9SfgEcei2WkpZyA6sPRzdH5jJTc61umobw934i9LehRapjwMA6QhTUHLSsQNHE6ELv2i6gLi61FdrT3rW8mRcCE1GFy8AQef7MrZL94X3dPVvHbAzEajxvZkPfVcCLawnVTcGC3ACoUdd46PQDcuTDia6ETbADpXQC2gK93JR8RJ3siC7TCrPddUihnQPCvtYU862sVPc2aCC2qXXoZP3CyfUT3KYc2oq5tQ1huALDN9ndPYMgMKrSCouBzH5XYcQH7fz4pkL8ecjpj3qXNfjLPMUfbZd4K4w1AU989PW3LJSKYbwUvKLAHfzgbXe2SyAs8b8g3uwnWS8o2kMLndJVBfBi17HkkeCDnpXtNRULccKRY4BPRpUR1mzP6LKN2aLh4xT1hQSd94FaXZvDdXGNEbd8hb8zAaZg8GZCqsHYtFqvXEoq7Hc7Ayrp1Fnxc5b5WJ4myqCw1di8Gr1S7RMmQdCtfceEMoiZbQojzmTCz3omrdYti9KYA3t5fU3SkDpmEYufjqAGeQkphKY53eFMuSmiXavUiu7Xgy2ziXuHwuypVwBLZtTbLU2nM1JhJa5g1MSREozxatpKzidygaeiAm8mvDjhRS5dpGeUvs4F4FuxcMukTCuAQo3miuXTgoNy8bmtktF3uQ6Rk2xNDn7eNp865Rot3nWup1fymFQ3kisLKZDZHnJ3d8aktg1gAnMBWbmDetMisWyZPcsWJdQGJM9V7gMyLmBAkRKUSo3AeGanVC6g7bFXYkt5nVmVsf3qu5Y5qGBeT2nzJ5JEsBestt6QH59cr4tbb9bGQn2LhhyYBqZHe51bCmeskq1fWW9fofPthZcQbNXy7RfB352jX4SShCNDeJGKES7ifEvXoDy57Exfa8j4MPtcQNqpK8VZJAPnBkofGNkgCZEQMAJDitgwsJuQdA8ckQKbTyhpWy1LLfzKB6CxXoregVEQAE3BGXDFhJs5GzMrKd8PUMDXrxWdUXA9DAyXJyqiMeiYd3BzKLWSoVXdE2zzw84pBMrWkBDBgjXhUixduNrtyLhxQJbUFbMraRsS8Pdw42tcVursDYyokbNakNXZ8wq2bQkc8BmwL8hsx657XPoCNeFAdQh8xXymZwNusqdhsW8Gzv4mqJq3XnhN8eoMckpRZbf2ecSZBtkbbUhriRNrcvBkWwip6Kk1ZM2VxBiUcpm37bawZPjCf8Wg39VC1LpTLu4Mx1w9sXxdZfzGHrhGGaqjm2uB54r4ZbkAmCPPXJ7zVeo8bEpQY73jFAjjM7M15bbWTXsRfqJ7BDydLkdQBspwNd3xbdTWcjabZ6N9hZR67Q3E839vKgiRd53Cu9Y8rZd58CHhmes487CNbEWa4AuK4c8stsX5iU8rZMAe6YYywzWBBGKHnJJ9o9zaWoF2yQFQr2yxVdLkpZh2k7WtS9rwaZkZkdt2QqnzrLVBWp6i5cZDryHVak6Ln5ttpPE1xAgvF3VGHk3rA2uuLQXD76ZxgnVjPj9X8pG2vyucpRAsHf7mbPA5wfQmUDw5oUKGvyT4qW2ZQPtC8dgaNGFRB4Y5NnWEYE2taiYhDdTonAFzAtwd2wpa5yLYZiNus6AzAf3HvFyDcvA4DADkyyn9iFPVxVKXPT6fDDac5QefytJhEwP5kesCBj15ggmMWfoiAfpCTqcGUYMBWQ9kApu3YTGDWf9nDJMJgT6pr4bo7nad4XVb18d6turVbN9Psii5XAANXTh4nfpTkaaGA2HVGy849rCq9V4ZWgtUdH8GqNHqaAYrsyi37ctAgXQ2fbZS851iYz5dCTnsAJnnsBijy9dJqbPqA9nZAuk8cUiSSaX7jY59SknSS2ptgXHwr2ch5nXXQkDMh5wNA6eARixgQ6hvpgQKekPXpVhVbfapRku2RiaBLjJ3Ec5TZuqM58ByLsJ3CcrNbBxvHh533M1sbbx4gL1booefbXhWvryP9HnJ9TWfWZ81AzufcHSSyEFfGbqQPqg8kabuqRF4et7JxFauWNmJM7ftV7gnxp7DbRMpNwdsrX6SbRivR6i61FnWMMCtVvHtGLbzghJKP2ArcVcd21Dmx7HP3vdqCaX3dGTyB1MX18TesPWDUa8hqnYgkW2SKLmwromioJouM1U9oCBbBREHLx3YDWYXeHoYbiU9RQk4zAiejvqQ1FUhQWp38EYnnY2ii119LEDoEKbuVLFHuoFHpYsfHHBNTSzfhaEa71kiYMm8NpSsxBC5JDUo3Nbypz3coM15yu53WwMuaSBu6fTFxMXFwoEiDQ3DLUPyxeVcts5THfmMrhDteNqvMmTsoZQgx6S9aM3dnLZdfp99WyCCzin98HGCWh4wpSC6rVA8TMZQ963zGH27VkEwKfXQsci9LdTTyPJS3mmoRc12o7pM86CkFyF4EFkC2xaSCyTei97ci8SP4cMssqFSjHphMgboCMTEhHsyFHw9sVhgz525dxid5XXGP5KudQP5B7ou1CrEfd3Lp5ov9JUAxB3axuZCtRNBoBrpAYuv1nY7kMZY1wex8gYBC2t1hF55M38LPqY72PPQTdA2KKxAfSkxnTHjMgvr7dY4HVmP1f4v6zu3F1hYZ4Ay6CcRbvxSMaX1qDReZqjzN1haq9oRFbxqaHED3BFSvsEDkfHzfjLzxBB5zofBfCaabMxsJB7MhJZ1SnSBvdQbQveFQ5L4PVqyJZXBgJBwh9bcqhe3Smap2TVCdDWvwxo2zjGFxrda2HL8Fmb3PNFPs5XJ8bHaf8C6s9hDUpgv1uJyZLJPSKN7irVCGfCZeuFi4UUfzHFVVvYP1jftmHZKxEBx1KGFVWCdW7Bau5rd5VgabWyxDvG17HyZvLLKzbzLvT3b25DziM2XTqG1rWXGP3XchYUVqKKn2BReCBpmHH2J2LPMNma7gwJVpyVwHyhG7zXsEvDZaosDA6F2ya8cmFxf8WpNdWqdkMu1os33X61QsLtLdXk1zbWYRWvhZ7X4uJQQnFdQ2YaeCBegZx3vpTXb1EGLh3QtheZAvoaBHB8NWU2moxzQjkyAGnHaHjBVPCsjwVqmsCjprewjqpuqXFhvDYYpaPBCbHtbYNJyL9cSqcYpTW7zpP7grWMKBvEFvDiqWmLxTMwbuzccCdZqZrvNKfCqeJvPrfGwh26PwrVeZ11n3ZtiB25LdZEtWfE2dJUnFTUL25Cw3W1GRWcMYCYiXY1QxsAnopJYtFUucHTSaBckUd6LHy87eyBjfyyMTJtrhxEyCyi6YPeNVvJzRMCXemEo8GqYRduW3UNUP5rbDAH6UFi5kkvdTHpJi8UiZsMZzmj3tAKRGm8sdphmvEdSKSApqpkN5tXdY67sFikQJ5NMSnvE2CAvvmni8fBEwQnqo7HTSP3CWHLwuo4sbiGvKggVKxT5SgEpmmjq4GXsXHPJpicmFM3NA91gXQDhXQkF4ePTN76W1qg4vBTJU9L36c1o5aLZmGvzjoEaNTTUWvxcAXcXhePGtdmuHSkwLyPLP2huq8ho2aNYiSH7SbWNicLMosHNACscM4knxP45NyHrrJUJoB9BCEGDUZoJB7SVrft7sC13Ki6dz87ZEeehXkvJj5gwz75kjU6Ao2yPhwqVXkRGSnAv14E63eXxjG19C7xRxfnDMrTPztpgKPyAKAh8GbSv3KMQu4LkMnnn4GN2bkNh9FByT1n5NCxMX6yNh91xKtcsbpDL2Ka4dwteyK7k8dHmZGM4NTkqhSj5RhTYqX6boEPgQmtq9NKMtds7tVNfKXno5Wmg5m8TNVCB5BWWyxsrsLMNk4mF4QmQZt7uV4NU9UdQJ9JNmNoNEk5y9sstPbL7X633Z4aQDKUTqxXC1YDxjE7nMsXjtHzDi2fTbSApGam218d8MvW1jGwRWunjtKtGQdZUjdzfhdoYGSNALC6YjHXfQJyXXd4BxX5uVWrfpcjyvdgso9DQHeMVnJBkp94o81QZcjyVdGnv5EzrArDs5Te8nRxNdADU44qGw9vSffXYr55hpknSU9TZf2ioFXBnarWvFhrQRG6R5CrrTNYZso5WAwhRubNmr2ScoPcKpUaML"];
+        let expected = expect!["JX3W4yabT1hG9hMoGdDZf3HMKiL2SJSKjFkuggoTYfjuywFkSUbRkDMeeQX7JoNa6DF8mSXbbDCfA2rWZ57bD4UX7HK5RSTpur1LgK6cF6zbGc3nnwfiiwGkydTByemoH8J5ayje5NGANFDfYbZMNYEurm1tPLsEFTmgYGt2MhCNKivcfanSjV6uJdYQsSS3M7FDjBqCvBgHArRXB3tcH2uUs1gMTDWVCpieRXAgPuTq2etqLNqifybkvUZtQDXsk9mxBPGrthKz1S5R9Reii9ESBQ76JRB6Nuwgd7jrbXSFS1Z5g6yW3fETk854vtsNeh4BE147zhaNN8ZrpkFk5UrkrWgphiMjcyWLDEm3gRVYokvoTWWgtR6h5XVLGQDgrLxVnRYjBjLXaKwufRkVn7XcVaNwS6io87DDo7oJEb3rxWCifvasLy1Lw1GnfwzS5E9ojnWC53ZqAxNqmVEehSgjtBYZtcZ2Tfon3kwppuPAcmYvnHvxpxrhCrDvYhMKNAuAqXe8kRvSNFAynACmB3gFMx7YNKHHbrUuPrv4s8LBm1WacThPsSWESe7S5ojVhVSmPjorUSM73vncVqiwhGgvNPozLeuZ1KUnF7tsxsKZGzadXmcaqqrTyiTcC7dsKkbPQm2YUbZAfGcVZSeiei3z9eXHp5u9VLTk6FUS2LrE9XhWG78z9DAcn3RMZmwApdhwLgtL8FmX9aRBNpHQZjHuMdiNH5osvpt1ygHQGnBzysysPazeBm7RVj9hfcoqm5R8QGB5PRrUZgTajdaDsezAdkbpsphdiVgZfF1oX3YpcvQDtZNu7CnYZNTfYiKtF5u3PnpawTfT6owcNMHYLxxLk9Hc9sLxL28PjyjDPeXeNSH5TxNXKsqPv8ZLYfdDc1AEfVdjxxeJokXPknoKnDv9NsARhvquewhreMjusJf53ws2MFx6LL7m1m5WLrmNyX7fmkfxbRvtsmJUVPPy73pUdyw9usaXb7Nkia24vT56Y8Q7Lj1a11XkD5Jqe2rt79qTiqG8yZVUW5kF9C7xqhdA3vTkfhMSJxnU7Pyd5MoPnCVz1bhXqAobEPYNhVDQ9mKZuxz9wEBSsZRH42MoWkWbqnjqASRvtTtn91wVkVZ1D7Zjj9jhdMMKwaRLWMu3dptkuK84LpCwS4xZjaxhrTLHwZjPm9CFVvdUDscfHKPfGq1fE7GA1Gwzu7Gt9ppPhBDUXSFZJmLRaPzmeDe8kXeM7rVRCwAv9M3mSkA4BAFcpF3jQsXSPBAVsVDXyFddKpzy1yCdh4L9fkJdVs9jih3CegADJuKA73JoYk9ee46piSNhEFU8vUfctX9qHAk2h6g1HGgGHQeW8giYKGC2RyDt25pet4aXVdaV76Gt2USiATVQK8bCjEe74xBR6TLs4XJfq3VPavmspHeBpiRN5VndUfPayBpSCQQLfLE5EdziUrtQmTJocTAKQy2nz7W7u6D6hn9NX8kBN75sm8ZWE1GiAwBHvGV8mKsnSiB8sMP9CJ4C8v9QtudsYBqGKdAdbCMBUmPZUHsae5kYRMtHbDK1HJgDyEqAVtjZNidQuRttLPDKkyRfEtLLfjRCm5eyeiQSpGvAzSfMA5WXCZy8GLaaqakig83G96FzNnw6wHSYnZWXtWfaigMBQ6Hn9TyqRoGqXALpfk8bF348ziR6ZAedPDisyJ77npu6RgshFf7kgTqLoZKA88F2J5DQ5D7Hh5h8JTT69oryNc7iAU79Ua9EwmWgJFmeVp1V8yS5Dw78CbdrBoUFqPStNMuoi3xN478MRi2mKsGFqVkuQ7PhhP5ETY5k3LX8DaR5Zk2cNff4MctJLKjaTySH8P1JY8WQpyrnTjxJ1BC9RbA2cdNFwSZQX2cPdbsNUUhniMLQDNat9N4oaCPKqvEc3GAbPdjrX9bC3oMvTLoBR1djSHAtLsGXWs8FGe8uE9qxZGHUqECTywPtoYNmmtcwx9vbTpi8RkBwhnE6TXmXmE2s5VJVd1uNy3TygcVfHZn9aSGkW5g1NFakv3Hdc9qMjmpVEL6i1Sr63zfMKKPhVho4tvo1eWtQZQnEh9MnEgc4KHZPwDJcf9b1Jzq2VA7NUPRRqNgWd2SzsF4xkC8xDQapoYZKAhhzr2yCjaMecQGWAGuWkeCK2HKHdnpFP9RpinoAyomYnWDqqmfv1ozmLTDTcZt22kBJDaXG9znTHkK33y2oTXbrgXabvQFqtbyKm5sbbLAmErVQ1mfWai4cVALt1zeYq7cLnTSHbRt9bxZtp8KNzfE9vw92zsjgLpuyNr2up3m1QkP8sE6pmc62Q2e1Nab5TJkd2NZqsKDQ1CPGpKCjF89Q6PPp472pcwvJpMQswgQfFnGA8CdxWRNnom1MNkS1eBengwbhnjgyoVMiccUxd4XZ27h6vSdk2uhdQCwfYpAr4Px8gCUmYBPSGFqKDzEaPhAX9AGXwc7r26vzdYEv1mJeQtwFi48u3jaTXXtSB1rb8xWXaTXEVZsWPvdfpLGFuce8Eg2rayn8umJmTRXbLZifFrt5tSBKSLbFDakF2148r24Puuq7GxwHSbwVKPaHZu1r5qju6xeC2NhEmvur6y1fvPrHMMvgQVCeaeAsNjcDzR11dCB1uLLHiDTHGyBckryK5BJTPhUGqH2M8qmLdfyTM1yNYcTy9oXrpNy8tg9WRSw2fpinMcyscLpjfAXS8Tgpa2514Z5w2FsXypU5j232jufeCcWSS781ohswa4XA5scvDDKi2WMcu1MXwZuf8unrNMNHfNnGLQVr71ApyfprHgExStzrsxFgVPmAdKxDvPdKB7SBjKD6F6uWBroKyphzKivp6GMEr5VG7rG5dAyt3gDXLh2RXrGnynwpiK6kPjqiWgair11ZN24EDAmCQBUiEbuwZy1rDYdP4YDEeMM65Pith4RWv6YKSfpvpFqEnUGshd3Hjpwgxs66dE4ffjpSQBDnrBRYQfcQWwRrP7K4Ydh9yadBymKH9i6f3Gj4D8W55Gi4rtvD8aFf4qWBCi7xELCe7Licy5VpdyiNKUhQodFoQx3c2tbMxThWpGKWDmkS3WH9y6uTQkr6xqHZFcD3jB2ZfppcZmmC5m4SjbAFwNyutfzSsQSmETvFg59aVqTErRc5JKXgcFA7WHUxEdDaCRSx9B4BssNbkAkrkJMVaQJ62xcvXunsxoTk9jF6eRVw53YdfNWY97pFXgd9VFtN8NhCzRHT1kMyDb9BG2ZX36usDE7riD7DvjLoUGo4RGZZ9JMMVQg2z8NViWkMJGHkR3r9oydHfjprj7wmR8iwmeetGLb9au4HuQLH4VKGyTFScQ5FF61BmGwHiuroU8kWTJaD75QUbTfvXZyFCkhdbyfQNWgMSqwunW1Zfb3QdGyGRWjCEGVZ3Xn4BR6vLEdkNU9y19ki6tGD7B2Y6UuqmT2XgKWnhBc9kn7ZShVSZqaQ9KF99BmE5Gs6AJwDkPBBH5S52c3kqxJkG4RcXEsxV6oPwjcVA2PQkmVLCsGyrCCxhwRoWTHwcZd56X2btkcWuJzXrH1BxieX1aVQHcNt1kqCooxu6fhRXSMUhJTtursNPSK9ttafymkD8mdMsE8LpLpQbLtvfmUU5aKugoniaEMjyHbrZ9KQvt93gH7JVtNW3jUD9RkRGiCYtsadh8W5ntp4LcARXgixWt9CoktvSVi31f3qYk7QXCRZDkvXzC3R8QknsH3SiYfw4cYPm9dGHnEmhS8dDsGJCm48cwyB5RjMsxdYBWZkFUqSGv25bHi6VkgxugprLJvG8G6xvBfaT7u861uBkz2F71u8fQZRw7AT1Tmu5o5jqYQUaQo9iLjbaD5Admsrb5Ji4S19nhTb1ZFe5Stroo8zVedeoWVXiwBQQkR7KRJ9SqYHYaMPN2tFEDC52yThYk5oHtjDm5MEvBGzbvUMv22nbu6EcaVCyZ57rL5VtHyK1tfmGoizFQ6tikSdhYpNx4asop2En15rHFE2QRqnNDH5igQ1t8uy9JGKZFqkXRpeY4Epw4H2R19hWHhRCTP6ZH5hEd1RLCPoKqHMHYYtLaSXBNgrQoaY3HoDbLADzoEW1YGqWNbFwrDfThUnjqaqpWntVnT6EMP5J4HjEkXYDvHwc68X4nBsHqX1a9JhoE2dH6HQLXYETwYc1tHPmkgSAV5DFuDmSBEFxqLPgRAWWbUHwa4sZfdzDu7v5TC9xX94B7D1zTTv72pMr6dsvbpfkG5RBjYX9nAqzK9PuJMxnAyYmStZgizgB2ex5HoNKCspb2U8Mur4KCjdj7zCq2jtP3YjmgDwMmFPvb2b2N8TEmKoTPXUCYhstX2AV2Nt8hkTpF42jnYR62vJWm3vkbHrXfWjL1hqAiSTRP7Sky1yCDyqYWEUZqEuxT8qsPKVwmkah2mqLjRmJS9ntqm6hf3SCSfF3UJ6MU73yJ4n8Q5RZwsYmG5whq3uRyYjfB5LRtvvo53gvMZYtscpZuk3eAeXKH8e37q3kiVwmDWBaVFx47wG4Rg"];
         expected.assert_eq(&sig.to_string());
     }
 
+    #[test]
+    fn unversioned_signature_kat() {
+        // Created before format versions were introduced, which committed to the signer's entire
+        // public key.
+        let mut rng = ChaChaRng::seed_from_u64(0xDEADBEEF);
+        let signer = SecKey::random(&mut rng);
+        let message = rng.gen::<[u8; 64]>();
+        let sig = "ps3H9GzNz9cGv5AXJeQ74TvaVxHGGaYFwKYV2M1KKQKcuRBWgS6C2QyazzCqwCbSC1toXUXV45VwEQRU18w5SSJb78DrJLYY47rmCPnUnem4njmFFt8HMSXFco2fGBGn53gDEcEqicsdACF8DmoawFp7ghVpquBwGacKDzsbfqEkYK2ktbXKSRb4rstBYBS12F5v7N9Tq1Jq2BRjCUfGQJmax1QxbDSX1eL5MTp11vjwATwDfgAVaQRJfkGYGdR34ZtTtHBNQJj8HLvBQA72BajbkxNvf5JDJeogf3BNWQgdGfrmibayJ9a9xxgTVPBSoKigrkuDUR9bVs257Zwp9dEPEzLh8vYMQqo3HuPm6EPiNpgHM8tS5YJvpmym1wVLBDdt9T4LaogSu3U4bM5zfUykW7q6d3iHcDp3gktaqm38amW5W37SfYyaXdYkxvMgx1gQz1iPo6Bt9SfgEcei2WkpZyA6sPRzdH5jJTc61umobw934i9LehRapjwMA6QhTUHLSsQNHE6ELv2i6gLi61FdrT3rW8mRcCE1GFy8AQef7MrZL94X3dPVvHbAzEajxvZkPfVcCLawnVTcGC3ACoUdd46PQDcuTDia6ETbADpXQC2gK93JR8RJ3siC7TCrPddUihnQPCvtYU862sVPc2aCC2qXXoZP3CyfUT3KYc2oq5tQ1huALDN9ndPYMgMKrSCouBzH5XYcQH7fz4pkL8ecjpj3qXNfjLPMUfbZd4K4w1AU989PW3LJSKYbwUvKLAHfzgbXe2SyAs8b8g3uwnWS8o2kMLndJVBfBi17HkkeCDnpXtNRULccKRY4BPRpUR1mzP6LKN2aLh4xT1hQSd94FaXZvDdXGNEbd8hb8zAaZg8GZCqsHYtFqvXEoq7Hc7Ayrp1Fnxc5b5WJ4myqCw1di8Gr1S7RMmQdCtfceEMoiZbQojzmTCz3omrdYti9KYA3t5fU3SkDpmEYufjqAGeQkphKY53eFMuSmiXavUiu7Xgy2ziXuHwuypVwBLZtTbLU2nM1JhJa5g1MSREozxatpKzidygaeiAm8mvDjhRS5dpGeUvs4F4FuxcMukTCuAQo3miuXTgoNy8bmtktF3uQ6Rk2xNDn7eNp865Rot3nWup1fymFQ3kisLKZDZHnJ3d8aktg1gAnMBWbmDetMisWyZPcsWJdQGJM9V7gMyLmBAkRKUSo3AeGanVC6g7bFXYkt5nVmVsf3qu5Y5qGBeT2nzJ5JEsBestt6QH59cr4tbb9bGQn2LhhyYBqZHe51bCmeskq1fWW9fofPthZcQbNXy7RfB352jX4SShCNDeJGKES7ifEvXoDy57Exfa8j4MPtcQNqpK8VZJAPnBkofGNkgCZEQMAJDitgwsJuQdA8ckQKbTyhpWy1LLfzKB6CxXoregVEQAE3BGXDFhJs5GzMrKd8PUMDXrxWdUXA9DAyXJyqiMeiYd3BzKLWSoVXdE2zzw84pBMrWkBDBgjXhUixduNrtyLhxQJbUFbMraRsS8Pdw42tcVursDYyokbNakNXZ8wq2bQkc8BmwL8hsx657XPoCNeFAdQh8xXymZwNusqdhsW8Gzv4mqJq3XnhN8eoMckpRZbf2ecSZBtkbbUhriRNrcvBkWwip6Kk1ZM2VxBiUcpm37bawZPjCf8Wg39VC1LpTLu4Mx1w9sXxdZfzGHrhGGaqjm2uB54r4ZbkAmCPPXJ7zVeo8bEpQY73jFAjjM7M15bbWTXsRfqJ7BDydLkdQBspwNd3xbdTWcjabZ6N9hZR67Q3E839vKgiRd53Cu9Y8rZd58CHhmes487CNbEWa4AuK4c8stsX5iU8rZMAe6YYywzWBBGKHnJJ9o9zaWoF2yQFQr2yxVdLkpZh2k7WtS9rwaZkZkdt2QqnzrLVBWp6i5cZDryHVak6Ln5ttpPE1xAgvF3VGHk3rA2uuLQXD76ZxgnVjPj9X8pG2vyucpRAsHf7mbPA5wfQmUDw5oUKGvyT4qW2ZQPtC8dgaNGFRB4Y5NnWEYE2taiYhDdTonAFzAtwd2wpa5yLYZiNus6AzAf3HvFyDcvA4DADkyyn9iFPVxVKXPT6fDDac5QefytJhEwP5kesCBj15ggmMWfoiAfpCTqcGUYMBWQ9kApu3YTGDWf9nDJMJgT6pr4bo7nad4XVb18d6turVbN9Psii5XAANXTh4nfpTkaaGA2HVGy849rCq9V4ZWgtUdH8GqNHqaAYrsyi37ctAgXQ2fbZS851iYz5dCTnsAJnnsBijy9dJqbPqA9nZAuk8cUiSSaX7jY59SknSS2ptgXHwr2ch5nXXQkDMh5wNA6eARixgQ6hvpgQKekPXpVhVbfapRku2RiaBLjJ3Ec5TZuqM58ByLsJ3CcrNbBxvHh533M1sbbx4gL1booefbXhWvryP9HnJ9TWfWZ81AzufcHSSyEFfGbqQPqg8kabuqRF4et7JxFauWNmJM7ftV7gnxp7DbRMpNwdsrX6SbRivR6i61FnWMMCtVvHtGLbzghJKP2ArcVcd21Dmx7HP3vdqCaX3dGTyB1MX18TesPWDUa8hqnYgkW2SKLmwromioJouM1U9oCBbBREHLx3YDWYXeHoYbiU9RQk4zAiejvqQ1FUhQWp38EYnnY2ii119LEDoEKbuVLFHuoFHpYsfHHBNTSzfhaEa71kiYMm8NpSsxBC5JDUo3Nbypz3coM15yu53WwMuaSBu6fTFxMXFwoEiDQ3DLUPyxeVcts5THfmMrhDteNqvMmTsoZQgx6S9aM3dnLZdfp99WyCCzin98HGCWh4wpSC6rVA8TMZQ963zGH27VkEwKfXQsci9LdTTyPJS3mmoRc12o7pM86CkFyF4EFkC2xaSCyTei97ci8SP4cMssqFSjHphMgboCMTEhHsyFHw9sVhgz525dxid5XXGP5KudQP5B7ou1CrEfd3Lp5ov9JUAxB3axuZCtRNBoBrpAYuv1nY7kMZY1wex8gYBC2t1hF55M38LPqY72PPQTdA2KKxAfSkxnTHjMgvr7dY4HVmP1f4v6zu3F1hYZ4Ay6CcRbvxSMaX1qDReZqjzN1haq9oRFbxqaHED3BFSvsEDkfHzfjLzxBB5zofBfCaabMxsJB7MhJZ1SnSBvdQbQveFQ5L4PVqyJZXBgJBwh9bcqhe3Smap2TVCdDWvwxo2zjGFxrda2HL8Fmb3PNFPs5XJ8bHaf8C6s9hDUpgv1uJyZLJPSKN7irVCGfCZeuFi4UUfzHFVVvYP1jftmHZKxEBx1KGFVWCdW7Bau5rd5VgabWyxDvG17HyZvLLKzbzLvT3b25DziM2XTqG1rWXGP3XchYUVqKKn2BReCBpmHH2J2LPMNma7gwJVpyVwHyhG7zXsEvDZaosDA6F2ya8cmFxf8WpNdWqdkMu1os33X61QsLtLdXk1zbWYRWvhZ7X4uJQQnFdQ2YaeCBegZx3vpTXb1EGLh3QtheZAvoaBHB8NWU2moxzQjkyAGnHaHjBVPCsjwVqmsCjprewjqpuqXFhvDYYpaPBCbHtbYNJyL9cSqcYpTW7zpP7grWMKBvEFvDiqWmLxTMwbuzccCdZqZrvNKfCqeJvPrfGwh26PwrVeZ11n3ZtiB25LdZEtWfE2dJUnFTUL25Cw3W1GRWcMYCYiXY1QxsAnopJYtFUucHTSaBckUd6LHy87eyBjfyyMTJtrhxEyCyi6YPeNVvJzRMCXemEo8GqYRduW3UNUP5rbDAH6UFi5kkvdTHpJi8UiZsMZzmj3tAKRGm8sdphmvEdSKSApqpkN5tXdY67sFikQJ5NMSnvE2CAvvmni8fBEwQnqo7HTSP3CWHLwuo4sbiGvKggVKxT5SgEpmmjq4GXsXHPJpicmFM3NA91gXQDhXQkF4ePTN76W1qg4vBTJU9L36c1o5aLZmGvzjoEaNTTUWvxcAXcXhePGtdmuHSkwLyPLP2huq8ho2aNYiSH7SbWNicLMosHNACscM4knxP45NyHrrJUJoB9BCEGDUZoJB7SVrft7sC13Ki6dz87ZEeehXkvJj5gwz75kjU6Ao2yPhwqVXkRGSnAv14E63eXxjG19C7xRxfnDMrTPztpgKPyAKAh8GbSv3KMQu4LkMnnn4GN2bkNh9FByT1n5NCxMX6yNh91xKtcsbpDL2Ka4dwteyK7k8dHmZGM4NTkqhSj5RhTYqX6boEPgQmtq9NKMtds7tVNfKXno5Wmg5m8TNVCB5BWWyxsrsLMNk4mF4QmQZt7uV4NU9UdQJ9JNmNoNEk5y9sstPbL7X633Z4aQDKUTqxXC1YDxjE7nMsXjtHzDi2fTbSApGam218d8MvW1jGwRWunjtKtGQdZUjdzfhdoYGSNALC6YjHXfQJyXXd4BxX5uVWrfpcjyvdgso9DQHeMVnJBkp94o81QZcjyVdGnv5EzrArDs5Te8nRxNdADU44qGw9vSffXYr55hpknSU9TZf2ioFXBnarWvFhrQRG6R5CrrTNYZso5WAwhRubNmr2ScoPcKpUaML"
+        .parse::<Signature>()
+        .expect("should parse");
+
+        assert_matches!(
+            verify_slice(&signer.pub_key.ver_key, Some(&signer.pub_key), &message, &sig),
+            Ok(())
+        );
+        assert_matches!(
+            verify_slice(&signer.pub_key.ver_key, None, &message, &sig),
+            Err(VerifyError::InvalidSignature)
+        );
+    }
+
     #[test]
     fn signature_decoding() {
         let (_, _, _, sig) = setup();
@@ -207,9 +383,9 @@ mod tests {
         );
     }
 
-    fn setup() -> (ChaChaRng, SecKey, Vec<u8>, Signature) {
+    fn setup() -> (ChaChaRng, SignKey, Vec<u8>, Signature) {
         let mut rng = ChaChaRng::seed_from_u64(0xDEADBEEF);
-        let signer = SecKey::random(&mut rng);
+        let signer = SignKey::random(&mut rng);
         let message = rng.gen::<[u8; 64]>();
         let sig = sign(&mut rng, &signer, Cursor::new(message)).expect("signing should be ok");
         (rng, signer, message.to_vec(), sig)
@@ -0,0 +1,372 @@
+//! Shamir secret sharing of secret key seeds over GF(256).
+
+use std::{
+    collections::HashSet,
+    fmt::{self, Debug, Formatter},
+    str::FromStr,
+};
+
+use lockstitch::{Protocol, TAG_LEN};
+use rand::{CryptoRng, Rng};
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+use crate::{
+    keys::{PubKey, SecKey, SK_LEN},
+    CombineSharesError, ParseShareError, SplitSharesError,
+};
+
+/// The length of a key ID, in bytes.
+const KEY_ID_LEN: usize = 16;
+
+/// The length of an encoded share, in bytes.
+const SHARE_LEN: usize = KEY_ID_LEN + 2 + SK_LEN + TAG_LEN;
+
+/// A share of a secret key, produced by [`crate::SecretKey::split`].
+///
+/// Each share contains the ID of the key it was split from, the number of shares required to
+/// reconstruct the key, the share's index, one point of each of the seed's polynomials, and an
+/// authentication tag which can be verified once the key has been reconstructed.
+#[derive(Clone, Eq, PartialEq)]
+pub struct Share([u8; SHARE_LEN]);
+
+impl Share {
+    /// Decodes a share from a byte slice.
+    #[must_use]
+    pub fn decode(b: impl AsRef<[u8]>) -> Option<Share> {
+        Some(Share(b.as_ref().try_into().ok()?))
+    }
+
+    /// Encodes the share as a byte array.
+    #[must_use]
+    pub const fn encode(&self) -> [u8; SHARE_LEN] {
+        self.0
+    }
+
+    /// Returns the number of shares required to reconstruct the secret key.
+    #[must_use]
+    pub const fn threshold(&self) -> u8 {
+        self.0[KEY_ID_LEN]
+    }
+
+    /// Returns the index of the share.
+    #[must_use]
+    pub const fn index(&self) -> u8 {
+        self.0[KEY_ID_LEN + 1]
+    }
+
+    fn key_id(&self) -> &[u8] {
+        &self.0[..KEY_ID_LEN]
+    }
+
+    fn y(&self) -> &[u8] {
+        &self.0[KEY_ID_LEN + 2..KEY_ID_LEN + 2 + SK_LEN]
+    }
+
+    fn tag(&self) -> &[u8] {
+        &self.0[SHARE_LEN - TAG_LEN..]
+    }
+}
+
+impl Debug for Share {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Share")
+            .field("threshold", &self.threshold())
+            .field("index", &self.index())
+            .finish_non_exhaustive()
+    }
+}
+
+impl fmt::Display for Share {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", bs58::encode(self.0).into_string())
+    }
+}
+
+impl FromStr for Share {
+    type Err = ParseShareError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Share::decode(bs58::decode(s).into_vec()?.as_slice()).ok_or(ParseShareError::InvalidLength)
+    }
+}
+
+impl Drop for Share {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl ZeroizeOnDrop for Share {}
+
+/// Splits the given secret key's seed into `shares` shares, any `threshold` of which can be combined
+/// to reconstruct the secret key.
+///
+/// # Errors
+///
+/// Returns [`SplitSharesError::InvalidThreshold`] if `threshold` is zero or greater than `shares`.
+pub fn split(
+    mut rng: impl Rng + CryptoRng,
+    sk: &SecKey,
+    threshold: u8,
+    shares: u8,
+) -> Result<Vec<Share>, SplitSharesError> {
+    if threshold == 0 || threshold > shares {
+        return Err(SplitSharesError::InvalidThreshold);
+    }
+
+    // Generate random coefficients for each byte's polynomial. The constant term of each polynomial
+    // is the corresponding byte of the seed.
+    let mut coefficients = vec![[0u8; SK_LEN]; usize::from(threshold)];
+    coefficients[0].copy_from_slice(&sk.seed);
+    for c in coefficients.iter_mut().skip(1) {
+        rng.fill_bytes(c);
+    }
+
+    let key_id = key_id(&sk.pub_key);
+    let auth = auth_protocol(&sk.seed);
+    let shares = (1..=shares)
+        .map(|x| {
+            let mut share = [0u8; SHARE_LEN];
+            let (share_key_id, rest) = share.split_at_mut(KEY_ID_LEN);
+            let (share_t, rest) = rest.split_at_mut(1);
+            let (share_x, rest) = rest.split_at_mut(1);
+            let (share_y, share_tag) = rest.split_at_mut(SK_LEN);
+
+            share_key_id.copy_from_slice(&key_id);
+            share_t[0] = threshold;
+            share_x[0] = x;
+
+            // Evaluate each byte's polynomial at x using Horner's method.
+            for (j, y) in share_y.iter_mut().enumerate() {
+                *y = coefficients.iter().rev().fold(0, |acc, c| gf_mul(acc, x) ^ c[j]);
+            }
+
+            share_tag.copy_from_slice(&tag(auth.clone(), &key_id, threshold, x, share_y));
+            Share(share)
+        })
+        .collect();
+
+    coefficients.zeroize();
+    Ok(shares)
+}
+
+/// Combines the given shares to reconstruct a secret key.
+pub fn combine(shares: &[Share]) -> Result<SecKey, CombineSharesError> {
+    let first = shares.first().ok_or(CombineSharesError::NotEnoughShares)?;
+    let threshold = first.threshold();
+
+    // Ensure all shares are from the same split of the same key and have unique indexes.
+    let mut indexes = HashSet::with_capacity(shares.len());
+    for share in shares {
+        if share.key_id() != first.key_id()
+            || share.threshold() != threshold
+            || share.index() == 0
+            || !indexes.insert(share.index())
+        {
+            return Err(CombineSharesError::InconsistentShares);
+        }
+    }
+
+    if threshold == 0 || shares.len() < usize::from(threshold) {
+        return Err(CombineSharesError::NotEnoughShares);
+    }
+
+    // Interpolate each byte's polynomial at zero using the first `threshold` shares.
+    let points = &shares[..usize::from(threshold)];
+    let mut seed = [0u8; SK_LEN];
+    for (i, share) in points.iter().enumerate() {
+        let basis = points
+            .iter()
+            .enumerate()
+            .filter(|&(m, _)| m != i)
+            .fold(1, |acc, (_, p)| gf_mul(acc, gf_div(p.index(), p.index() ^ share.index())));
+        for (s, y) in seed.iter_mut().zip(share.y()) {
+            *s ^= gf_mul(basis, *y);
+        }
+    }
+
+    let sk = SecKey::from_canonical_bytes(seed).expect("should be a valid seed");
+    seed.zeroize();
+
+    // Verify that the reconstructed key has the expected ID and that every share's tag is valid.
+    let auth = auth_protocol(&sk.seed);
+    let valid = lockstitch::ct_eq(&key_id(&sk.pub_key), first.key_id())
+        && shares.iter().all(|share| {
+            let tag = tag(auth.clone(), share.key_id(), threshold, share.index(), share.y());
+            lockstitch::ct_eq(&tag, share.tag())
+        });
+    if !valid {
+        return Err(CombineSharesError::InvalidShares);
+    }
+
+    Ok(sk)
+}
+
+/// Derives the ID of the given public key.
+fn key_id(pk: &PubKey) -> [u8; KEY_ID_LEN] {
+    let mut key_id = Protocol::new("veil.sss.key-id");
+    key_id.mix("public-key", &pk.encoded);
+    key_id.derive_array("key-id")
+}
+
+/// Initializes a protocol keyed with the given seed for authenticating shares.
+fn auth_protocol(seed: &[u8; SK_LEN]) -> Protocol {
+    let mut auth = Protocol::new("veil.sss");
+    auth.mix("seed", seed);
+    auth
+}
+
+/// Calculates the authentication tag of a share.
+fn tag(mut auth: Protocol, key_id: &[u8], threshold: u8, x: u8, y: &[u8]) -> [u8; TAG_LEN] {
+    auth.mix("key-id", key_id);
+    auth.mix("threshold", &[threshold]);
+    auth.mix("index", &[x]);
+    auth.mix("share", y);
+    auth.derive_array("tag")
+}
+
+/// Multiplies two elements of GF(2⁸) modulo the AES polynomial `x⁸ + x⁴ + x³ + x + 1` in constant
+/// time.
+const fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+    let mut p = 0u8;
+    let mut i = 0;
+    while i < 8 {
+        p ^= a & (b & 1).wrapping_neg();
+        let carry = (a >> 7).wrapping_neg();
+        a = (a << 1) ^ (0x1B & carry);
+        b >>= 1;
+        i += 1;
+    }
+    p
+}
+
+/// Returns the multiplicative inverse of an element of GF(2⁸) as `a²⁵⁴`, in constant time.
+const fn gf_inv(a: u8) -> u8 {
+    let a2 = gf_mul(a, a);
+    let a4 = gf_mul(a2, a2);
+    let a8 = gf_mul(a4, a4);
+    let a16 = gf_mul(a8, a8);
+    let a32 = gf_mul(a16, a16);
+    let a64 = gf_mul(a32, a32);
+    let a128 = gf_mul(a64, a64);
+    gf_mul(gf_mul(gf_mul(a128, a64), gf_mul(a32, a16)), gf_mul(gf_mul(a8, a4), a2))
+}
+
+/// Divides two elements of GF(2⁸).
+const fn gf_div(a: u8, b: u8) -> u8 {
+    gf_mul(a, gf_inv(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_matches::assert_matches;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    use super::*;
+
+    #[test]
+    fn field_inverse() {
+        for a in 1..=255u8 {
+            assert_eq!(1, gf_mul(a, gf_inv(a)), "invalid inverse of {a}");
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let (_, sk, shares) = setup(3, 5);
+
+        for subset in
+            [&shares[..3], &shares[2..], &[shares[4].clone(), shares[0].clone(), shares[2].clone()]]
+        {
+            let sk_p = combine(subset).expect("should combine");
+            assert_eq!(sk.seed, sk_p.seed, "invalid seed");
+            assert_eq!(sk.pub_key, sk_p.pub_key, "invalid public key");
+        }
+    }
+
+    #[test]
+    fn all_shares() {
+        let (_, sk, shares) = setup(3, 5);
+        let sk_p = combine(&shares).expect("should combine");
+        assert_eq!(sk.pub_key, sk_p.pub_key, "invalid public key");
+    }
+
+    #[test]
+    fn not_enough_shares() {
+        let (_, _, shares) = setup(3, 5);
+        assert_matches!(combine(&shares[..2]), Err(CombineSharesError::NotEnoughShares));
+        assert_matches!(combine(&[]), Err(CombineSharesError::NotEnoughShares));
+    }
+
+    #[test]
+    fn duplicate_shares() {
+        let (_, _, shares) = setup(3, 5);
+        assert_matches!(
+            combine(&[shares[0].clone(), shares[0].clone(), shares[1].clone()]),
+            Err(CombineSharesError::InconsistentShares)
+        );
+    }
+
+    #[test]
+    fn invalid_threshold() {
+        let mut rng = ChaChaRng::seed_from_u64(0xDEADBEEF);
+        let sk = SecKey::random(&mut rng);
+        for (threshold, n) in [(0, 3), (4, 3), (1, 0)] {
+            assert_matches!(
+                split(&mut rng, &sk, threshold, n),
+                Err(SplitSharesError::InvalidThreshold),
+                "threshold {threshold} of {n} shares"
+            );
+        }
+        assert_matches!(split(&mut rng, &sk, 255, 255), Ok(shares) if shares.len() == 255);
+    }
+
+    #[test]
+    fn mixed_keys() {
+        let (mut rng, _, shares) = setup(2, 3);
+        let other = SecKey::random(&mut rng);
+        let other_shares = split(&mut rng, &other, 2, 3).expect("should split");
+        assert_matches!(
+            combine(&[shares[0].clone(), other_shares[1].clone()]),
+            Err(CombineSharesError::InconsistentShares)
+        );
+    }
+
+    #[test]
+    fn modified_share() {
+        let (_, _, mut shares) = setup(3, 5);
+        shares[1].0[KEY_ID_LEN + 2 + 100] ^= 1;
+        assert_matches!(combine(&shares[..3]), Err(CombineSharesError::InvalidShares));
+    }
+
+    #[test]
+    fn modified_extra_share() {
+        let (_, _, mut shares) = setup(3, 5);
+        shares[4].0[KEY_ID_LEN + 2 + 100] ^= 1;
+        assert_matches!(combine(&shares), Err(CombineSharesError::InvalidShares));
+    }
+
+    #[test]
+    fn share_encoding() {
+        let (_, _, shares) = setup(2, 2);
+        let decoded = shares[0].to_string().parse::<Share>();
+        assert_eq!(Ok(shares[0].clone()), decoded, "error parsing share");
+
+        assert_eq!(
+            Err(ParseShareError::InvalidEncoding(bs58::decode::Error::InvalidCharacter {
+                character: 'l',
+                index: 4,
+            })),
+            "invalid share".parse::<Share>(),
+            "parsed invalid share"
+        );
+    }
+
+    fn setup(threshold: u8, n: u8) -> (ChaChaRng, SecKey, Vec<Share>) {
+        let mut rng = ChaChaRng::seed_from_u64(0xDEADBEEF);
+        let sk = SecKey::random(&mut rng);
+        let shares = split(&mut rng, &sk, threshold, n).expect("should split");
+        (rng, sk, shares)
+    }
+}
@@ -0,0 +1,512 @@
+use std::{
+    io::{self, Read},
+    num::NonZeroUsize,
+    ops::Range,
+    thread,
+};
+
+use lockstitch::Protocol;
+
+use crate::{Digest, VerifyProofError, DIGEST_LEN};
+
+/// The length of the chunks a [`DigestTree`] hashes independently of each other.
+pub const TREE_CHUNK_LEN: usize = 1024 * 1024;
+
+/// The hash of a leaf or an interior node of a [`DigestTree`].
+type Node = [u8; DIGEST_LEN];
+
+/// A Merkle tree of the chunks of a message, used to create `veil.digest.tree` digests.
+///
+/// Unlike [`Digest::new`], the message's chunks are hashed in parallel, and a [`RangeProof`] can
+/// prove that part of the message belongs to a digest without the rest of the message. A
+/// `veil.digest.tree` digest is never equal to a `veil.digest` digest of the same message.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DigestTree {
+    leaves: Vec<Node>,
+    len: u64,
+}
+
+impl DigestTree {
+    /// Create a tree from a reader.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error returned by operations on `reader`.
+    pub fn new(mut reader: impl Read) -> io::Result<DigestTree> {
+        // Read a chunk for each thread at a time and hash them in parallel.
+        let threads = parallelism();
+        let batch_len = threads * TREE_CHUNK_LEN;
+        let mut batch = Vec::with_capacity(batch_len);
+        let mut leaves = Vec::new();
+        let mut len = 0;
+        loop {
+            batch.clear();
+            let n = (&mut reader).take(batch_len as u64).read_to_end(&mut batch)?;
+            len += n as u64;
+            leaves.extend(hash_chunks(&batch, threads));
+            if n < batch_len {
+                break;
+            }
+        }
+
+        Ok(DigestTree::from_leaves(leaves, len))
+    }
+
+    /// Create a tree from a message slice.
+    ///
+    /// The tree is identical to one created by [`DigestTree::new`] for the same message.
+    #[must_use]
+    pub fn from_slice(message: &[u8]) -> DigestTree {
+        DigestTree::from_leaves(hash_chunks(message, parallelism()), message.len() as u64)
+    }
+
+    /// The length of the message in bytes.
+    #[must_use]
+    pub const fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Returns `true` if the message is empty.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Create a `veil.digest.tree` digest of a sequence of metadata values and the message.
+    #[must_use]
+    pub fn digest(&self, metadata: &[impl AsRef<[u8]>]) -> Digest {
+        root_digest(metadata, self.len, &root(&self.leaves))
+    }
+
+    /// Create a proof that the given range of the message belongs to the tree's digest.
+    ///
+    /// The proof covers all the chunks which overlap with `range`, so the range the proof is
+    /// verified against may be larger; see [`RangeProof::range`]. Returns `None` if `range` is
+    /// empty or extends past the end of the message.
+    #[must_use]
+    pub fn prove(&self, range: Range<u64>) -> Option<RangeProof> {
+        if range.is_empty() || range.end > self.len {
+            return None;
+        }
+
+        // Expand the range to whole chunks.
+        let chunks = usize::try_from(range.start / TREE_CHUNK_LEN as u64).ok()?
+            ..usize::try_from(range.end.div_ceil(TREE_CHUNK_LEN as u64)).ok()?;
+
+        // Collect the roots of the subtrees which don't overlap with the chunks.
+        let mut path = Vec::new();
+        prove_subtree(&self.leaves, 0, &chunks, &mut path);
+
+        Some(RangeProof { len: self.len, chunks: chunks.start as u64..chunks.end as u64, path })
+    }
+
+    /// Create a tree from its leaves, adding a leaf for the empty message if necessary.
+    fn from_leaves(mut leaves: Vec<Node>, len: u64) -> DigestTree {
+        if leaves.is_empty() {
+            leaves.push(leaf(&[]));
+        }
+        DigestTree { leaves, len }
+    }
+}
+
+/// A proof that a range of whole chunks belongs to a message with a given `veil.digest.tree`
+/// digest.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RangeProof {
+    len: u64,
+    chunks: Range<u64>,
+    path: Vec<Node>,
+}
+
+impl RangeProof {
+    /// The range of the message covered by the proof.
+    #[must_use]
+    pub fn range(&self) -> Range<u64> {
+        let start = self.chunks.start.saturating_mul(TREE_CHUNK_LEN as u64);
+        let end = self.chunks.end.saturating_mul(TREE_CHUNK_LEN as u64).min(self.len);
+        start..end
+    }
+
+    /// Verify that `data` is the range of a message with the given metadata and digest which is
+    /// covered by the proof.
+    ///
+    /// # Errors
+    ///
+    /// If `data` is not the range covered by the proof or the proof is invalid, returns
+    /// [`VerifyProofError::InvalidProof`].
+    pub fn verify(
+        &self,
+        digest: &Digest,
+        metadata: &[impl AsRef<[u8]>],
+        data: &[u8],
+    ) -> Result<(), VerifyProofError> {
+        // Check the proof's range against the message and the data.
+        let leaf_count = usize::try_from(self.len.div_ceil(TREE_CHUNK_LEN as u64).max(1))
+            .map_err(|_| VerifyProofError::InvalidProof)?;
+        let range = self.range();
+        if self.chunks.is_empty()
+            || self.chunks.end > leaf_count as u64
+            || range.end - range.start != data.len() as u64
+        {
+            return Err(VerifyProofError::InvalidProof);
+        }
+
+        // Hash the data's chunks and recalculate the root of the tree with the proof's path.
+        let chunks = self.chunks.start as usize..self.chunks.end as usize;
+        let mut leaves = hash_chunks(data, parallelism()).into_iter();
+        let mut path = self.path.iter();
+        let root = verify_subtree(leaf_count, 0, &chunks, &mut leaves, &mut path)
+            .ok_or(VerifyProofError::InvalidProof)?;
+        if path.next().is_some() {
+            return Err(VerifyProofError::InvalidProof);
+        }
+
+        // Compare the counterfactual digest to the given digest in constant time.
+        (root_digest(metadata, self.len, &root) == *digest)
+            .then_some(())
+            .ok_or(VerifyProofError::InvalidProof)
+    }
+
+    /// Create a proof from its encoded form.
+    #[must_use]
+    pub fn decode(b: impl AsRef<[u8]>) -> Option<RangeProof> {
+        let b = b.as_ref();
+        if b.len() < 24 || (b.len() - 24) % DIGEST_LEN != 0 {
+            return None;
+        }
+        let (len, b) = b.split_at(8);
+        let (start, b) = b.split_at(8);
+        let (end, path) = b.split_at(8);
+
+        Some(RangeProof {
+            len: u64::from_le_bytes(len.try_into().ok()?),
+            chunks: u64::from_le_bytes(start.try_into().ok()?)
+                ..u64::from_le_bytes(end.try_into().ok()?),
+            path: path
+                .chunks_exact(DIGEST_LEN)
+                .map(|n| n.try_into().expect("should be 32 bytes"))
+                .collect(),
+        })
+    }
+
+    /// Encode the proof as the message length, the start and end chunk indexes (all little-endian
+    /// 64-bit integers), and the hashes of the subtrees outside the proof's range.
+    #[must_use]
+    pub fn encode(&self) -> Vec<u8> {
+        let mut b = Vec::with_capacity(24 + self.path.len() * DIGEST_LEN);
+        b.extend_from_slice(&self.len.to_le_bytes());
+        b.extend_from_slice(&self.chunks.start.to_le_bytes());
+        b.extend_from_slice(&self.chunks.end.to_le_bytes());
+        b.extend(self.path.iter().flatten());
+        b
+    }
+}
+
+/// Returns the number of threads to hash chunks on.
+fn parallelism() -> usize {
+    thread::available_parallelism().map_or(1, NonZeroUsize::get)
+}
+
+/// Hashes each chunk of `message` on up to `threads` threads, preserving the chunks' order.
+fn hash_chunks(message: &[u8], threads: usize) -> Vec<Node> {
+    let chunks = message.chunks(TREE_CHUNK_LEN).collect::<Vec<_>>();
+    if threads == 1 || chunks.len() < 2 {
+        return chunks.into_iter().map(leaf).collect();
+    }
+
+    // Split the chunks into contiguous runs and hash each on its own thread.
+    let run_len = chunks.len().div_ceil(threads);
+    thread::scope(|s| {
+        chunks
+            .chunks(run_len)
+            .map(|run| s.spawn(move || run.iter().copied().map(leaf).collect::<Vec<_>>()))
+            .collect::<Vec<_>>()
+            .into_iter()
+            .flat_map(|handle| handle.join().expect("hashing should not panic"))
+            .collect()
+    })
+}
+
+/// Returns the size of the left subtree of a tree with `n > 1` leaves: the largest power of two
+/// less than `n`.
+const fn split(n: usize) -> usize {
+    1 << (n - 1).ilog2()
+}
+
+/// Returns the root of the tree with the given leaves.
+fn root(leaves: &[Node]) -> Node {
+    if let [leaf] = leaves {
+        return *leaf;
+    }
+    let (left, right) = leaves.split_at(split(leaves.len()));
+    node(&root(left), &root(right))
+}
+
+/// Appends the roots of the subtrees of `leaves` which don't overlap with `chunks` to `path`, in
+/// order. `offset` is the index of the first of `leaves`.
+fn prove_subtree(leaves: &[Node], offset: usize, chunks: &Range<usize>, path: &mut Vec<Node>) {
+    let end = offset + leaves.len();
+    if end <= chunks.start || chunks.end <= offset {
+        path.push(root(leaves));
+    } else if !(chunks.start <= offset && end <= chunks.end) {
+        let (left, right) = leaves.split_at(split(leaves.len()));
+        prove_subtree(left, offset, chunks, path);
+        prove_subtree(right, offset + left.len(), chunks, path);
+    }
+}
+
+/// Recalculates the root of a subtree of `n` leaves starting at `offset`, taking the hashes of
+/// chunks from `leaves` and the roots of subtrees which don't overlap with `chunks` from `path`.
+/// Returns `None` if either runs out.
+fn verify_subtree<'a>(
+    n: usize,
+    offset: usize,
+    chunks: &Range<usize>,
+    leaves: &mut impl Iterator<Item = Node>,
+    path: &mut impl Iterator<Item = &'a Node>,
+) -> Option<Node> {
+    if offset + n <= chunks.start || chunks.end <= offset {
+        path.next().copied()
+    } else if n == 1 {
+        leaves.next()
+    } else {
+        let k = split(n);
+        let left = verify_subtree(k, offset, chunks, leaves, path)?;
+        let right = verify_subtree(n - k, offset + k, chunks, leaves, path)?;
+        Some(node(&left, &right))
+    }
+}
+
+/// Hashes a chunk of the message.
+fn leaf(chunk: &[u8]) -> Node {
+    let mut protocol = Protocol::new("veil.digest.tree");
+    protocol.mix("leaf", chunk);
+    protocol.derive_array("node")
+}
+
+/// Hashes two child nodes.
+fn node(left: &Node, right: &Node) -> Node {
+    let mut protocol = Protocol::new("veil.digest.tree");
+    protocol.mix("left", left);
+    protocol.mix("right", right);
+    protocol.derive_array("node")
+}
+
+/// Creates a digest of a sequence of metadata values and the length and tree root of a message.
+fn root_digest(metadata: &[impl AsRef<[u8]>], len: u64, root: &Node) -> Digest {
+    // Initialize a protocol.
+    let mut protocol = Protocol::new("veil.digest.tree");
+
+    // Mix the metadata values in order into the protocol.
+    for v in metadata {
+        protocol.mix("metadata", v.as_ref());
+    }
+
+    // Mix the message length and the tree root into the protocol.
+    protocol.mix_int("len", len);
+    protocol.mix("root", root);
+
+    // Derive 32 bytes as a digest.
+    Digest(protocol.derive_array("digest"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use assert_matches::assert_matches;
+    use expect_test::expect;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaChaRng;
+
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let message = setup(3 * TREE_CHUNK_LEN + 1234);
+
+        let a = DigestTree::new(Cursor::new(&message)).expect("cursor reads should be infallible");
+        let b = DigestTree::from_slice(&message);
+
+        assert_eq!(a, b, "inconsistent trees");
+        assert_eq!(4, a.leaves.len());
+        assert_eq!(a.digest(&["metadata"]), b.digest(&["metadata"]), "inconsistent digests");
+    }
+
+    #[test]
+    fn thread_count() {
+        let message = setup(5 * TREE_CHUNK_LEN);
+
+        let sequential = hash_chunks(&message, 1);
+        for threads in [2, 3, 8] {
+            assert_eq!(sequential, hash_chunks(&message, threads), "depends on thread count");
+        }
+    }
+
+    #[test]
+    fn empty_message() {
+        let tree = DigestTree::from_slice(&[]);
+
+        assert!(tree.is_empty());
+        assert_eq!(vec![leaf(&[])], tree.leaves);
+        assert_eq!(None, tree.prove(0..0), "proved an empty range");
+    }
+
+    #[test]
+    fn different_messages() {
+        let message = setup(TREE_CHUNK_LEN + 1);
+        let mut different_message = message.clone();
+        different_message[TREE_CHUNK_LEN] ^= 1;
+
+        let a = DigestTree::from_slice(&message).digest(&["metadata"]);
+        let b = DigestTree::from_slice(&different_message).digest(&["metadata"]);
+        let c = DigestTree::from_slice(&message[..TREE_CHUNK_LEN]).digest(&["metadata"]);
+
+        assert_ne!(a, b, "collision on message");
+        assert_ne!(a, c, "collision on length");
+    }
+
+    #[test]
+    fn different_metadata() {
+        let tree = DigestTree::from_slice(&setup(64));
+
+        assert_ne!(tree.digest(&["one", "two"]), tree.digest(&["two", "one"]));
+    }
+
+    #[test]
+    fn not_a_sequential_digest() {
+        let message = setup(64);
+
+        assert_ne!(
+            Digest::from_slice(&["metadata"], &message),
+            DigestTree::from_slice(&message).digest(&["metadata"]),
+            "collision with veil.digest"
+        );
+    }
+
+    #[test]
+    fn range_proofs() {
+        let message = setup(7 * TREE_CHUNK_LEN + 1234);
+        let tree = DigestTree::from_slice(&message);
+        let digest = tree.digest(&["metadata"]);
+
+        for (range, expected) in [
+            (0..1, 0..TREE_CHUNK_LEN),
+            (
+                TREE_CHUNK_LEN as u64 + 5..3 * TREE_CHUNK_LEN as u64,
+                TREE_CHUNK_LEN..3 * TREE_CHUNK_LEN,
+            ),
+            (2..message.len() as u64, 0..message.len()),
+            (message.len() as u64 - 1..message.len() as u64, 7 * TREE_CHUNK_LEN..message.len()),
+        ] {
+            let proof = tree.prove(range).expect("should prove a valid range");
+            assert_eq!(expected.start as u64..expected.end as u64, proof.range());
+
+            let data = &message[expected];
+            assert_matches!(proof.verify(&digest, &["metadata"], data), Ok(()));
+            assert_matches!(
+                RangeProof::decode(proof.encode()).expect("should decode a valid proof").verify(
+                    &digest,
+                    &["metadata"],
+                    data
+                ),
+                Ok(())
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_range() {
+        let tree = DigestTree::from_slice(&setup(64));
+
+        assert_eq!(None, tree.prove(10..10), "proved an empty range");
+        assert_eq!(None, tree.prove(10..65), "proved a range past the end");
+    }
+
+    #[test]
+    fn wrong_data() {
+        let message = setup(3 * TREE_CHUNK_LEN);
+        let tree = DigestTree::from_slice(&message);
+        let digest = tree.digest(&["metadata"]);
+        let proof = tree.prove(TREE_CHUNK_LEN as u64..2 * TREE_CHUNK_LEN as u64).unwrap();
+
+        let mut data = message[TREE_CHUNK_LEN..2 * TREE_CHUNK_LEN].to_vec();
+        data[22] ^= 1;
+        assert_matches!(
+            proof.verify(&digest, &["metadata"], &data),
+            Err(VerifyProofError::InvalidProof)
+        );
+        assert_matches!(
+            proof.verify(&digest, &["metadata"], &message[..TREE_CHUNK_LEN]),
+            Err(VerifyProofError::InvalidProof),
+            "verified the wrong chunk"
+        );
+        assert_matches!(
+            proof.verify(&digest, &["metadata"], &message[TREE_CHUNK_LEN..2 * TREE_CHUNK_LEN - 1]),
+            Err(VerifyProofError::InvalidProof),
+            "verified truncated data"
+        );
+    }
+
+    #[test]
+    fn wrong_digest() {
+        let message = setup(2 * TREE_CHUNK_LEN);
+        let tree = DigestTree::from_slice(&message);
+        let proof = tree.prove(0..1).unwrap();
+        let data = &message[..TREE_CHUNK_LEN];
+
+        assert_matches!(
+            proof.verify(&tree.digest(&["other metadata"]), &["metadata"], data),
+            Err(VerifyProofError::InvalidProof)
+        );
+        assert_matches!(
+            proof.verify(&Digest::from_slice(&["metadata"], &message), &["metadata"], data),
+            Err(VerifyProofError::InvalidProof)
+        );
+    }
+
+    #[test]
+    fn modified_proofs() {
+        let message = setup(5 * TREE_CHUNK_LEN);
+        let tree = DigestTree::from_slice(&message);
+        let digest = tree.digest(&["metadata"]);
+        let encoded =
+            tree.prove(2 * TREE_CHUNK_LEN as u64..3 * TREE_CHUNK_LEN as u64).unwrap().encode();
+        let data = &message[2 * TREE_CHUNK_LEN..3 * TREE_CHUNK_LEN];
+
+        // Modify the length, the chunk range, and each node of the path.
+        for i in (0..24).step_by(8).chain((24..encoded.len()).step_by(DIGEST_LEN)) {
+            let mut modified = encoded.clone();
+            modified[i] ^= 1;
+            if let Some(proof) = RangeProof::decode(&modified) {
+                assert_matches!(
+                    proof.verify(&digest, &["metadata"], data),
+                    Err(VerifyProofError::InvalidProof),
+                    "verified a modified proof at byte {i}"
+                );
+            }
+        }
+
+        assert_eq!(None, RangeProof::decode(&encoded[..encoded.len() - 1]));
+        assert_matches!(
+            RangeProof::decode(&encoded[..encoded.len() - DIGEST_LEN])
+                .expect("should decode a truncated path")
+                .verify(&digest, &["metadata"], data),
+            Err(VerifyProofError::InvalidProof)
+        );
+    }
+
+    #[test]
+    fn digest_kat() {
+        let digest = DigestTree::from_slice(&setup(2 * TREE_CHUNK_LEN + 64)).digest(&["kat"]);
+        let expected = expect!["Fh33qVQfa4e5nfHonhkZzToKHMoNPkHcZH3YGH7vtD6p"];
+        expected.assert_eq(&digest.to_string());
+    }
+
+    fn setup(len: usize) -> Vec<u8> {
+        let mut rng = ChaChaRng::seed_from_u64(0xDEADBEEF);
+        let mut message = vec![0u8; len];
+        rng.fill(message.as_mut_slice());
+        message
+    }
+}
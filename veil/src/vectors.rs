@@ -0,0 +1,547 @@
+//! Known-answer test vectors, published in `test-vectors/` for other implementations to check their
+//! conformance against.
+//!
+//! Each vector is generated from a seeded [`ChaChaRng`], and each test both replays the published
+//! vectors and checks that they're identical to freshly-generated ones. To regenerate them after an
+//! intentional format change, run `cargo xtask test-vectors`.
+
+use std::{env, fmt::Debug, fs, io, path::PathBuf};
+
+use rand::{Rng, RngCore, SeedableRng};
+use rand_chacha::ChaChaRng;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    kemeleon::{self, ENC_CT_LEN},
+    keys::{DecKey, PubKey, SecKey, VerKey, DEC_SK_LEN},
+    message, pbenc, sig, DecryptLimits, Digest, DigestTree, EncryptOptions, FileMetadata, Kdf, Mac,
+    RangeProof, Signature, DIGEST_LEN, TREE_CHUNK_LEN,
+};
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct KeyVector {
+    rng_seed: u64,
+    #[serde(with = "hex")]
+    seed: Vec<u8>,
+    #[serde(with = "hex")]
+    public_key: Vec<u8>,
+    children: Vec<ChildKeyVector>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct ChildKeyVector {
+    label: String,
+    #[serde(with = "hex")]
+    public_key: Vec<u8>,
+}
+
+#[test]
+fn keys() {
+    let generated = [1, 2]
+        .into_iter()
+        .map(|rng_seed| {
+            let key = SecKey::random(ChaChaRng::seed_from_u64(rng_seed));
+            KeyVector {
+                rng_seed,
+                seed: key.seed.to_vec(),
+                public_key: key.pub_key.encoded.to_vec(),
+                children: ["work", "work/2026"]
+                    .into_iter()
+                    .map(|label| ChildKeyVector {
+                        label: label.into(),
+                        public_key: key.derive_child(label).pub_key.encoded.to_vec(),
+                    })
+                    .collect(),
+            }
+        })
+        .collect();
+
+    conform("keys.json", generated, |v: &KeyVector| {
+        let key = SecKey::from_canonical_bytes(&v.seed).expect("should be a valid seed");
+        assert_eq!(v.public_key, key.pub_key.encoded);
+        for child in &v.children {
+            assert_eq!(child.public_key, key.derive_child(&child.label).pub_key.encoded);
+        }
+    });
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct PbencVector {
+    rng_seed: u64,
+    passphrase: String,
+    kdf: String,
+    time_cost: u8,
+    memory_cost: u8,
+    parallelism: u8,
+    #[serde(with = "hex")]
+    plaintext: Vec<u8>,
+    #[serde(with = "hex")]
+    ciphertext: Vec<u8>,
+}
+
+#[test]
+fn pbenc() {
+    let generated = [
+        (3, Kdf::Balloon { time_cost: 0, memory_cost: 0, parallelism: 0 }),
+        (4, Kdf::Balloon { time_cost: 1, memory_cost: 2, parallelism: 1 }),
+        (5, Kdf::Argon2id { time_cost: 0, memory_cost: 3, parallelism: 0 }),
+        (6, Kdf::Argon2id { time_cost: 1, memory_cost: 5, parallelism: 1 }),
+    ]
+    .into_iter()
+    .map(|(rng_seed, kdf)| {
+        let mut rng = ChaChaRng::seed_from_u64(rng_seed);
+        let passphrase = format!("passphrase {rng_seed}");
+        let plaintext = rng.gen::<[u8; 64]>().to_vec();
+        let mut ciphertext = vec![0u8; plaintext.len() + pbenc::OVERHEAD];
+        pbenc::encrypt(&mut rng, passphrase.as_bytes(), kdf, &plaintext, &mut ciphertext)
+            .expect("should encrypt");
+
+        let (kdf, time_cost, memory_cost, parallelism) = match kdf {
+            Kdf::Balloon { time_cost, memory_cost, parallelism } => {
+                ("balloon", time_cost, memory_cost, parallelism)
+            }
+            Kdf::Argon2id { time_cost, memory_cost, parallelism } => {
+                ("argon2id", time_cost, memory_cost, parallelism)
+            }
+        };
+        PbencVector {
+            rng_seed,
+            passphrase,
+            kdf: kdf.into(),
+            time_cost,
+            memory_cost,
+            parallelism,
+            plaintext,
+            ciphertext,
+        }
+    })
+    .collect();
+
+    conform("pbenc.json", generated, |v: &PbencVector| {
+        let kdf_id = match v.kdf.as_str() {
+            "balloon" => 0x00,
+            "argon2id" => 0x01,
+            kdf => panic!("unknown KDF {kdf:?}"),
+        };
+        assert_eq!(
+            [kdf_id, v.time_cost, v.memory_cost, v.parallelism],
+            v.ciphertext[..4],
+            "KDF parameters should be encoded in the ciphertext"
+        );
+
+        let mut in_out = v.ciphertext.clone();
+        assert_eq!(
+            Some(v.plaintext.as_slice()),
+            pbenc::decrypt(v.passphrase.as_bytes(), &mut in_out),
+            "should decrypt with the passphrase"
+        );
+    });
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct KemeleonVector {
+    rng_seed: u64,
+    #[serde(with = "hex")]
+    decapsulation_key_seed: Vec<u8>,
+    #[serde(with = "hex")]
+    encapsulation_key: Vec<u8>,
+    #[serde(with = "hex")]
+    ciphertext: Vec<u8>,
+    #[serde(with = "hex")]
+    encoded_ciphertext: Vec<u8>,
+    #[serde(with = "hex")]
+    shared_secret: Vec<u8>,
+}
+
+#[test]
+fn kemeleon() {
+    let generated = [7, 8, 9]
+        .into_iter()
+        .map(|rng_seed| {
+            let mut rng = ChaChaRng::seed_from_u64(rng_seed);
+            let dk_seed = rng.gen::<[u8; DEC_SK_LEN]>();
+            let dk = DecKey::from_canonical_bytes(dk_seed).expect("should be a valid seed");
+            let (ect, ss) = kemeleon::encapsulate(&dk.ek, &mut rng);
+            KemeleonVector {
+                rng_seed,
+                decapsulation_key_seed: dk_seed.to_vec(),
+                encapsulation_key: dk.ek_encoded.to_vec(),
+                ciphertext: kemeleon::decode_ct(ect).to_vec(),
+                encoded_ciphertext: ect.to_vec(),
+                shared_secret: ss.to_vec(),
+            }
+        })
+        .collect();
+
+    conform("kemeleon.json", generated, |v: &KemeleonVector| {
+        let dk = DecKey::from_canonical_bytes(&v.decapsulation_key_seed)
+            .expect("should be a valid seed");
+        assert_eq!(v.encapsulation_key, dk.ek_encoded);
+
+        let ect: [u8; ENC_CT_LEN] =
+            v.encoded_ciphertext.as_slice().try_into().expect("should be 1252 bytes");
+        assert_eq!(v.ciphertext, kemeleon::decode_ct(ect), "should decode to the ciphertext");
+        assert_eq!(v.shared_secret, kemeleon::decapsulate(&dk.dk, ect));
+    });
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct DigestVector {
+    metadata: Vec<String>,
+    #[serde(with = "hex")]
+    message: Vec<u8>,
+    #[serde(with = "hex")]
+    digest: Vec<u8>,
+}
+
+#[test]
+fn digest() {
+    let generated = [
+        (vec![], b"".as_slice()),
+        (vec![], b"this is a message"),
+        (vec!["announcement.txt", "made-with-veil"], b"this is a message"),
+        (vec!["our special secret"], &[0xAB; 1000]),
+        (vec!["a larger message"], &[0xCD; 20_000]),
+    ]
+    .into_iter()
+    .map(|(metadata, message)| DigestVector {
+        digest: Digest::from_slice(&metadata, message).encode().to_vec(),
+        metadata: metadata.into_iter().map(String::from).collect(),
+        message: message.to_vec(),
+    })
+    .collect();
+
+    conform("digest.json", generated, |v: &DigestVector| {
+        assert_eq!(v.digest, Digest::from_slice(&v.metadata, &v.message).encode());
+    });
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct DigestTreeVector {
+    metadata: Vec<String>,
+    message_len: u64,
+    #[serde(with = "hex")]
+    digest: Vec<u8>,
+    proofs: Vec<RangeProofVector>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct RangeProofVector {
+    start: u64,
+    end: u64,
+    #[serde(with = "hex")]
+    proof: Vec<u8>,
+}
+
+/// Returns a `veil.digest.tree` test message, whose `i`th byte is `i mod 251`.
+fn tree_message(len: u64) -> Vec<u8> {
+    (0..len).map(|i| (i % 251) as u8).collect()
+}
+
+#[test]
+fn digest_tree() {
+    const C: u64 = TREE_CHUNK_LEN as u64;
+    let generated = [
+        (vec![], 0, vec![]),
+        (vec!["made-with-veil"], 1000, vec![(10, 20)]),
+        (vec![], C, vec![(0, C)]),
+        (vec!["disk.img"], 3 * C + 12345, vec![(0, 1), (C + 5, 2 * C + 5), (3 * C, 3 * C + 12345)]),
+    ]
+    .into_iter()
+    .map(|(metadata, message_len, ranges)| {
+        let tree = DigestTree::from_slice(&tree_message(message_len));
+        DigestTreeVector {
+            digest: tree.digest(&metadata).encode().to_vec(),
+            proofs: ranges
+                .into_iter()
+                .map(|(start, end)| {
+                    let proof = tree.prove(start..end).expect("should prove a valid range");
+                    let range = proof.range();
+                    RangeProofVector { start: range.start, end: range.end, proof: proof.encode() }
+                })
+                .collect(),
+            metadata: metadata.into_iter().map(String::from).collect(),
+            message_len,
+        }
+    })
+    .collect();
+
+    conform("digest-tree.json", generated, |v: &DigestTreeVector| {
+        let message = tree_message(v.message_len);
+        let digest = DigestTree::from_slice(&message).digest(&v.metadata);
+        assert_eq!(v.digest, digest.encode());
+        for p in &v.proofs {
+            let proof = RangeProof::decode(&p.proof).expect("should be a valid proof");
+            assert_eq!(p.start..p.end, proof.range());
+            let range = p.start as usize..p.end as usize;
+            assert_eq!(Ok(()), proof.verify(&digest, &v.metadata, &message[range]));
+        }
+    });
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct MacVector {
+    #[serde(with = "hex")]
+    key: Vec<u8>,
+    metadata: Vec<String>,
+    #[serde(with = "hex")]
+    message: Vec<u8>,
+    #[serde(with = "hex")]
+    mac: Vec<u8>,
+}
+
+#[test]
+fn mac() {
+    let generated = [
+        (b"our special secret".as_slice(), vec![], b"".as_slice()),
+        (b"our special secret", vec![], b"this is a message"),
+        (&[0xCD; 32], vec!["announcement.txt"], &[0xAB; 1000]),
+    ]
+    .into_iter()
+    .map(|(key, metadata, message)| MacVector {
+        mac: Mac::from_slice(key, &metadata, message).encode().to_vec(),
+        key: key.to_vec(),
+        metadata: metadata.into_iter().map(String::from).collect(),
+        message: message.to_vec(),
+    })
+    .collect();
+
+    conform("mac.json", generated, |v: &MacVector| {
+        assert_eq!(v.mac, Mac::from_slice(&v.key, &v.metadata, &v.message).encode());
+    });
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct SigVector {
+    rng_seed: u64,
+    #[serde(with = "hex")]
+    signer_seed: Vec<u8>,
+    #[serde(with = "hex")]
+    verifying_key: Vec<u8>,
+    #[serde(with = "hex")]
+    message: Vec<u8>,
+    #[serde(with = "hex")]
+    signature: Vec<u8>,
+}
+
+#[test]
+fn sig() {
+    let generated = [(10, 0), (11, 17), (12, 1000)]
+        .into_iter()
+        .map(|(rng_seed, len)| {
+            let mut rng = ChaChaRng::seed_from_u64(rng_seed);
+            let signer = SecKey::random(&mut rng);
+            let mut message = vec![0u8; len];
+            rng.fill_bytes(&mut message);
+            let signature = sig::sign_slice(&mut rng, &signer.sign_key, &message);
+            SigVector {
+                rng_seed,
+                signer_seed: signer.seed.to_vec(),
+                verifying_key: signer.sign_key.ver_key.encoded.to_vec(),
+                message,
+                signature: signature.encode().to_vec(),
+            }
+        })
+        .collect();
+
+    conform("sig.json", generated, |v: &SigVector| {
+        let signer = SecKey::from_canonical_bytes(&v.signer_seed).expect("should be a valid seed");
+        assert_eq!(v.verifying_key, signer.sign_key.ver_key.encoded);
+
+        let vk = VerKey::from_canonical_bytes(&v.verifying_key).expect("should be a valid key");
+        let signature = Signature::decode(&v.signature).expect("should be a valid signature");
+        sig::verify_slice(&vk, None, &v.message, &signature).expect("should verify");
+    });
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct DigestSigVector {
+    rng_seed: u64,
+    #[serde(with = "hex")]
+    signer_seed: Vec<u8>,
+    #[serde(with = "hex")]
+    verifying_key: Vec<u8>,
+    #[serde(with = "hex")]
+    digest: Vec<u8>,
+    #[serde(with = "hex")]
+    signature: Vec<u8>,
+}
+
+#[test]
+fn sig_digest() {
+    let generated = [13, 14]
+        .into_iter()
+        .map(|rng_seed| {
+            let mut rng = ChaChaRng::seed_from_u64(rng_seed);
+            let signer = SecKey::random(&mut rng);
+            let digest = Digest::decode(rng.gen::<[u8; DIGEST_LEN]>()).expect("should be valid");
+            let signature = sig::sign_digest(&mut rng, &signer.sign_key, &digest);
+            DigestSigVector {
+                rng_seed,
+                signer_seed: signer.seed.to_vec(),
+                verifying_key: signer.sign_key.ver_key.encoded.to_vec(),
+                digest: digest.encode().to_vec(),
+                signature: signature.encode().to_vec(),
+            }
+        })
+        .collect();
+
+    conform("sig-digest.json", generated, |v: &DigestSigVector| {
+        let signer = SecKey::from_canonical_bytes(&v.signer_seed).expect("should be a valid seed");
+        assert_eq!(v.verifying_key, signer.sign_key.ver_key.encoded);
+
+        let vk = VerKey::from_canonical_bytes(&v.verifying_key).expect("should be a valid key");
+        let digest = Digest::decode(&v.digest).expect("should be a valid digest");
+        let signature = Signature::decode(&v.signature).expect("should be a valid signature");
+        sig::verify_digest(&vk, &digest, &signature).expect("should verify");
+    });
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct MessageVector {
+    rng_seed: u64,
+    #[serde(with = "hex")]
+    sender_seed: Vec<u8>,
+    receiver_seeds: Vec<MessageReceiver>,
+    fakes: usize,
+    block_len: usize,
+    metadata: Option<MetadataVector>,
+    #[serde(with = "hex")]
+    plaintext: Vec<u8>,
+    #[serde(with = "hex")]
+    ciphertext: Vec<u8>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+struct MessageReceiver(#[serde(with = "hex")] Vec<u8>);
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct MetadataVector {
+    name: String,
+    size: u64,
+    mode: u32,
+    mtime: i64,
+}
+
+#[test]
+fn message() {
+    let generated =
+        [(13, 1, 0, 256, 0, false), (14, 2, 2, 256, 700, false), (15, 3, 5, 1024, 300, true)]
+            .into_iter()
+            .map(|(rng_seed, n, fakes, block_len, len, with_metadata)| {
+                let mut rng = ChaChaRng::seed_from_u64(rng_seed);
+                let sender = SecKey::random(&mut rng);
+                let receivers = (0..n).map(|_| SecKey::random(&mut rng)).collect::<Vec<_>>();
+                let mut plaintext = vec![0u8; len];
+                rng.fill_bytes(&mut plaintext);
+                let metadata = with_metadata.then(|| FileMetadata {
+                    name: "message.bin".into(),
+                    size: len as u64,
+                    mode: 0o600,
+                    mtime: 1_700_000_000,
+                });
+
+                // Place the real receivers' headers first, followed by the fakes.
+                let pks = receivers
+                    .iter()
+                    .map(|r| Some(r.pub_key.clone()))
+                    .chain((0..fakes).map(|_| None::<PubKey>))
+                    .collect::<Vec<_>>();
+                let options =
+                    EncryptOptions { block_len, metadata: metadata.clone(), ..Default::default() };
+                let mut ciphertext = Vec::new();
+                message::encrypt_slice(
+                    &mut rng,
+                    &plaintext,
+                    &mut ciphertext,
+                    &sender,
+                    &pks,
+                    &options,
+                )
+                .expect("should encrypt");
+
+                MessageVector {
+                    rng_seed,
+                    sender_seed: sender.seed.to_vec(),
+                    receiver_seeds: receivers
+                        .iter()
+                        .map(|r| MessageReceiver(r.seed.to_vec()))
+                        .collect(),
+                    fakes,
+                    block_len,
+                    metadata: metadata.map(|m| MetadataVector {
+                        name: m.name,
+                        size: m.size,
+                        mode: m.mode,
+                        mtime: m.mtime,
+                    }),
+                    plaintext,
+                    ciphertext,
+                }
+            })
+            .collect();
+
+    conform("message.json", generated, |v: &MessageVector| {
+        let sender = SecKey::from_canonical_bytes(&v.sender_seed).expect("should be a valid seed");
+        let metadata = v.metadata.as_ref().map(|m| FileMetadata {
+            name: m.name.clone(),
+            size: m.size,
+            mode: m.mode,
+            mtime: m.mtime,
+        });
+        for receiver in &v.receiver_seeds {
+            let receiver =
+                SecKey::from_canonical_bytes(&receiver.0).expect("should be a valid seed");
+            let mut plaintext = Vec::new();
+            let (n, m) = message::decrypt(
+                v.ciphertext.as_slice(),
+                &mut plaintext,
+                &receiver.dec_key,
+                None,
+                &sender.pub_key,
+                &DecryptLimits::default(),
+            )
+            .expect("should decrypt");
+            assert_eq!(v.plaintext.len() as u64, n);
+            assert_eq!(v.plaintext, plaintext);
+            assert_eq!(metadata, m);
+        }
+    });
+}
+
+/// Replays the published vectors in `test-vectors/{name}` with `check`, then checks that they're
+/// identical to the `generated` vectors. If `UPDATE_VECTORS` is set, the published vectors are
+/// overwritten with the generated vectors first.
+fn conform<T>(name: &str, generated: Vec<T>, check: impl Fn(&T))
+where
+    T: Debug + PartialEq + Serialize + DeserializeOwned,
+{
+    let path = [env!("CARGO_MANIFEST_DIR"), "..", "test-vectors", name].iter().collect::<PathBuf>();
+    if env::var_os("UPDATE_VECTORS").is_some() {
+        let mut json = serde_json::to_string_pretty(&generated).expect("should serialize");
+        json.push('\n');
+        fs::write(&path, json).expect("should write test vectors");
+    }
+
+    let published: Vec<T> = match fs::read_to_string(&path) {
+        Ok(json) => serde_json::from_str(&json).expect("should be valid test vectors"),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            panic!("{} not found; run `cargo xtask test-vectors`", path.display())
+        }
+        Err(e) => panic!("should read {}: {e}", path.display()),
+    };
+
+    for v in &published {
+        check(v);
+    }
+
+    assert_eq!(published.len(), generated.len(), "{name} has the wrong number of vectors");
+    for (i, (published, generated)) in published.iter().zip(&generated).enumerate() {
+        assert!(
+            published == generated,
+            "{name} #{i} differs from the generated vector; run `cargo xtask test-vectors` if the \
+             format has changed intentionally"
+        );
+    }
+}
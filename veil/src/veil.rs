@@ -6,14 +6,24 @@ use std::{
     io,
     io::{Read, Write},
     iter,
+    path::Path,
     str::FromStr,
 };
 
 use rand::{prelude::SliceRandom, CryptoRng, Rng};
+use zeroize::Zeroize;
 
 use crate::{
-    keys::{PubKey, SecKey, PK_LEN, SK_LEN},
-    message, pbenc, sig, DecryptError, EncryptError, ParsePublicKeyError, Signature, VerifyError,
+    archive::{Archiver, Extractor},
+    keys::{
+        DecKey, PubKey, SecKey, SignKey, VerKey, DEC_SK_LEN, ML_DSA_PK_LEN, PK_LEN, SIGN_SK_LEN,
+        SK_LEN,
+    },
+    message::{self, DEFAULT_BLOCK_LEN},
+    mnemonic,
+    pbenc::{self, Kdf},
+    pipeline, sig, sss, CombineSharesError, DecryptError, Digest, EncryptError, FileMetadata,
+    ParseMnemonicError, ParsePublicKeyError, Share, Signature, SplitSharesError, VerifyError,
 };
 
 /// A secret key, used to encrypt, decrypt, and sign messages.
@@ -33,82 +43,255 @@ impl SecretKey {
         PublicKey(self.0.pub_key.clone())
     }
 
-    /// Encrypts the secret key with the given passphrase and `veil.pbenc` parameters and writes it
-    /// to the given writer.
+    /// Encrypts the secret key with the given passphrase and `veil.pbenc` KDF and writes it to the
+    /// given writer.
     ///
     /// # Errors
     ///
-    /// Returns any error returned by operations on `writer`.
+    /// Returns any error returned by operations on `writer`, or [`io::ErrorKind::InvalidInput`] if
+    /// the KDF's costs are out of bounds (see [`Kdf::is_valid`]).
     pub fn store(
         &self,
-        mut writer: impl Write,
+        writer: impl Write,
         rng: impl Rng + CryptoRng,
         passphrase: &[u8],
-        time_cost: u8,
-        memory_cost: u8,
-        parallelism: u8,
+        kdf: Kdf,
     ) -> io::Result<usize> {
-        let mut enc_key = [0u8; SK_LEN + pbenc::OVERHEAD];
-        pbenc::encrypt(
-            rng,
-            passphrase,
-            time_cost,
-            memory_cost,
-            parallelism,
-            &self.0.seed,
-            &mut enc_key,
-        );
-        writer.write_all(&enc_key)?;
-        Ok(enc_key.len())
+        store_seed(writer, rng, passphrase, kdf, &self.0.seed)
     }
 
     /// Loads and decrypts the secret key from the given reader with the given passphrase.
     ///
+    /// The KDF used to encrypt the secret key is detected automatically.
+    ///
     /// # Errors
     ///
     /// If the passphrase is incorrect and/or the ciphertext has been modified, a
-    /// [`DecryptError::InvalidCiphertext`] error will be returned. If an error occurred while
-    /// reading, a [`DecryptError::ReadIo`] error will be returned.
-    pub fn load(mut reader: impl Read, passphrase: &[u8]) -> Result<SecretKey, DecryptError> {
-        let mut b = Vec::with_capacity(SK_LEN + pbenc::OVERHEAD);
-        reader.read_to_end(&mut b).map_err(DecryptError::ReadIo)?;
+    /// [`DecryptError::InvalidCiphertext`] error will be returned. If the reader contains a
+    /// different type of stored key, a [`DecryptError::InvalidKeyType`] error will be returned
+    /// without decrypting it. If an error occurred while reading, a [`DecryptError::ReadIo`] error
+    /// will be returned.
+    pub fn load(reader: impl Read, passphrase: &[u8]) -> Result<SecretKey, DecryptError> {
+        load_seed(reader, passphrase, &[SK_LEN], |seed| {
+            SecKey::from_canonical_bytes(seed).map(SecretKey)
+        })
+    }
 
-        // Decrypt the ciphertext and use the plaintext as the secret key.
-        pbenc::decrypt(passphrase, &mut b)
-            .and_then(SecKey::from_canonical_bytes)
-            .map(SecretKey)
-            .ok_or(DecryptError::InvalidCiphertext)
+    /// Returns a copy of the signing half of the secret key, which can sign messages but not
+    /// decrypt them.
+    #[must_use]
+    pub fn signing_key(&self) -> SigningSecretKey {
+        SigningSecretKey(
+            SignKey::from_canonical_bytes(self.0.sign_key.seed).expect("should be a valid seed"),
+        )
+    }
+
+    /// Returns a copy of the decryption half of the secret key, which can decrypt messages but not
+    /// sign them.
+    ///
+    /// The copy keeps this key's [`PublicKey`], which messages encrypted before format versions
+    /// were introduced are bound to, but storing it only stores the decryption half.
+    #[must_use]
+    pub fn decryption_key(&self) -> DecryptionSecretKey {
+        DecryptionSecretKey(
+            DecKey::from_canonical_bytes(self.0.dec_key.seed).expect("should be a valid seed"),
+            Some(self.0.pub_key.clone()),
+        )
+    }
+
+    /// Deterministically derives a labeled child secret key from this secret key.
+    ///
+    /// Child keys are unlinkable to their parent without knowledge of the parent secret key, but
+    /// can always be recovered from it. Labels are hierarchical, with segments separated by `/`:
+    /// `derive_child("work/2026")` is equivalent to `derive_child("work").derive_child("2026")`.
+    #[must_use]
+    pub fn derive_child(&self, label: &str) -> SecretKey {
+        SecretKey(self.0.derive_child(label))
+    }
+
+    /// Encodes the secret key as a checksummed mnemonic phrase of 192 words, suitable for paper
+    /// backups.
+    ///
+    /// **N.B.:** The mnemonic is not encrypted and must be handled with the same care as the
+    /// secret key itself.
+    #[must_use]
+    pub fn to_mnemonic(&self) -> String {
+        mnemonic::encode(&self.0.seed)
+    }
+
+    /// Decodes a secret key from a mnemonic phrase created by [`SecretKey::to_mnemonic`].
+    ///
+    /// Words may be separated by any whitespace and are case-insensitive.
+    ///
+    /// # Errors
+    ///
+    /// If the mnemonic has the wrong number of words, returns
+    /// [`ParseMnemonicError::InvalidLength`]. If a word is not in the word list, returns
+    /// [`ParseMnemonicError::InvalidWord`]. If the mnemonic has been altered, returns
+    /// [`ParseMnemonicError::InvalidChecksum`].
+    pub fn from_mnemonic(s: &str) -> Result<SecretKey, ParseMnemonicError> {
+        let mut seed = mnemonic::decode(s)?;
+        let sk = SecKey::from_canonical_bytes(seed).expect("should be a valid seed");
+        seed.zeroize();
+        Ok(SecretKey(sk))
+    }
+
+    /// Splits the secret key into `shares` shares using Shamir secret sharing, any `threshold` of
+    /// which can be combined to reconstruct the secret key.
+    ///
+    /// # Errors
+    ///
+    /// If `threshold` is zero or greater than `shares`, returns
+    /// [`SplitSharesError::InvalidThreshold`].
+    pub fn split(
+        &self,
+        rng: impl Rng + CryptoRng,
+        threshold: u8,
+        shares: u8,
+    ) -> Result<Vec<Share>, SplitSharesError> {
+        sss::split(rng, &self.0, threshold, shares)
+    }
+
+    /// Combines the given shares to reconstruct a secret key.
+    ///
+    /// # Errors
+    ///
+    /// If fewer than the threshold number of shares are given, returns
+    /// [`CombineSharesError::NotEnoughShares`]. If the shares are from different keys or are
+    /// duplicated, returns [`CombineSharesError::InconsistentShares`]. If any of the shares have
+    /// been modified, returns [`CombineSharesError::InvalidShares`].
+    pub fn combine(shares: &[Share]) -> Result<SecretKey, CombineSharesError> {
+        sss::combine(shares).map(SecretKey)
     }
 
     /// Encrypts the contents of the reader and write the ciphertext to the writer.
     ///
-    /// Optionally add a number of fake receivers to disguise the number of true receivers.
+    /// The given [`EncryptOptions`] control whether fake receivers are added to disguise the number
+    /// of true receivers, whether the number of receivers is padded to a bucket size, and whether
+    /// the sender is added as a receiver.
     ///
     /// Returns the number of bytes of ciphertext written to `writer`.
     ///
     /// # Errors
     ///
     /// If there is an error while reading from `reader` or writing to `writer`, an [`EncryptError`]
-    /// will be returned.
+    /// will be returned. If `options.metadata` has an invalid name or doesn't fit in a single block,
+    /// returns [`EncryptError::InvalidMetadata`]. If `options.block_len` is not between
+    /// [`MIN_BLOCK_LEN`](crate::MIN_BLOCK_LEN) and [`MAX_BLOCK_LEN`](crate::MAX_BLOCK_LEN), returns
+    /// [`EncryptError::InvalidBlockLength`].
     pub fn encrypt(
         &self,
         mut rng: impl Rng + CryptoRng,
         reader: impl Read,
         writer: impl Write,
         receivers: &[PublicKey],
-        fakes: Option<usize>,
+        options: EncryptOptions,
+    ) -> Result<u64, EncryptError> {
+        let receivers = self.receivers(&mut rng, receivers, &options);
+        message::encrypt(&mut rng, reader, writer, &self.0, &receivers, &options)
+    }
+
+    /// Encrypts the contents of `plaintext` and writes the ciphertext to the writer, like
+    /// [`SecretKey::encrypt`], but copies blocks directly from the slice instead of reading them.
+    ///
+    /// This is useful for large, memory-mapped files. The output is identical to that of
+    /// [`SecretKey::encrypt`].
+    ///
+    /// Returns the number of bytes of ciphertext written to `writer`.
+    ///
+    /// # Errors
+    ///
+    /// If there is an error while writing to `writer`, an [`EncryptError`] will be returned. If
+    /// `options.metadata` is invalid, returns [`EncryptError::InvalidMetadata`]. If
+    /// `options.block_len` is out of bounds, returns [`EncryptError::InvalidBlockLength`].
+    pub fn encrypt_slice(
+        &self,
+        mut rng: impl Rng + CryptoRng,
+        plaintext: &[u8],
+        writer: impl Write,
+        receivers: &[PublicKey],
+        options: EncryptOptions,
     ) -> Result<u64, EncryptError> {
+        let receivers = self.receivers(&mut rng, receivers, &options);
+        message::encrypt_slice(&mut rng, plaintext, writer, &self.0, &receivers, &options)
+    }
+
+    /// Encrypts the directory tree at `dir` as an archive and writes the ciphertext to the writer.
+    ///
+    /// Each file and directory is archived with its path relative to `dir`, its size, its
+    /// permissions, and its modification time. Files are read as they're encrypted, so the tree is
+    /// never buffered in memory. Anything other than regular files and directories is skipped.
+    ///
+    /// Returns the number of bytes of ciphertext written to `writer`.
+    ///
+    /// # Errors
+    ///
+    /// If there is an error while reading the directory tree or writing to `writer`, an
+    /// [`EncryptError`] will be returned. If `options.block_len` is out of bounds, returns
+    /// [`EncryptError::InvalidBlockLength`].
+    pub fn encrypt_dir(
+        &self,
+        rng: impl Rng + CryptoRng,
+        dir: impl AsRef<Path>,
+        writer: impl Write,
+        receivers: &[PublicKey],
+        options: EncryptOptions,
+    ) -> Result<u64, EncryptError> {
+        let archive = Archiver::new(dir.as_ref()).map_err(EncryptError::ReadIo)?;
+        self.encrypt(rng, archive, writer, receivers, options)
+    }
+
+    /// Returns the shuffled list of receivers, including the sender and fake receivers, as
+    /// specified by the given options.
+    fn receivers(
+        &self,
+        mut rng: impl Rng + CryptoRng,
+        receivers: &[PublicKey],
+        options: &EncryptOptions,
+    ) -> Vec<Option<PubKey>> {
+        // Add the sender as a receiver, unless they're already one.
+        let sender =
+            options.include_self.then(|| self.public_key()).filter(|pk| !receivers.contains(pk));
+
+        // Add fake receivers, then pad the receivers list to the next bucket size with more fakes.
+        let n = receivers.len() + usize::from(sender.is_some()) + options.fakes;
+        let fakes = options.fakes + options.bucket.pad(n) - n;
+
         let mut receivers = receivers
             .iter()
+            .chain(sender.as_ref())
             .map(|pk| Some(pk.0.clone()))
-            .chain(iter::repeat(None).take(fakes.unwrap_or_default()))
+            .chain(iter::repeat_n(None, fakes))
             .collect::<Vec<_>>();
 
         // Shuffle the receivers list.
         receivers.shuffle(&mut rng);
+        receivers
+    }
 
-        // Finally, encrypt.
-        message::encrypt(&mut rng, reader, writer, &self.0, &receivers)
+    /// Encrypts the contents of the reader and write the ciphertext to the writer, like
+    /// [`SecretKey::encrypt`], but reads and writes on separate threads.
+    ///
+    /// The output is identical to that of [`SecretKey::encrypt`], but throughput is higher for
+    /// large inputs because I/O and encryption happen concurrently. If encryption fails, this
+    /// returns without waiting for a blocked read of `reader` to finish.
+    ///
+    /// # Errors
+    ///
+    /// If there is an error while reading from `reader` or writing to `writer`, an [`EncryptError`]
+    /// will be returned.
+    pub fn encrypt_pipelined(
+        &self,
+        rng: impl Rng + CryptoRng,
+        reader: impl Read + Send + 'static,
+        writer: impl Write + Send,
+        receivers: &[PublicKey],
+        options: EncryptOptions,
+    ) -> Result<u64, EncryptError> {
+        let (res, written) =
+            pipeline::pipeline(reader, writer, |r, w| self.encrypt(rng, r, w, receivers, options));
+        written.map_err(EncryptError::WriteIo)?;
+        res
     }
 
     /// Decrypts the contents of `reader`, if possible, and writes the plaintext to `writer`.
@@ -127,7 +310,301 @@ impl SecretKey {
         writer: impl Write,
         sender: &PublicKey,
     ) -> Result<u64, DecryptError> {
-        message::decrypt(reader, writer, &self.0, &sender.0)
+        self.decrypt_with_limits(reader, writer, sender, &DecryptLimits::default())
+    }
+
+    /// Decrypts the contents of `reader`, if possible, and writes the plaintext to `writer`,
+    /// stopping if the ciphertext exceeds any of the given limits.
+    ///
+    /// Returns the number of bytes of plaintext written to `writer`.
+    ///
+    /// # Errors
+    ///
+    /// If the ciphertext exceeds any of the given limits, returns [`DecryptError::LimitExceeded`].
+    /// Otherwise, returns the same errors as [`SecretKey::decrypt`].
+    pub fn decrypt_with_limits(
+        &self,
+        reader: impl Read,
+        writer: impl Write,
+        sender: &PublicKey,
+        limits: &DecryptLimits,
+    ) -> Result<u64, DecryptError> {
+        self.decrypt_with_metadata(reader, writer, sender, limits).map(|(n, _)| n)
+    }
+
+    /// Decrypts the contents of `reader`, if possible, and writes the plaintext to `writer`, like
+    /// [`SecretKey::decrypt_with_limits`], returning any file metadata the sender included.
+    ///
+    /// Returns the number of bytes of plaintext written to `writer` and the file metadata, if any.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`SecretKey::decrypt_with_limits`].
+    pub fn decrypt_with_metadata(
+        &self,
+        reader: impl Read,
+        writer: impl Write,
+        sender: &PublicKey,
+        limits: &DecryptLimits,
+    ) -> Result<(u64, Option<FileMetadata>), DecryptError> {
+        message::decrypt(reader, writer, &self.0.dec_key, Some(&self.0.pub_key), &sender.0, limits)
+    }
+
+    /// Decrypts the contents of `reader`, if possible, and writes the plaintext to `writer`, like
+    /// [`SecretKey::decrypt_with_limits`], but reads and writes on separate threads.
+    ///
+    /// Returns the number of bytes of plaintext written to `writer`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`SecretKey::decrypt_with_limits`].
+    pub fn decrypt_pipelined(
+        &self,
+        reader: impl Read + Send + 'static,
+        writer: impl Write + Send,
+        sender: &PublicKey,
+        limits: &DecryptLimits,
+    ) -> Result<u64, DecryptError> {
+        decrypt_pipelined(reader, writer, &self.0.dec_key, Some(&self.0.pub_key), &sender.0, limits)
+    }
+
+    /// Decrypts the contents of `ciphertext`, if possible, and writes the plaintext to `writer`,
+    /// like [`SecretKey::decrypt_with_limits`], but reads directly from the slice.
+    ///
+    /// Returns the number of bytes of plaintext written to `writer`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`SecretKey::decrypt_with_limits`].
+    pub fn decrypt_slice(
+        &self,
+        ciphertext: &[u8],
+        writer: impl Write,
+        sender: &PublicKey,
+        limits: &DecryptLimits,
+    ) -> Result<u64, DecryptError> {
+        message::decrypt(
+            ciphertext,
+            writer,
+            &self.0.dec_key,
+            Some(&self.0.pub_key),
+            &sender.0,
+            limits,
+        )
+        .map(|(n, _)| n)
+    }
+
+    /// Decrypts an archive created by [`SecretKey::encrypt_dir`] from `reader`, if possible, and
+    /// extracts it into `dir`, which is created if it doesn't exist.
+    ///
+    /// Entries are extracted as they're decrypted, but directory permissions and modification times
+    /// are only restored once the message has been verified. If decryption fails, `dir` may contain
+    /// a partial and unauthenticated extraction, which should be discarded.
+    ///
+    /// Returns the number of bytes of archive decrypted.
+    ///
+    /// # Errors
+    ///
+    /// If `dir` exists and is not empty, or if there was an error writing the extracted files,
+    /// returns [`DecryptError::WriteIo`]. If the message is not a valid archive, returns
+    /// [`DecryptError::InvalidArchive`]. Otherwise, returns the same errors as
+    /// [`SecretKey::decrypt_with_limits`].
+    pub fn decrypt_dir(
+        &self,
+        reader: impl Read,
+        dir: impl AsRef<Path>,
+        sender: &PublicKey,
+        limits: &DecryptLimits,
+    ) -> Result<u64, DecryptError> {
+        decrypt_dir(reader, dir.as_ref(), &self.0.dec_key, Some(&self.0.pub_key), &sender.0, limits)
+    }
+
+    /// Reads the contents of the reader and returns a digital signature.
+    ///
+    /// # Errors
+    ///
+    /// If there is an error while reading from `message`, an [`io::Error`] will be returned.
+    pub fn sign(&self, rng: impl Rng + CryptoRng, message: impl Read) -> io::Result<Signature> {
+        sig::sign(rng, &self.0.sign_key, message)
+    }
+
+    /// Returns a digital signature of the contents of `message`.
+    ///
+    /// The signature is identical to one created by [`SecretKey::sign`] for the same message.
+    #[must_use]
+    pub fn sign_slice(&self, rng: impl Rng + CryptoRng, message: &[u8]) -> Signature {
+        sig::sign_slice(rng, &self.0.sign_key, message)
+    }
+
+    /// Returns a digital signature of a pre-computed digest, e.g. one calculated on another
+    /// machine.
+    ///
+    /// A signature of a digest can only be verified with [`PublicKey::verify_digest`], and is never
+    /// valid as a signature of a message.
+    #[must_use]
+    pub fn sign_digest(&self, rng: impl Rng + CryptoRng, digest: &Digest) -> Signature {
+        sig::sign_digest(rng, &self.0.sign_key, digest)
+    }
+}
+
+impl Debug for SecretKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.public_key().fmt(f)
+    }
+}
+
+/// Options for encrypting a message.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EncryptOptions {
+    /// The number of fake receivers to add to disguise the number of true receivers.
+    pub fakes: usize,
+
+    /// Whether to add the sender as a receiver, allowing them to decrypt the message later.
+    pub include_self: bool,
+
+    /// The bucket size to pad the number of receivers (including fakes) to with more fakes.
+    pub bucket: ReceiversBucket,
+
+    /// The length of each plaintext block, in bytes. Must be between
+    /// [`MIN_BLOCK_LEN`](crate::MIN_BLOCK_LEN) and [`MAX_BLOCK_LEN`](crate::MAX_BLOCK_LEN).
+    pub block_len: usize,
+
+    /// Whether to compress the plaintext before encrypting it.
+    ///
+    /// **N.B.:** If an attacker can influence part of a compressed plaintext, the length of the
+    /// ciphertext may reveal other parts of it.
+    #[cfg(feature = "compression")]
+    pub compress: bool,
+
+    /// File metadata to encrypt along with the plaintext, if any.
+    pub metadata: Option<FileMetadata>,
+}
+
+impl EncryptOptions {
+    #[cfg(feature = "compression")]
+    pub(crate) const fn compress(&self) -> bool {
+        self.compress
+    }
+
+    #[cfg(not(feature = "compression"))]
+    pub(crate) const fn compress(&self) -> bool {
+        false
+    }
+}
+
+impl Default for EncryptOptions {
+    fn default() -> Self {
+        EncryptOptions {
+            fakes: 0,
+            include_self: false,
+            bucket: ReceiversBucket::default(),
+            block_len: DEFAULT_BLOCK_LEN,
+            #[cfg(feature = "compression")]
+            compress: false,
+            metadata: None,
+        }
+    }
+}
+
+/// A policy for padding the number of receivers of a message with fake receivers, disguising the
+/// true number of receivers.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ReceiversBucket {
+    /// Don't pad the number of receivers.
+    #[default]
+    Exact,
+
+    /// Pad the number of receivers to the next power of two.
+    PowerOfTwo,
+
+    /// Pad the number of receivers to the given number of slots, or to the next multiple of it if
+    /// there are more receivers than slots.
+    Fixed(usize),
+}
+
+impl ReceiversBucket {
+    /// Returns the padded number of receivers for the given number of receivers.
+    const fn pad(self, n: usize) -> usize {
+        match self {
+            ReceiversBucket::Exact | ReceiversBucket::Fixed(0) => n,
+            ReceiversBucket::PowerOfTwo => n.next_power_of_two(),
+            ReceiversBucket::Fixed(slots) => n.next_multiple_of(slots),
+        }
+    }
+}
+
+/// Limits on the resources used to decrypt a message, protecting against hostile or garbage
+/// ciphertexts.
+///
+/// Each header requires an ML-KEM-768 decapsulation to try to decrypt, so an unlimited number of
+/// headers allows a ciphertext to consume an arbitrary amount of CPU time.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DecryptLimits {
+    /// The maximum number of headers to read, including fake receivers.
+    pub max_headers: Option<u64>,
+
+    /// The maximum total length of the ciphertext, in bytes.
+    pub max_ciphertext_len: Option<u64>,
+
+    /// The maximum length of a single plaintext block, in bytes.
+    pub max_block_len: Option<usize>,
+
+    /// The maximum total length of the plaintext, in bytes. Unlike the other limits, this bounds
+    /// the output of a compressed message, which can be much larger than its ciphertext.
+    pub max_plaintext_len: Option<u64>,
+}
+
+/// A signing-only secret key, used to sign messages.
+#[derive(PartialEq, Eq)]
+pub struct SigningSecretKey(SignKey);
+
+impl SigningSecretKey {
+    /// Creates a randomly generated signing key.
+    #[must_use]
+    pub fn random(rng: impl Rng + CryptoRng) -> SigningSecretKey {
+        SigningSecretKey(SignKey::random(rng))
+    }
+
+    /// Returns the corresponding verifying key.
+    #[must_use]
+    pub fn public_key(&self) -> VerifyingPublicKey {
+        VerifyingPublicKey(self.0.ver_key.clone(), None)
+    }
+
+    /// Encrypts the signing key with the given passphrase and `veil.pbenc` KDF and writes it to
+    /// the given writer.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error returned by operations on `writer`, or [`io::ErrorKind::InvalidInput`] if
+    /// the KDF's costs are out of bounds (see [`Kdf::is_valid`]).
+    pub fn store(
+        &self,
+        writer: impl Write,
+        rng: impl Rng + CryptoRng,
+        passphrase: &[u8],
+        kdf: Kdf,
+    ) -> io::Result<usize> {
+        store_seed(writer, rng, passphrase, kdf, &self.0.seed)
+    }
+
+    /// Loads and decrypts a signing key from the given reader with the given passphrase.
+    ///
+    /// The reader may contain either a stored signing key or a stored [`SecretKey`], in which case
+    /// its signing half is used.
+    ///
+    /// # Errors
+    ///
+    /// If the passphrase is incorrect and/or the ciphertext has been modified, a
+    /// [`DecryptError::InvalidCiphertext`] error will be returned. If the reader contains a
+    /// different type of stored key, a [`DecryptError::InvalidKeyType`] error will be returned
+    /// without decrypting it. If an error occurred while reading, a [`DecryptError::ReadIo`] error
+    /// will be returned.
+    pub fn load(reader: impl Read, passphrase: &[u8]) -> Result<SigningSecretKey, DecryptError> {
+        load_seed(reader, passphrase, &[SIGN_SK_LEN, SK_LEN], |seed| match seed.len() {
+            SIGN_SK_LEN => SignKey::from_canonical_bytes(seed).map(SigningSecretKey),
+            _ => SecKey::from_canonical_bytes(seed).map(|sk| SecretKey(sk).signing_key()),
+        })
     }
 
     /// Reads the contents of the reader and returns a digital signature.
@@ -138,12 +615,280 @@ impl SecretKey {
     pub fn sign(&self, rng: impl Rng + CryptoRng, message: impl Read) -> io::Result<Signature> {
         sig::sign(rng, &self.0, message)
     }
+
+    /// Returns a digital signature of the contents of `message`.
+    ///
+    /// The signature is identical to one created by [`SigningSecretKey::sign`] for the same
+    /// message.
+    #[must_use]
+    pub fn sign_slice(&self, rng: impl Rng + CryptoRng, message: &[u8]) -> Signature {
+        sig::sign_slice(rng, &self.0, message)
+    }
+
+    /// Returns a digital signature of a pre-computed digest, like [`SecretKey::sign_digest`].
+    #[must_use]
+    pub fn sign_digest(&self, rng: impl Rng + CryptoRng, digest: &Digest) -> Signature {
+        sig::sign_digest(rng, &self.0, digest)
+    }
+}
+
+impl Debug for SigningSecretKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.public_key().fmt(f)
+    }
+}
+
+/// A decryption-only secret key, used to decrypt messages sent to a [`PublicKey`].
+///
+/// Messages encrypted before format versions were introduced are bound to the receiver's entire
+/// [`PublicKey`], and can only be decrypted by a decryption key which came from a [`SecretKey`].
+pub struct DecryptionSecretKey(DecKey, Option<PubKey>);
+
+impl DecryptionSecretKey {
+    /// Encrypts the decryption key with the given passphrase and `veil.pbenc` KDF and writes it to
+    /// the given writer.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error returned by operations on `writer`, or [`io::ErrorKind::InvalidInput`] if
+    /// the KDF's costs are out of bounds (see [`Kdf::is_valid`]).
+    pub fn store(
+        &self,
+        writer: impl Write,
+        rng: impl Rng + CryptoRng,
+        passphrase: &[u8],
+        kdf: Kdf,
+    ) -> io::Result<usize> {
+        store_seed(writer, rng, passphrase, kdf, &self.0.seed)
+    }
+
+    /// Loads and decrypts a decryption key from the given reader with the given passphrase.
+    ///
+    /// The reader may contain either a stored decryption key or a stored [`SecretKey`], in which
+    /// case its decryption half is used, as with [`SecretKey::decryption_key`].
+    ///
+    /// # Errors
+    ///
+    /// If the passphrase is incorrect and/or the ciphertext has been modified, a
+    /// [`DecryptError::InvalidCiphertext`] error will be returned. If the reader contains a
+    /// different type of stored key, a [`DecryptError::InvalidKeyType`] error will be returned
+    /// without decrypting it. If an error occurred while reading, a [`DecryptError::ReadIo`] error
+    /// will be returned.
+    pub fn load(reader: impl Read, passphrase: &[u8]) -> Result<DecryptionSecretKey, DecryptError> {
+        load_seed(reader, passphrase, &[DEC_SK_LEN, SK_LEN], |seed| match seed.len() {
+            DEC_SK_LEN => {
+                DecKey::from_canonical_bytes(seed).map(|dk| DecryptionSecretKey(dk, None))
+            }
+            _ => SecKey::from_canonical_bytes(seed).map(|sk| SecretKey(sk).decryption_key()),
+        })
+    }
+
+    /// Decrypts the contents of `reader`, if possible, and writes the plaintext to `writer`.
+    ///
+    /// Returns the number of bytes of plaintext written to `writer`.
+    ///
+    /// # Errors
+    ///
+    /// If the ciphertext has been modified, was not sent by the sender, or was not encrypted for
+    /// this key, returns [`DecryptError::InvalidCiphertext`]. If there was an error reading from
+    /// `reader` or writing to `writer`, returns [`DecryptError::ReadIo`] or
+    /// [`DecryptError::WriteIo`].
+    pub fn decrypt(
+        &self,
+        reader: impl Read,
+        writer: impl Write,
+        sender: &PublicKey,
+    ) -> Result<u64, DecryptError> {
+        self.decrypt_with_limits(reader, writer, sender, &DecryptLimits::default())
+    }
+
+    /// Decrypts the contents of `reader`, if possible, and writes the plaintext to `writer`,
+    /// stopping if the ciphertext exceeds any of the given limits.
+    ///
+    /// Returns the number of bytes of plaintext written to `writer`.
+    ///
+    /// # Errors
+    ///
+    /// If the ciphertext exceeds any of the given limits, returns [`DecryptError::LimitExceeded`].
+    /// Otherwise, returns the same errors as [`DecryptionSecretKey::decrypt`].
+    pub fn decrypt_with_limits(
+        &self,
+        reader: impl Read,
+        writer: impl Write,
+        sender: &PublicKey,
+        limits: &DecryptLimits,
+    ) -> Result<u64, DecryptError> {
+        self.decrypt_with_metadata(reader, writer, sender, limits).map(|(n, _)| n)
+    }
+
+    /// Decrypts the contents of `reader`, if possible, and writes the plaintext to `writer`, like
+    /// [`DecryptionSecretKey::decrypt_with_limits`], returning any file metadata the sender
+    /// included.
+    ///
+    /// Returns the number of bytes of plaintext written to `writer` and the file metadata, if any.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`DecryptionSecretKey::decrypt_with_limits`].
+    pub fn decrypt_with_metadata(
+        &self,
+        reader: impl Read,
+        writer: impl Write,
+        sender: &PublicKey,
+        limits: &DecryptLimits,
+    ) -> Result<(u64, Option<FileMetadata>), DecryptError> {
+        message::decrypt(reader, writer, &self.0, self.1.as_ref(), &sender.0, limits)
+    }
+
+    /// Decrypts the contents of `reader`, if possible, and writes the plaintext to `writer`, like
+    /// [`DecryptionSecretKey::decrypt_with_limits`], but reads and writes on separate threads.
+    ///
+    /// Returns the number of bytes of plaintext written to `writer`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`DecryptionSecretKey::decrypt_with_limits`].
+    pub fn decrypt_pipelined(
+        &self,
+        reader: impl Read + Send + 'static,
+        writer: impl Write + Send,
+        sender: &PublicKey,
+        limits: &DecryptLimits,
+    ) -> Result<u64, DecryptError> {
+        decrypt_pipelined(reader, writer, &self.0, self.1.as_ref(), &sender.0, limits)
+    }
+
+    /// Decrypts the contents of `ciphertext`, if possible, and writes the plaintext to `writer`,
+    /// like [`DecryptionSecretKey::decrypt_with_limits`], but reads directly from the slice.
+    ///
+    /// Returns the number of bytes of plaintext written to `writer`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`DecryptionSecretKey::decrypt_with_limits`].
+    pub fn decrypt_slice(
+        &self,
+        ciphertext: &[u8],
+        writer: impl Write,
+        sender: &PublicKey,
+        limits: &DecryptLimits,
+    ) -> Result<u64, DecryptError> {
+        message::decrypt(ciphertext, writer, &self.0, self.1.as_ref(), &sender.0, limits)
+            .map(|(n, _)| n)
+    }
+
+    /// Decrypts an archive from `reader`, if possible, and extracts it into `dir`, like
+    /// [`SecretKey::decrypt_dir`].
+    ///
+    /// Returns the number of bytes of archive decrypted.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`SecretKey::decrypt_dir`].
+    pub fn decrypt_dir(
+        &self,
+        reader: impl Read,
+        dir: impl AsRef<Path>,
+        sender: &PublicKey,
+        limits: &DecryptLimits,
+    ) -> Result<u64, DecryptError> {
+        decrypt_dir(reader, dir.as_ref(), &self.0, self.1.as_ref(), &sender.0, limits)
+    }
+}
+
+impl PartialEq for DecryptionSecretKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl Eq for DecryptionSecretKey {}
+
+impl Debug for DecryptionSecretKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// Decrypts the contents of `reader` with reads and writes on separate threads.
+fn decrypt_pipelined(
+    reader: impl Read + Send + 'static,
+    writer: impl Write + Send,
+    receiver: &DecKey,
+    receiver_pk: Option<&PubKey>,
+    sender: &PubKey,
+    limits: &DecryptLimits,
+) -> Result<u64, DecryptError> {
+    let (res, written) = pipeline::pipeline(reader, writer, |r, w| {
+        message::decrypt(r, w, receiver, receiver_pk, sender, limits)
+    });
+    written.map_err(DecryptError::WriteIo)?;
+    res.map(|(n, _)| n)
+}
+
+/// Decrypts an archive from `reader` and extracts it into `dir`.
+fn decrypt_dir(
+    reader: impl Read,
+    dir: &Path,
+    receiver: &DecKey,
+    receiver_pk: Option<&PubKey>,
+    sender: &PubKey,
+    limits: &DecryptLimits,
+) -> Result<u64, DecryptError> {
+    let mut extractor = Extractor::new(dir).map_err(DecryptError::WriteIo)?;
+    match message::decrypt(reader, &mut extractor, receiver, receiver_pk, sender, limits) {
+        Ok((n, _)) => extractor.finish().and(Ok(n)),
+        Err(DecryptError::WriteIo(_)) if extractor.is_invalid() => {
+            Err(DecryptError::InvalidArchive)
+        }
+        Err(e) => Err(e),
+    }
 }
 
-impl Debug for SecretKey {
-    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        self.public_key().fmt(f)
+/// Encrypts the given secret key seed with the given passphrase and writes it to `writer`.
+fn store_seed(
+    mut writer: impl Write,
+    rng: impl Rng + CryptoRng,
+    passphrase: &[u8],
+    kdf: Kdf,
+    seed: &[u8],
+) -> io::Result<usize> {
+    let mut enc_key = vec![0u8; seed.len() + pbenc::OVERHEAD];
+    pbenc::encrypt(rng, passphrase, kdf, seed, &mut enc_key)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "invalid KDF parameters"))?;
+    writer.write_all(&enc_key)?;
+    Ok(enc_key.len())
+}
+
+/// Reads and decrypts a secret key seed from `reader` and decodes it with the given function.
+fn load_seed<T>(
+    mut reader: impl Read,
+    passphrase: &[u8],
+    seed_lens: &[usize],
+    decode: impl FnOnce(&[u8]) -> Option<T>,
+) -> Result<T, DecryptError> {
+    let mut b = Vec::with_capacity(SK_LEN + pbenc::OVERHEAD);
+    reader.read_to_end(&mut b).map_err(DecryptError::ReadIo)?;
+
+    // Secret keys stored in the unversioned format have a shorter header, which no current key file
+    // shares a length with.
+    let legacy = b.len() == SK_LEN + pbenc::LEGACY_OVERHEAD;
+    let seed_len = if legacy { SK_LEN } else { b.len().saturating_sub(pbenc::OVERHEAD) };
+
+    // Reject other types of keys by their length, which is public, before running the KDF.
+    if [SK_LEN, SIGN_SK_LEN, DEC_SK_LEN].contains(&seed_len) && !seed_lens.contains(&seed_len) {
+        return Err(DecryptError::InvalidKeyType);
     }
+
+    // Decrypt the ciphertext and decode the plaintext as a secret key.
+    let key = if legacy {
+        pbenc::decrypt_legacy(passphrase, &mut b)
+    } else {
+        pbenc::decrypt(passphrase, &mut b)
+    }
+    .and_then(decode);
+    b.zeroize();
+    key.ok_or(DecryptError::InvalidCiphertext)
 }
 
 /// A public key, used to verify messages.
@@ -172,7 +917,38 @@ impl PublicKey {
     /// [`VerifyError::InvalidSignature`]. If there was an error reading from `message`, returns
     /// [`VerifyError::ReadIo`].
     pub fn verify(&self, message: impl Read, sig: &Signature) -> Result<(), VerifyError> {
-        sig::verify(&self.0, message, sig)
+        sig::verify(&self.0.ver_key, Some(&self.0), message, sig)
+    }
+
+    /// Verifies that the given signature was created by the owner of this public key for the exact
+    /// contents of `message`, like [`PublicKey::verify`]. Returns `Ok(())` if successful.
+    ///
+    /// # Errors
+    ///
+    /// If the message has been modified or was not signed by the owner of this public key, returns
+    /// [`VerifyError::InvalidSignature`].
+    pub fn verify_slice(&self, message: &[u8], sig: &Signature) -> Result<(), VerifyError> {
+        sig::verify_slice(&self.0.ver_key, Some(&self.0), message, sig)
+    }
+
+    /// Verifies that the given signature was created by the owner of this public key for the given
+    /// digest with [`SecretKey::sign_digest`]. Returns `Ok(())` if successful.
+    ///
+    /// # Errors
+    ///
+    /// If the digest is different or was not signed by the owner of this public key, returns
+    /// [`VerifyError::InvalidSignature`].
+    pub fn verify_digest(&self, digest: &Digest, sig: &Signature) -> Result<(), VerifyError> {
+        sig::verify_digest(&self.0.ver_key, digest, sig)
+    }
+
+    /// Returns the verifying half of the public key, which can verify signatures.
+    ///
+    /// The verifying key keeps this public key, which signatures created before format versions
+    /// were introduced are bound to, but its encoding only includes the verifying half.
+    #[must_use]
+    pub fn verifying_key(&self) -> VerifyingPublicKey {
+        VerifyingPublicKey(self.0.ver_key.clone(), Some(self.0.clone()))
     }
 }
 
@@ -197,6 +973,98 @@ impl FromStr for PublicKey {
     }
 }
 
+/// A verifying public key, used to verify signatures.
+///
+/// Signatures created before format versions were introduced commit to the signer's entire
+/// [`PublicKey`], and can only be verified by a verifying key which came from one.
+#[derive(Clone)]
+pub struct VerifyingPublicKey(VerKey, Option<PubKey>);
+
+impl VerifyingPublicKey {
+    /// Decode a verifying key from a byte slice.
+    ///
+    /// The slice may contain either an encoded verifying key or an encoded [`PublicKey`], in which
+    /// case its verifying half is used, as with [`PublicKey::verifying_key`].
+    #[must_use]
+    pub fn decode(b: impl AsRef<[u8]>) -> Option<VerifyingPublicKey> {
+        let b = b.as_ref();
+        match b.len() {
+            PK_LEN => PublicKey::decode(b).map(|pk| pk.verifying_key()),
+            _ => VerKey::from_canonical_bytes(b).map(|vk| VerifyingPublicKey(vk, None)),
+        }
+    }
+
+    /// Encode the verifying key as a byte array.
+    #[must_use]
+    pub const fn encode(&self) -> [u8; ML_DSA_PK_LEN] {
+        self.0.encoded
+    }
+
+    /// Verifies that the given signature was created by the owner of this verifying key for the
+    /// exact contents of `message`. Returns `Ok(())` if successful.
+    ///
+    /// # Errors
+    ///
+    /// If the message has been modified or was not signed by the owner of this verifying key,
+    /// returns [`VerifyError::InvalidSignature`]. If there was an error reading from `message`,
+    /// returns [`VerifyError::ReadIo`].
+    pub fn verify(&self, message: impl Read, sig: &Signature) -> Result<(), VerifyError> {
+        sig::verify(&self.0, self.1.as_ref(), message, sig)
+    }
+
+    /// Verifies that the given signature was created by the owner of this verifying key for the
+    /// exact contents of `message`, like [`VerifyingPublicKey::verify`]. Returns `Ok(())` if
+    /// successful.
+    ///
+    /// # Errors
+    ///
+    /// If the message has been modified or was not signed by the owner of this verifying key,
+    /// returns [`VerifyError::InvalidSignature`].
+    pub fn verify_slice(&self, message: &[u8], sig: &Signature) -> Result<(), VerifyError> {
+        sig::verify_slice(&self.0, self.1.as_ref(), message, sig)
+    }
+
+    /// Verifies that the given signature was created by the owner of this verifying key for the
+    /// given digest, like [`PublicKey::verify_digest`]. Returns `Ok(())` if successful.
+    ///
+    /// # Errors
+    ///
+    /// If the digest is different or was not signed by the owner of this verifying key, returns
+    /// [`VerifyError::InvalidSignature`].
+    pub fn verify_digest(&self, digest: &Digest, sig: &Signature) -> Result<(), VerifyError> {
+        sig::verify_digest(&self.0, digest, sig)
+    }
+}
+
+impl PartialEq for VerifyingPublicKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl Eq for VerifyingPublicKey {}
+
+impl Debug for VerifyingPublicKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.to_string())
+    }
+}
+
+impl fmt::Display for VerifyingPublicKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", bs58::encode(self.encode()).into_string())
+    }
+}
+
+impl FromStr for VerifyingPublicKey {
+    type Err = ParsePublicKeyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        VerifyingPublicKey::decode(bs58::decode(s).into_vec()?.as_slice())
+            .ok_or(ParsePublicKeyError::InvalidPublicKey)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::io::Cursor;
@@ -234,8 +1102,33 @@ mod tests {
         let k = SecretKey::random(&mut rng);
 
         let mut ciphertext = Vec::new();
-        k.store(&mut ciphertext, &mut rng, b"hello world", 1, 1, 1)
-            .expect("should store successfully");
+        k.store(
+            &mut ciphertext,
+            &mut rng,
+            b"hello world",
+            Kdf::Balloon { time_cost: 1, memory_cost: 1, parallelism: 1 },
+        )
+        .expect("should store successfully");
+
+        let k_p = SecretKey::load(Cursor::new(&ciphertext), b"hello world")
+            .expect("should load successfully");
+
+        assert_eq!(k, k_p);
+    }
+
+    #[test]
+    fn secret_key_argon2id_round_trip() {
+        let mut rng = ChaChaRng::seed_from_u64(0xDEADBEEF);
+        let k = SecretKey::random(&mut rng);
+
+        let mut ciphertext = Vec::new();
+        k.store(
+            &mut ciphertext,
+            &mut rng,
+            b"hello world",
+            Kdf::Argon2id { time_cost: 0, memory_cost: 4, parallelism: 0 },
+        )
+        .expect("should store successfully");
 
         let k_p = SecretKey::load(Cursor::new(&ciphertext), b"hello world")
             .expect("should load successfully");
@@ -243,6 +1136,36 @@ mod tests {
         assert_eq!(k, k_p);
     }
 
+    #[test]
+    fn mnemonic_round_trip() {
+        let rng = ChaChaRng::seed_from_u64(0xDEADBEEF);
+        let k = SecretKey::random(rng);
+
+        let k_p = SecretKey::from_mnemonic(&k.to_mnemonic()).expect("should decode successfully");
+
+        assert_eq!(k, k_p);
+    }
+
+    #[test]
+    fn split_and_combine() {
+        let mut rng = ChaChaRng::seed_from_u64(0xDEADBEEF);
+        let k = SecretKey::random(&mut rng);
+
+        let shares = k.split(&mut rng, 3, 5).expect("should split");
+        let k_p = SecretKey::combine(&shares[1..4]).expect("should combine successfully");
+
+        assert_eq!(k.public_key(), k_p.public_key());
+    }
+
+    #[test]
+    fn split_invalid_threshold() {
+        let mut rng = ChaChaRng::seed_from_u64(0xDEADBEEF);
+        let k = SecretKey::random(&mut rng);
+
+        assert_matches!(k.split(&mut rng, 0, 5), Err(SplitSharesError::InvalidThreshold));
+        assert_matches!(k.split(&mut rng, 6, 5), Err(SplitSharesError::InvalidThreshold));
+    }
+
     #[test]
     fn round_trip() {
         let (_, a, b, plaintext, ciphertext) = setup(64);
@@ -254,6 +1177,248 @@ mod tests {
         assert_eq!(plaintext.to_vec(), dst.into_inner(), "incorrect plaintext");
     }
 
+    #[test]
+    fn pipelined() {
+        let mut rng = ChaChaRng::seed_from_u64(0xDEADBEEF);
+        let a = SecretKey::random(&mut rng);
+        let b = SecretKey::random(&mut rng);
+        let mut plaintext = vec![0u8; 1024 * 1024 + 37];
+        rng.fill_bytes(&mut plaintext);
+
+        let options = EncryptOptions { fakes: 3, ..Default::default() };
+        let mut ciphertext = Vec::new();
+        a.encrypt(
+            ChaChaRng::seed_from_u64(0xC0FFEE),
+            Cursor::new(&plaintext),
+            &mut ciphertext,
+            &[b.public_key()],
+            options.clone(),
+        )
+        .expect("encryption should be ok");
+
+        let mut ciphertext_p = Vec::new();
+        a.encrypt_pipelined(
+            ChaChaRng::seed_from_u64(0xC0FFEE),
+            Cursor::new(plaintext.clone()),
+            &mut ciphertext_p,
+            &[b.public_key()],
+            options,
+        )
+        .expect("encryption should be ok");
+        assert_eq!(ciphertext, ciphertext_p, "pipelined output should be identical");
+
+        let mut dst = Vec::new();
+        let ptx_len = b
+            .decrypt_pipelined(
+                Cursor::new(ciphertext.clone()),
+                &mut dst,
+                &a.public_key(),
+                &DecryptLimits::default(),
+            )
+            .expect("decryption should be ok");
+        assert_eq!(dst.len() as u64, ptx_len, "returned/observed plaintext length mismatch");
+        assert_eq!(plaintext, dst, "incorrect plaintext");
+
+        *ciphertext.last_mut().expect("ciphertext should not be empty") ^= 1;
+        assert_matches!(
+            b.decryption_key().decrypt_pipelined(
+                Cursor::new(ciphertext),
+                io::sink(),
+                &a.public_key(),
+                &DecryptLimits::default(),
+            ),
+            Err(DecryptError::InvalidCiphertext)
+        );
+    }
+
+    #[test]
+    fn slices() {
+        let mut rng = ChaChaRng::seed_from_u64(0xDEADBEEF);
+        let a = SecretKey::random(&mut rng);
+        let b = SecretKey::random(&mut rng);
+        let mut plaintext = vec![0u8; 200 * 1024];
+        rng.fill_bytes(&mut plaintext);
+
+        let options = EncryptOptions { fakes: 3, ..Default::default() };
+        let mut ciphertext = Vec::new();
+        a.encrypt(
+            ChaChaRng::seed_from_u64(0xC0FFEE),
+            Cursor::new(&plaintext),
+            &mut ciphertext,
+            &[b.public_key()],
+            options.clone(),
+        )
+        .expect("encryption should be ok");
+
+        let mut ciphertext_s = Vec::new();
+        a.encrypt_slice(
+            ChaChaRng::seed_from_u64(0xC0FFEE),
+            &plaintext,
+            &mut ciphertext_s,
+            &[b.public_key()],
+            options,
+        )
+        .expect("encryption should be ok");
+        assert_eq!(ciphertext, ciphertext_s, "slice output should be identical");
+
+        let mut dst = Vec::new();
+        b.decryption_key()
+            .decrypt_slice(&ciphertext, &mut dst, &a.public_key(), &DecryptLimits::default())
+            .expect("decryption should be ok");
+        assert_eq!(plaintext, dst, "incorrect plaintext");
+
+        let sig = a.signing_key().sign_slice(&mut rng, &plaintext);
+        assert_matches!(a.public_key().verify(Cursor::new(&plaintext), &sig), Ok(()));
+        assert_matches!(a.public_key().verifying_key().verify_slice(&plaintext, &sig), Ok(()));
+    }
+
+    #[test]
+    fn metadata() {
+        let mut rng = ChaChaRng::seed_from_u64(0xDEADBEEF);
+        let a = SecretKey::random(&mut rng);
+        let b = SecretKey::random(&mut rng);
+        let metadata = FileMetadata { name: "hello.txt".into(), size: 5, mode: 0o644, mtime: 7 };
+
+        let mut ciphertext = Vec::new();
+        a.encrypt(
+            &mut rng,
+            Cursor::new(b"hello"),
+            &mut ciphertext,
+            &[b.public_key()],
+            EncryptOptions { metadata: Some(metadata.clone()), ..Default::default() },
+        )
+        .expect("encryption should be ok");
+
+        let mut dst = Vec::new();
+        let (ptx_len, decrypted) = b
+            .decryption_key()
+            .decrypt_with_metadata(
+                Cursor::new(&ciphertext),
+                &mut dst,
+                &a.public_key(),
+                &DecryptLimits::default(),
+            )
+            .expect("decryption should be ok");
+        assert_eq!(5, ptx_len);
+        assert_eq!(b"hello".to_vec(), dst, "incorrect plaintext");
+        assert_eq!(Some(metadata), decrypted, "incorrect metadata");
+
+        // Decrypting without asking for metadata ignores it.
+        let mut dst = Vec::new();
+        b.decrypt(Cursor::new(&ciphertext), &mut dst, &a.public_key())
+            .expect("decryption should be ok");
+        assert_eq!(b"hello".to_vec(), dst, "incorrect plaintext");
+    }
+
+    #[test]
+    fn directories() {
+        let mut rng = ChaChaRng::seed_from_u64(0xDEADBEEF);
+        let a = SecretKey::random(&mut rng);
+        let b = SecretKey::random(&mut rng);
+
+        let src = tempfile::tempdir().expect("should create temp dir");
+        std::fs::create_dir(src.path().join("docs")).expect("should create dir");
+        std::fs::write(src.path().join("docs/plan.txt"), b"attack at dawn").expect("should write");
+
+        let mut ciphertext = Vec::new();
+        a.encrypt_dir(&mut rng, src.path(), &mut ciphertext, &[b.public_key()], Default::default())
+            .expect("encryption should be ok");
+
+        let dst = tempfile::tempdir().expect("should create temp dir");
+        let out = dst.path().join("out");
+        b.decrypt_dir(Cursor::new(&ciphertext), &out, &a.public_key(), &DecryptLimits::default())
+            .expect("decryption should be ok");
+        assert_eq!(
+            b"attack at dawn".to_vec(),
+            std::fs::read(out.join("docs/plan.txt")).expect("should read")
+        );
+
+        // A plain message isn't an archive.
+        let mut ciphertext = Vec::new();
+        a.encrypt(
+            &mut rng,
+            Cursor::new(b"hello, this is not an archive"),
+            &mut ciphertext,
+            &[b.public_key()],
+            Default::default(),
+        )
+        .expect("encryption should be ok");
+        assert_matches!(
+            b.decryption_key().decrypt_dir(
+                Cursor::new(&ciphertext),
+                dst.path().join("other"),
+                &a.public_key(),
+                &DecryptLimits::default(),
+            ),
+            Err(DecryptError::InvalidArchive)
+        );
+    }
+
+    #[test]
+    fn include_self() {
+        let mut rng = ChaChaRng::seed_from_u64(0xDEADBEEF);
+        let a = SecretKey::random(&mut rng);
+        let b = SecretKey::random(&mut rng);
+
+        let mut ciphertext = Vec::new();
+        a.encrypt(
+            &mut rng,
+            Cursor::new(b"sent items"),
+            &mut ciphertext,
+            &[b.public_key()],
+            EncryptOptions { include_self: true, ..Default::default() },
+        )
+        .expect("encryption should be ok");
+
+        for receiver in [&a, &b] {
+            let mut dst = Vec::new();
+            receiver
+                .decrypt(Cursor::new(&ciphertext), &mut dst, &a.public_key())
+                .expect("decryption should be ok");
+            assert_eq!(b"sent items".to_vec(), dst, "incorrect plaintext");
+        }
+
+        // Including the sender when they're already a receiver doesn't add another header.
+        let mut deduped = Vec::new();
+        a.encrypt(
+            &mut rng,
+            Cursor::new(b"sent items"),
+            &mut deduped,
+            &[b.public_key(), a.public_key()],
+            EncryptOptions { include_self: true, ..Default::default() },
+        )
+        .expect("encryption should be ok");
+        assert_eq!(ciphertext.len(), deduped.len(), "sender should only be added once");
+    }
+
+    #[test]
+    fn receivers_bucket() {
+        assert_eq!(3, ReceiversBucket::Exact.pad(3));
+        assert_eq!(4, ReceiversBucket::PowerOfTwo.pad(3));
+        assert_eq!(4, ReceiversBucket::PowerOfTwo.pad(4));
+        assert_eq!(32, ReceiversBucket::PowerOfTwo.pad(17));
+        assert_eq!(16, ReceiversBucket::Fixed(16).pad(3));
+        assert_eq!(32, ReceiversBucket::Fixed(16).pad(17));
+        assert_eq!(3, ReceiversBucket::Fixed(0).pad(3));
+
+        // Messages with different numbers of receivers in the same bucket are the same length.
+        let mut rng = ChaChaRng::seed_from_u64(0xDEADBEEF);
+        let a = SecretKey::random(&mut rng);
+        let lens = [1, 5, 7]
+            .into_iter()
+            .map(|n| {
+                let receivers =
+                    (0..n).map(|_| SecretKey::random(&mut rng).public_key()).collect::<Vec<_>>();
+                let options =
+                    EncryptOptions { bucket: ReceiversBucket::Fixed(8), ..Default::default() };
+                a.encrypt(&mut rng, Cursor::new(b"hello"), io::sink(), &receivers, options)
+                    .expect("encryption should be ok")
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(lens[0], lens[1], "ciphertexts should be the same length");
+        assert_eq!(lens[1], lens[2], "ciphertexts should be the same length");
+    }
+
     #[test]
     fn wrong_sender() {
         let (rng, _, b, _, ciphertext) = setup(64);
@@ -296,6 +1461,128 @@ mod tests {
         key.public_key().verify(Cursor::new(message), &sig).expect("verification should be ok");
     }
 
+    #[test]
+    fn sign_and_verify_digest() {
+        let mut rng = ChaChaRng::seed_from_u64(0xDEADBEEF);
+        let key = SecretKey::random(&mut rng);
+        let digest = Digest::from_slice(&["artifact"], &rng.gen::<[u8; 64]>());
+
+        let sig = key.sign_digest(&mut rng, &digest);
+        key.public_key().verify_digest(&digest, &sig).expect("verification should be ok");
+        key.public_key()
+            .verifying_key()
+            .verify_digest(&digest, &sig)
+            .expect("verification should be ok");
+
+        let sig = key.signing_key().sign_digest(&mut rng, &digest);
+        key.public_key().verify_digest(&digest, &sig).expect("verification should be ok");
+        assert_matches!(
+            key.public_key().verify_slice(&digest.encode(), &sig),
+            Err(VerifyError::InvalidSignature)
+        );
+    }
+
+    #[test]
+    fn signing_key() {
+        let mut rng = ChaChaRng::seed_from_u64(0xDEADBEEF);
+        let key = SecretKey::random(&mut rng);
+        let signing_key = key.signing_key();
+        assert_eq!(key.public_key().verifying_key(), signing_key.public_key());
+
+        let message = rng.gen::<[u8; 64]>();
+        let sig = signing_key.sign(&mut rng, Cursor::new(message)).expect("signing should be ok");
+
+        key.public_key().verify(Cursor::new(message), &sig).expect("verification should be ok");
+        signing_key
+            .public_key()
+            .verify(Cursor::new(message), &sig)
+            .expect("verification should be ok");
+    }
+
+    #[test]
+    fn signing_key_round_trip() {
+        let mut rng = ChaChaRng::seed_from_u64(0xDEADBEEF);
+        let kdf = Kdf::Balloon { time_cost: 1, memory_cost: 1, parallelism: 1 };
+        let k = SigningSecretKey::random(&mut rng);
+
+        let mut ciphertext = Vec::new();
+        k.store(&mut ciphertext, &mut rng, b"hello world", kdf).expect("should store successfully");
+        let k_p = SigningSecretKey::load(Cursor::new(&ciphertext), b"hello world")
+            .expect("should load successfully");
+        assert_eq!(k, k_p);
+
+        assert_matches!(
+            SigningSecretKey::load(Cursor::new(&ciphertext), b"goodbye world"),
+            Err(DecryptError::InvalidCiphertext)
+        );
+        assert_matches!(
+            SecretKey::load(Cursor::new(&ciphertext), b"hello world"),
+            Err(DecryptError::InvalidKeyType)
+        );
+        assert_matches!(
+            DecryptionSecretKey::load(Cursor::new(&ciphertext), b"hello world"),
+            Err(DecryptError::InvalidKeyType)
+        );
+
+        let combined = SecretKey::random(&mut rng);
+        let mut ciphertext = Vec::new();
+        combined
+            .store(&mut ciphertext, &mut rng, b"hello world", kdf)
+            .expect("should store successfully");
+        let k_p = SigningSecretKey::load(Cursor::new(&ciphertext), b"hello world")
+            .expect("should load successfully");
+        assert_eq!(combined.signing_key(), k_p);
+    }
+
+    #[test]
+    fn decryption_key() {
+        let (_, a, b, plaintext, ciphertext) = setup(64);
+        let mut dst = Cursor::new(Vec::new());
+        let ptx_len = b
+            .decryption_key()
+            .decrypt(Cursor::new(ciphertext), &mut dst, &a.public_key())
+            .expect("decryption should be ok");
+        assert_eq!(dst.position(), ptx_len, "returned/observed plaintext length mismatch");
+        assert_eq!(plaintext.to_vec(), dst.into_inner(), "incorrect plaintext");
+    }
+
+    #[test]
+    fn decryption_key_round_trip() {
+        let mut rng = ChaChaRng::seed_from_u64(0xDEADBEEF);
+        let kdf = Kdf::Balloon { time_cost: 1, memory_cost: 1, parallelism: 1 };
+        let combined = SecretKey::random(&mut rng);
+        let k = combined.decryption_key();
+
+        let mut ciphertext = Vec::new();
+        k.store(&mut ciphertext, &mut rng, b"hello world", kdf).expect("should store successfully");
+        let k_p = DecryptionSecretKey::load(Cursor::new(&ciphertext), b"hello world")
+            .expect("should load successfully");
+        assert_eq!(k, k_p);
+
+        assert_matches!(
+            SigningSecretKey::load(Cursor::new(&ciphertext), b"hello world"),
+            Err(DecryptError::InvalidKeyType)
+        );
+
+        let mut ciphertext = Vec::new();
+        combined
+            .store(&mut ciphertext, &mut rng, b"hello world", kdf)
+            .expect("should store successfully");
+        let k_p = DecryptionSecretKey::load(Cursor::new(&ciphertext), b"hello world")
+            .expect("should load successfully");
+        assert_eq!(k, k_p);
+    }
+
+    #[test]
+    fn verifying_key_encoding() {
+        let rng = ChaChaRng::seed_from_u64(0xDEADBEEF);
+        let pk = SecretKey::random(rng).public_key();
+        let vk = pk.verifying_key();
+
+        assert_eq!(Ok(vk.clone()), vk.to_string().parse::<VerifyingPublicKey>());
+        assert_eq!(Ok(vk), pk.to_string().parse::<VerifyingPublicKey>());
+    }
+
     fn setup(n: usize) -> (rand_chacha::ChaCha20Rng, SecretKey, SecretKey, Vec<u8>, Vec<u8>) {
         let mut rng = ChaChaRng::seed_from_u64(0xDEADBEEF);
 
@@ -313,7 +1600,7 @@ mod tests {
                 Cursor::new(&plaintext),
                 Cursor::new(&mut ciphertext),
                 &[b.public_key()],
-                Some(20),
+                EncryptOptions { fakes: 20, ..Default::default() },
             )
             .expect("encryption should be ok");
         assert_eq!(
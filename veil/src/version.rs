@@ -0,0 +1,26 @@
+//! Format versions.
+
+/// The version of the message, signature, and encrypted secret key formats produced by this
+/// implementation.
+///
+/// Versions are never encoded in plaintext. Instead, each version has its own domain separation
+/// strings, and messages also seal the version in their headers. Decryption and verification try
+/// each of the supported versions in turn.
+pub const FORMAT_VERSION: u8 = 1;
+
+/// The version of the formats produced before versions were introduced, whose protocols use
+/// unversioned domain separation strings (e.g. `veil.sig`).
+pub const UNVERSIONED: u8 = 0;
+
+/// The format versions which can be decrypted or verified, newest first.
+pub const SUPPORTED_VERSIONS: [u8; 2] = [FORMAT_VERSION, UNVERSIONED];
+
+/// Returns the domain separation string of the given protocol for the given format version, e.g.
+/// `veil.sig.v1`, or the protocol's name if the version is [`UNVERSIONED`].
+pub fn domain(name: &str, version: u8) -> String {
+    if version == UNVERSIONED {
+        name.to_string()
+    } else {
+        format!("{name}.v{version}")
+    }
+}
@@ -0,0 +1,127 @@
+//! Frozen ciphertexts, signatures, and secret keys from previous format versions, which must
+//! continue to decrypt, verify, and load.
+//!
+//! These files must never be regenerated. When a new format version is added, add a new directory
+//! of vectors for it instead.
+
+use std::io;
+
+use rand::rngs::OsRng;
+use veil::{
+    DecryptLimits, DecryptionSecretKey, FileMetadata, Kdf, PublicKey, SecretKey, Signature,
+    VerifyingPublicKey,
+};
+
+const PASSPHRASE: &[u8] = b"veil";
+
+const KDF: Kdf = Kdf::Balloon { time_cost: 0, memory_cost: 0, parallelism: 0 };
+
+#[test]
+fn v0() {
+    let sender = SecretKey::load(&include_bytes!("compat/v0/sender.key")[..], PASSPHRASE)
+        .expect("should load an unversioned secret key");
+    let receiver = SecretKey::load(&include_bytes!("compat/v0/receiver.key")[..], PASSPHRASE)
+        .expect("should load an unversioned secret key");
+    assert!(
+        SecretKey::load(&include_bytes!("compat/v0/sender.key")[..], b"not veil").is_err(),
+        "should not load an unversioned secret key with the wrong passphrase"
+    );
+    let sender_pk = include_str!("compat/v0/sender.pub")
+        .trim()
+        .parse::<PublicKey>()
+        .expect("should parse an unversioned public key");
+    assert_eq!(sender.public_key(), sender_pk);
+
+    // The message is the text repeated 200 times, so it spans several blocks.
+    let message = include_bytes!("compat/v0/message.txt").repeat(200);
+    let ciphertext = include_bytes!("compat/v0/message.veil");
+    for limits in [
+        DecryptLimits::default(),
+        DecryptLimits {
+            max_ciphertext_len: Some(ciphertext.len() as u64),
+            ..DecryptLimits::default()
+        },
+    ] {
+        let mut plaintext = Vec::new();
+        let (len, metadata) = receiver
+            .decrypt_with_metadata(&ciphertext[..], &mut plaintext, &sender_pk, &limits)
+            .expect("should decrypt an unversioned message");
+        assert_eq!(message.len() as u64, len);
+        assert_eq!(message, plaintext);
+        assert_eq!(None, metadata);
+    }
+
+    // Unversioned messages commit to the receiver's entire public key, which a decryption key only
+    // has if it came from the full secret key.
+    let dec_key =
+        DecryptionSecretKey::load(&include_bytes!("compat/v0/receiver.key")[..], PASSPHRASE)
+            .expect("should load an unversioned secret key");
+    dec_key
+        .decrypt(&ciphertext[..], io::sink(), &sender_pk)
+        .expect("should decrypt an unversioned message");
+    let mut stored = Vec::new();
+    dec_key.store(&mut stored, OsRng, PASSPHRASE, KDF).expect("should store a decryption key");
+    let dec_key =
+        DecryptionSecretKey::load(&stored[..], PASSPHRASE).expect("should load a decryption key");
+    assert!(
+        dec_key.decrypt(&ciphertext[..], io::sink(), &sender_pk).is_err(),
+        "should not decrypt an unversioned message without the receiver's public key"
+    );
+
+    // Unversioned signatures commit to the signer's entire public key.
+    let message = include_bytes!("compat/v0/message.txt");
+    let sig = include_str!("compat/v0/message.sig")
+        .trim()
+        .parse::<Signature>()
+        .expect("should parse an unversioned signature");
+    sender_pk.verify_slice(message, &sig).expect("should verify an unversioned signature");
+    sender_pk.verify(&message[..], &sig).expect("should verify an unversioned signature");
+    sender_pk
+        .verifying_key()
+        .verify_slice(message, &sig)
+        .expect("should verify an unversioned signature");
+    let ver_key = sender_pk
+        .verifying_key()
+        .to_string()
+        .parse::<VerifyingPublicKey>()
+        .expect("should parse a verifying key");
+    assert!(
+        ver_key.verify_slice(message, &sig).is_err(),
+        "should not verify an unversioned signature without the signer's public key"
+    );
+}
+
+#[test]
+fn v1() {
+    let sender = SecretKey::load(&include_bytes!("compat/v1/sender.key")[..], PASSPHRASE)
+        .expect("should load a balloon-encrypted v1 secret key");
+    let receiver = SecretKey::load(&include_bytes!("compat/v1/receiver.key")[..], PASSPHRASE)
+        .expect("should load an Argon2id-encrypted v1 secret key");
+    let message = include_bytes!("compat/v1/message.txt");
+
+    let mut plaintext = Vec::new();
+    let (len, metadata) = receiver
+        .decrypt_with_metadata(
+            &include_bytes!("compat/v1/message.veil")[..],
+            &mut plaintext,
+            &sender.public_key(),
+            &DecryptLimits::default(),
+        )
+        .expect("should decrypt a v1 message");
+    assert_eq!(message.len() as u64, len);
+    assert_eq!(&message[..], plaintext);
+    assert_eq!(
+        Some(FileMetadata {
+            name: "message.txt".into(),
+            size: message.len() as u64,
+            mode: 0o644,
+            mtime: 1_700_000_000,
+        }),
+        metadata
+    );
+
+    let sig = include_str!("compat/v1/message.sig")
+        .parse::<Signature>()
+        .expect("should parse a v1 signature");
+    sender.public_key().verify_slice(message, &sig).expect("should verify a v1 signature");
+}
@@ -1,9 +1,13 @@
-use std::io::{self, Cursor};
+use std::{
+    fmt::{Debug, Display},
+    io::{self, Cursor},
+    str::FromStr,
+};
 
 use bolero::TypeGenerator;
 use rand::SeedableRng;
 use rand_chacha::{rand_core::OsRng, ChaChaRng};
-use veil::{SecretKey, Signature};
+use veil::{Digest, EncryptOptions, PublicKey, SecretKey, Share, Signature, VerifyingPublicKey};
 
 #[test]
 fn decrypt() {
@@ -17,8 +21,14 @@ fn decrypt() {
 fn encrypt() {
     bolero::check!().with_type::<(u64, Vec<u8>)>().for_each(|(seed, data)| {
         let key = SecretKey::random(ChaChaRng::seed_from_u64(*seed));
-        key.encrypt(OsRng, Cursor::new(data), io::sink(), &[key.public_key()], None)
-            .expect("should encrypt without error");
+        key.encrypt(
+            OsRng,
+            Cursor::new(data),
+            io::sink(),
+            &[key.public_key()],
+            EncryptOptions::default(),
+        )
+        .expect("should encrypt without error");
     });
 }
 
@@ -46,3 +56,54 @@ fn verify() {
         }
     });
 }
+
+#[test]
+fn from_str() {
+    /// A string to parse: either arbitrary, or a mutation of a valid encoding.
+    #[derive(Debug, TypeGenerator)]
+    enum Input {
+        Arbitrary(String),
+        Replace { index: u16, c: char },
+        Truncate { len: u16 },
+        Append(String),
+    }
+
+    impl Input {
+        fn apply(&self, valid: &str) -> String {
+            match self {
+                Input::Arbitrary(s) => s.clone(),
+                Input::Replace { index, c } => {
+                    let mut chars = valid.chars().collect::<Vec<_>>();
+                    let i = usize::from(*index) % chars.len();
+                    chars[i] = *c;
+                    chars.into_iter().collect()
+                }
+                Input::Truncate { len } => valid[..usize::from(*len) % valid.len()].to_string(),
+                Input::Append(s) => format!("{valid}{s}"),
+            }
+        }
+    }
+
+    /// Parses the input, and checks that anything which parses also round-trips.
+    fn check<T: FromStr + Display + PartialEq + Debug>(valid: &str, input: &Input) {
+        if let Ok(v) = input.apply(valid).parse::<T>() {
+            assert_eq!(Some(&v), v.to_string().parse::<T>().ok().as_ref(), "should round-trip");
+        }
+    }
+
+    let mut rng = ChaChaRng::seed_from_u64(0xDEADBEEF);
+    let key = SecretKey::random(&mut rng);
+    let pk = key.public_key().to_string();
+    let vk = key.public_key().verifying_key().to_string();
+    let sig = key.sign_slice(&mut rng, b"message").to_string();
+    let digest = Digest::from_slice(&[b"metadata"], b"message").to_string();
+    let share = key.split(&mut rng, 2, 3).expect("should split")[0].to_string();
+
+    bolero::check!().with_type().for_each(|input: &Input| {
+        check::<PublicKey>(&pk, input);
+        check::<VerifyingPublicKey>(&vk, input);
+        check::<Signature>(&sig, input);
+        check::<Digest>(&digest, input);
+        check::<Share>(&share, input);
+    });
+}
@@ -39,11 +39,26 @@ enum Command {
         #[arg(long, default_value = "1024")]
         size: u64,
     },
+
+    /// Regenerate the known-answer test vectors in `test-vectors/`.
+    TestVectors,
+
+    /// Run statistical randomness tests on messages, signatures, and encrypted secret keys.
+    RandomnessAudit {
+        /// How many times more samples to test than `cargo test` does.
+        #[arg(long, default_value = "16")]
+        scale: usize,
+
+        /// Where to write the report.
+        #[arg(long, default_value = "target/randomness-audit.txt")]
+        report: PathBuf,
+    },
 }
 
 #[derive(Clone, Debug, ValueEnum)]
 enum BenchmarkTarget {
     Encrypt,
+    EncryptPipelined,
     Sign,
     Verify,
     Digest,
@@ -59,6 +74,8 @@ fn main() -> Result<()> {
         Command::CI => ci(&sh),
         Command::Bench { args } => bench(&sh, args),
         Command::BenchCli { target, no_stash, size } => bench_cli(&sh, target, no_stash, size),
+        Command::TestVectors => test_vectors(&sh),
+        Command::RandomnessAudit { scale, report } => randomness_audit(&sh, scale, &report),
     }
 }
 
@@ -71,6 +88,22 @@ fn ci(sh: &Shell) -> Result<()> {
     Ok(())
 }
 
+fn test_vectors(sh: &Shell) -> Result<()> {
+    cmd!(sh, "cargo test -p veil --lib vectors::").env("UPDATE_VECTORS", "1").run()?;
+
+    Ok(())
+}
+
+fn randomness_audit(sh: &Shell, scale: usize, report: &Path) -> Result<()> {
+    let report = project_root().join(report);
+    cmd!(sh, "cargo test --release -p veil --lib randomness:: -- --nocapture")
+        .env("AUDIT_SCALE", scale.to_string())
+        .env("AUDIT_REPORT", report)
+        .run()?;
+
+    Ok(())
+}
+
 #[cfg(any(target_arch = "x86_64", target_arch = "x86"))]
 const RUSTFLAGS: &str = "-C target-cpu=native";
 
@@ -117,6 +150,11 @@ fn bench_cli(sh: &Shell, target: BenchmarkTarget, no_stash: bool, size: u64) ->
             let experiment = format!("head -c {size} /dev/zero | ./target/release/veil-experiment encrypt --passphrase-fd=3 -k /tmp/secret-key-experiment -i - -o /dev/null -r /tmp/public-key-experiment --fakes 9 3< <(echo -n secret)");
             cmd!(sh, "hyperfine --warmup 10 -S /bin/bash -n control {control} -n experimental {experiment}").run()?;
         }
+        BenchmarkTarget::EncryptPipelined => {
+            let control = format!("head -c {size} /dev/zero | ./target/release/veil-control encrypt --passphrase-fd=3 -k /tmp/secret-key-control -i - -o /dev/null -r /tmp/public-key-control --fakes 9 3< <(echo -n secret)");
+            let experiment = format!("head -c {size} /dev/zero | ./target/release/veil-experiment encrypt --passphrase-fd=3 -k /tmp/secret-key-experiment -i - -o /dev/null -r /tmp/public-key-experiment --fakes 9 --pipelined 3< <(echo -n secret)");
+            cmd!(sh, "hyperfine --warmup 10 -S /bin/bash -n control {control} -n experimental {experiment}").run()?;
+        }
         BenchmarkTarget::Sign => {
             let control = format!("head -c {size} /dev/zero | ./target/release/veil-control sign --passphrase-fd=3 -k /tmp/secret-key-control -i - -o /dev/null 3< <(echo -n secret)");
             let experiment = format!("head -c {size} /dev/zero | ./target/release/veil-experiment sign --passphrase-fd=3 -k /tmp/secret-key-experiment -i - -o /dev/null 3< <(echo -n secret)");